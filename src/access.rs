@@ -0,0 +1,260 @@
+//! Database and collection access rules.
+//!
+//! A connection always allows its `database_name`. The optional `databases`
+//! list adds further databases by exact name or glob pattern (`tenant_*`).
+//! Collections are filtered by `allowed_collections` and `denied_collections`,
+//! including collections an aggregation reads through `$lookup`, `$graphLookup`
+//! and `$unionWith`.
+
+use anyhow::{Result, bail};
+use mongodb::bson::{Document, doc};
+use serde_json::Value;
+
+/// Characters MongoDB does not allow in database names
+const INVALID_DATABASE_CHARS: &[char] = &['/', '\\', '.', ' ', '"', '$', '\0'];
+
+/// Match a name against a glob pattern supporting `*` (any run) and `?` (one character)
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Iterative matcher with backtracking to the last `*`
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Whether `name` is the default database or matches one of `allowed`
+pub fn database_allowed(default: &str, allowed: &[String], name: &str) -> bool {
+    name == default || allowed.iter().any(|pattern| glob_match(pattern, name))
+}
+
+/// Resolve the database for a call: the requested one if allowed, otherwise an error.
+/// Without a request the connection's default database is used.
+pub fn resolve_database(
+    default: &str,
+    allowed: &[String],
+    requested: Option<&str>,
+) -> Result<String> {
+    let Some(name) = requested.filter(|n| !n.is_empty()) else {
+        return Ok(default.to_string());
+    };
+
+    if name.contains(INVALID_DATABASE_CHARS) {
+        bail!("Invalid database name '{name}'");
+    }
+    if !database_allowed(default, allowed, name) {
+        bail!(
+            "Database '{name}' is not allowed for this connection. \
+             Use list_databases to see the databases you can query."
+        );
+    }
+    Ok(name.to_string())
+}
+
+/// Whether a collection passes the allow and deny lists.
+/// An empty allow list allows every collection; the deny list always wins.
+pub fn collection_allowed(allowed: &[String], denied: &[String], name: &str) -> bool {
+    (allowed.is_empty() || allowed.iter().any(|pattern| glob_match(pattern, name)))
+        && !denied.iter().any(|pattern| glob_match(pattern, name))
+}
+
+/// Reject a collection the connection does not expose
+pub fn check_collection(allowed: &[String], denied: &[String], name: &str) -> Result<()> {
+    if !collection_allowed(allowed, denied, name) {
+        bail!(
+            "Collection '{name}' is not accessible on this connection. \
+             Use list_collections to see the collections you can query."
+        );
+    }
+    Ok(())
+}
+
+/// A namespace read by a pipeline stage. `database` is set for cross-database `$lookup`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct StageNamespace<'a> {
+    pub stage: &'static str,
+    pub database: Option<&'a str>,
+    pub collection: &'a str,
+}
+
+/// Collections read by `$lookup`, `$graphLookup` and `$unionWith` stages at any depth,
+/// including sub-pipelines and `$facet`
+pub fn referenced_collections(value: &Value) -> Vec<StageNamespace<'_>> {
+    let mut found = Vec::new();
+    collect_references(value, &mut found);
+    found
+}
+
+fn collect_references<'a>(value: &'a Value, found: &mut Vec<StageNamespace<'a>>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let (stage, source) = match key.as_str() {
+                    "$lookup" => ("$lookup", child.get("from")),
+                    "$graphLookup" => ("$graphLookup", child.get("from")),
+                    // `{"$unionWith": "coll"}` or `{"$unionWith": {"coll": ..., "pipeline": ...}}`
+                    "$unionWith" => ("$unionWith", Some(child.get("coll").unwrap_or(child))),
+                    _ => ("", None),
+                };
+                match source {
+                    Some(Value::String(collection)) => found.push(StageNamespace {
+                        stage,
+                        database: None,
+                        collection,
+                    }),
+                    // Cross-database `from: {db: ..., coll: ...}`
+                    Some(Value::Object(ns)) => {
+                        if let Some(collection) = ns.get("coll").and_then(Value::as_str) {
+                            found.push(StageNamespace {
+                                stage,
+                                database: ns.get("db").and_then(Value::as_str),
+                                collection,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+                collect_references(child, found);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_references(item, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `listDatabases` command listing only names the user may access
+pub fn list_databases_command() -> Document {
+    doc! { "listDatabases": 1, "nameOnly": true, "authorizedDatabases": true }
+}
+
+/// Database names from a `listDatabases` reply that the connection allows, sorted
+pub fn allowed_databases(reply: &Value, default: &str, allowed: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = reply
+        .get("databases")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|db| db.get("name").and_then(Value::as_str))
+        .filter(|name| database_allowed(default, allowed, name))
+        .map(str::to_string)
+        .collect();
+
+    // The default database may not exist yet but is always queryable
+    if !names.iter().any(|n| n == default) {
+        names.push(default.to_string());
+    }
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("tenant_*", "tenant_acme"));
+        assert!(glob_match("tenant_*", "tenant_"));
+        assert!(!glob_match("tenant_*", "tenants"));
+        assert!(glob_match("*_prod", "billing_prod"));
+        assert!(glob_match("app?", "app1"));
+        assert!(!glob_match("app?", "app12"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b*c", "axxbyy"));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exactly"));
+    }
+
+    #[test]
+    fn test_resolve_database() {
+        let allowed = vec!["tenant_*".to_string(), "reports".to_string()];
+
+        assert_eq!(resolve_database("app", &allowed, None).unwrap(), "app");
+        assert_eq!(
+            resolve_database("app", &allowed, Some("tenant_acme")).unwrap(),
+            "tenant_acme"
+        );
+        assert_eq!(
+            resolve_database("app", &allowed, Some("reports")).unwrap(),
+            "reports"
+        );
+        assert!(resolve_database("app", &allowed, Some("admin")).is_err());
+        assert!(resolve_database("app", &[], Some("tenant_acme")).is_err());
+        assert!(resolve_database("app", &["*".to_string()], Some("a.b")).is_err());
+    }
+
+    #[test]
+    fn test_collection_allowed() {
+        let allowed = vec!["orders".to_string(), "users*".to_string()];
+        let denied = vec!["users_secrets".to_string(), "audit_*".to_string()];
+
+        assert!(collection_allowed(&allowed, &denied, "orders"));
+        assert!(collection_allowed(&allowed, &denied, "users_profiles"));
+        assert!(!collection_allowed(&allowed, &denied, "users_secrets"));
+        assert!(!collection_allowed(&allowed, &denied, "payments"));
+
+        // Without an allow list only the deny list applies
+        assert!(collection_allowed(&[], &denied, "payments"));
+        assert!(!collection_allowed(&[], &denied, "audit_log"));
+    }
+
+    #[test]
+    fn test_referenced_collections() {
+        let pipeline = json!([
+            {"$lookup": {"from": "orders", "localField": "_id", "foreignField": "userId", "as": "o"}},
+            {"$unionWith": "archived_users"},
+            {"$facet": {
+                "a": [{"$graphLookup": {"from": "employees", "startWith": "$boss",
+                    "connectFromField": "boss", "connectToField": "_id", "as": "chain"}}],
+                "b": [{"$unionWith": {"coll": "audit_log", "pipeline": [
+                    {"$lookup": {"from": {"db": "other", "coll": "secrets"}, "as": "s",
+                        "pipeline": []}}
+                ]}}]
+            }}
+        ]);
+        let found = referenced_collections(&pipeline);
+        let names: Vec<_> = found.iter().map(|ns| ns.collection).collect();
+        assert_eq!(
+            names,
+            [
+                "orders",
+                "archived_users",
+                "employees",
+                "audit_log",
+                "secrets"
+            ]
+        );
+        assert_eq!(found[4].database, Some("other"));
+        assert_eq!(found[3].stage, "$unionWith");
+    }
+
+    #[test]
+    fn test_allowed_databases() {
+        let reply = json!({"databases": [
+            {"name": "admin"}, {"name": "tenant_b"}, {"name": "tenant_a"}, {"name": "local"}
+        ], "ok": 1});
+        let names = allowed_databases(&reply, "app", &["tenant_*".to_string()]);
+        assert_eq!(names, ["app", "tenant_a", "tenant_b"]);
+    }
+}
@@ -0,0 +1,345 @@
+//! Append-only audit log of executed queries.
+//!
+//! One JSON object per line, by default in `<data_dir>/audit.jsonl`, readable by
+//! its owner only.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::config::Config;
+
+/// Audit log settings (top-level `audit` section)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditConfig {
+    /// Record executed queries (default false: entries hold raw filter values)
+    #[serde(default)]
+    pub enabled: bool,
+    /// Log file path (default: `<data_dir>/audit.jsonl`)
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Let the `query_history` tool read the log (default false). The log is
+    /// shared, so every client, including every HTTP session, sees all queries.
+    #[serde(default)]
+    pub history: bool,
+}
+
+/// One executed query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub connection: String,
+    pub database: String,
+    pub collection: String,
+    pub operation: String,
+    /// Final query after placeholder substitution
+    pub query: String,
+    /// Field whose distinct values were requested (distinct only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distinct_field: Option<String>,
+    /// Projection, sort and limit as requested (find only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub projection: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_query: Option<String>,
+    /// Skip offset for get_next_page continuations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip: Option<u64>,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub documents: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Most bytes `recent` reads from the end of the log
+const MAX_TAIL_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Bytes `recent` reads at a time, walking backwards from the end
+const TAIL_CHUNK_BYTES: u64 = 64 * 1024;
+
+/// Audit log writer. Disabled logs accept and discard entries.
+/// File I/O runs on the blocking thread pool, never on async worker threads.
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    /// Whether `query_history` may read the log
+    history: bool,
+    /// Serializes appends, so concurrent entries never interleave
+    lock: Arc<Mutex<()>>,
+}
+
+impl AuditLog {
+    pub fn new(config: &AuditConfig) -> Result<Self> {
+        let path = if config.enabled {
+            Some(match &config.path {
+                Some(path) => PathBuf::from(path),
+                None => Config::data_dir()?.join("audit.jsonl"),
+            })
+        } else {
+            None
+        };
+
+        if let Some(parent) = path.as_ref().and_then(|p| p.parent())
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).context("Failed to create audit log directory")?;
+        }
+
+        Ok(Self {
+            path,
+            history: config.history,
+            lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    pub const fn is_enabled(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Whether clients may read recent entries through `query_history`
+    pub const fn history_enabled(&self) -> bool {
+        self.history && self.is_enabled()
+    }
+
+    /// Append an entry. Failures are logged rather than failing the query.
+    pub async fn record(&self, entry: &AuditEntry) {
+        if let Err(e) = self.append(entry).await {
+            tracing::error!("Failed to write audit log entry: {:#}", e);
+        }
+    }
+
+    async fn append(&self, entry: &AuditEntry) -> Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+
+        let mut line = serde_json::to_string(entry).context("Failed to serialize audit entry")?;
+        line.push('\n');
+
+        let lock = self.lock.clone();
+        tokio::task::spawn_blocking(move || {
+            let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+            append_line(&path, &line)
+        })
+        .await
+        .context("Audit log writer failed")?
+    }
+
+    /// Most recent entries, newest first, optionally for one connection.
+    /// Only the last `MAX_TAIL_BYTES` of the log are searched.
+    pub async fn recent(&self, connection: Option<&str>, limit: usize) -> Result<Vec<AuditEntry>> {
+        let Some(path) = self.path.clone() else {
+            return Ok(Vec::new());
+        };
+
+        let connection = connection.map(str::to_string);
+        tokio::task::spawn_blocking(move || read_tail(&path, connection.as_deref(), limit))
+            .await
+            .context("Audit log reader failed")?
+    }
+}
+
+/// Append one line, creating the file readable by its owner only:
+/// entries contain raw filter values
+fn append_line(path: &Path, line: &str) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+    file.write_all(line.as_bytes())
+        .context("Failed to write audit log")?;
+    Ok(())
+}
+
+/// Read entries backwards from the end of the log until `limit` match.
+/// Lines that are not entries (e.g. truncated by a crash or still being written) are skipped.
+fn read_tail(path: &Path, connection: Option<&str>, limit: usize) -> Result<Vec<AuditEntry>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to open audit log {}", path.display()));
+        }
+    };
+    let len = file.metadata().context("Failed to read audit log")?.len();
+    let floor = len.saturating_sub(MAX_TAIL_BYTES);
+
+    let mut entries = Vec::new();
+    let mut pos = len;
+    // End of a line that starts before `pos`, completed by the next chunk read
+    let mut partial = Vec::new();
+    while pos > floor && entries.len() < limit {
+        let start = pos.saturating_sub(TAIL_CHUNK_BYTES).max(floor);
+        let mut chunk = vec![0; usize::try_from(pos - start)?];
+        file.seek(SeekFrom::Start(start))
+            .and_then(|_| file.read_exact(&mut chunk))
+            .context("Failed to read audit log")?;
+        chunk.extend_from_slice(&partial);
+        pos = start;
+
+        // Unless at the start of the file, the first line continues in the previous chunk
+        let split = if pos == 0 {
+            0
+        } else {
+            chunk
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(chunk.len(), |i| i + 1)
+        };
+        for line in chunk[split..].split(|&b| b == b'\n').rev() {
+            let Ok(entry) = serde_json::from_slice::<AuditEntry>(line) else {
+                continue;
+            };
+            if connection.is_some_and(|c| c != entry.connection) {
+                continue;
+            }
+            entries.push(entry);
+            if entries.len() == limit {
+                break;
+            }
+        }
+        chunk.truncate(split);
+        partial = chunk;
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(connection: &str, query: &str) -> AuditEntry {
+        AuditEntry {
+            timestamp: Utc::now(),
+            connection: connection.to_string(),
+            database: "app".to_string(),
+            collection: "users".to_string(),
+            operation: "find".to_string(),
+            query: query.to_string(),
+            distinct_field: None,
+            projection: None,
+            sort: None,
+            limit: None,
+            saved_query: None,
+            skip: None,
+            duration_ms: 3,
+            documents: Some(1),
+            bytes: Some(42),
+            error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_append_and_recent() {
+        let path = std::env::temp_dir().join(format!(
+            "ro-mongodb-mcp-rs-audit-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let log = AuditLog::new(&AuditConfig {
+            enabled: true,
+            path: Some(path.to_string_lossy().into_owned()),
+            history: true,
+        })
+        .unwrap();
+
+        log.record(&entry("prod", "{\"a\": 1}")).await;
+        log.record(&entry("local", "{\"b\": 2}")).await;
+        log.record(&AuditEntry {
+            projection: Some("{\"name\": 1}".to_string()),
+            sort: Some("{\"name\": -1}".to_string()),
+            limit: Some(5),
+            ..entry("prod", "{\"c\": 3}")
+        })
+        .await;
+
+        let recent = log.recent(None, 2).await.unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].query, "{\"c\": 3}");
+        assert_eq!(recent[0].projection.as_deref(), Some("{\"name\": 1}"));
+        assert_eq!(recent[0].sort.as_deref(), Some("{\"name\": -1}"));
+        assert_eq!(recent[0].limit, Some(5));
+        assert_eq!(recent[1].projection, None);
+        assert_eq!(recent[1].query, "{\"b\": 2}");
+
+        let prod = log.recent(Some("prod"), 10).await.unwrap();
+        assert_eq!(prod.len(), 2);
+        assert!(prod.iter().all(|e| e.connection == "prod"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_recent_reads_across_chunks() {
+        let path = std::env::temp_dir().join(format!(
+            "ro-mongodb-mcp-rs-audit-tail-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let log = AuditLog::new(&AuditConfig {
+            enabled: true,
+            path: Some(path.to_string_lossy().into_owned()),
+            history: true,
+        })
+        .unwrap();
+
+        // Enough entries to span several chunks, plus a line cut off by a crash
+        log.record(&entry("rare", "{\"first\": true}")).await;
+        let padding = "x".repeat(500);
+        for i in 0..400 {
+            log.record(&entry(
+                "prod",
+                &format!("{{\"i\": {i}, \"p\": \"{padding}\"}}"),
+            ))
+            .await;
+        }
+        append_line(&path, "{\"timestamp\": \"2024").unwrap();
+
+        let recent = log.recent(None, 3).await.unwrap();
+        assert_eq!(recent.len(), 3);
+        assert!(recent[0].query.starts_with("{\"i\": 399,"));
+        assert!(recent[2].query.starts_with("{\"i\": 397,"));
+
+        let rare = log.recent(Some("rare"), 10).await.unwrap();
+        assert_eq!(rare.len(), 1);
+        assert_eq!(rare[0].query, "{\"first\": true}");
+
+        let all = log.recent(None, 1000).await.unwrap();
+        assert_eq!(all.len(), 401);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disabled_audit_log() {
+        let log = AuditLog::new(&AuditConfig::default()).unwrap();
+        assert!(!log.is_enabled());
+        assert!(!log.history_enabled());
+        log.record(&entry("prod", "{}")).await;
+        assert!(log.recent(None, 10).await.unwrap().is_empty());
+    }
+}
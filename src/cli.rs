@@ -0,0 +1,638 @@
+//! Command line subcommands for running queries and managing saved queries
+//! without an MCP client.
+//!
+//! Queries go through the same connection registry, collection rules and redaction
+//! as the MCP tools, so a command returns exactly what the matching tool call did,
+//! and are recorded in the same audit log.
+
+use anyhow::{Context, Result, anyhow};
+use clap::{Args, Subcommand, ValueEnum};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::audit::{AuditEntry, AuditLog};
+use crate::config::Config;
+use crate::connection::{ConnectionRegistry, MongoConnection};
+use crate::mcp;
+use crate::mongodb::{QueryOperation, QueryOptions};
+use crate::read_preference::ReadPreferenceMode;
+use crate::reload::RegistryBuilder;
+use crate::results::QueryResult;
+use crate::saved_queries::{SavedQueries, SavedQuery};
+
+/// Widest column in table output; longer values are cut off
+const MAX_COLUMN_WIDTH: usize = 40;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Pretty-printed JSON, as the MCP tools return it
+    #[default]
+    Json,
+    /// Aligned columns, one row per document
+    Table,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run a read-only query, like the query_mongodb tool
+    Query(QueryArgs),
+    /// List a connection's collections and views, like the list_collections tool
+    Collections {
+        /// Connection name from config.yaml
+        connection: String,
+        /// Database instead of the connection's default
+        #[arg(long)]
+        database: Option<String>,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// List, show, run, delete, export or import saved queries
+    #[command(subcommand)]
+    Saved(SavedCommand),
+    /// Parse the config files and validate every connection without connecting
+    ValidateConfig,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SavedCommand {
+    /// List a connection's saved queries
+    List {
+        connection: String,
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Print one saved query
+    Show { connection: String, name: String },
+    /// Run a saved query, like the run_saved_query tool
+    Run {
+        connection: String,
+        name: String,
+        /// Value for a {{placeholder}}, as NAME=VALUE (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_variable)]
+        variables: Vec<(String, String)>,
+        #[command(flatten)]
+        overrides: QueryOverrides,
+    },
+    /// Delete a saved query
+    Delete { connection: String, name: String },
+    /// Print a connection's saved queries as YAML, for import elsewhere
+    Export {
+        connection: String,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+    /// Add saved queries from an export, replacing queries with the same name
+    Import {
+        connection: String,
+        /// File written by `saved export`
+        file: String,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct QueryArgs {
+    /// Connection name from config.yaml
+    connection: String,
+    /// Collection name (case-sensitive)
+    collection: String,
+    /// Filter (find, countDocuments, distinct) or pipeline (aggregate) as JSON; `-` reads stdin
+    query: String,
+    /// find, aggregate, countDocuments or distinct
+    #[arg(long, short, default_value = "find", value_parser = parse_operation)]
+    operation: QueryOperation,
+    /// Read preference for this query only, e.g. secondaryPreferred
+    #[arg(long, value_parser = parse_read_preference)]
+    read_preference: Option<ReadPreferenceMode>,
+    #[command(flatten)]
+    overrides: QueryOverrides,
+}
+
+/// Options shared by `query` and `saved run`, named after the tool parameters
+#[derive(Debug, Args)]
+pub struct QueryOverrides {
+    /// Database instead of the connection's default
+    #[arg(long)]
+    database: Option<String>,
+    /// (find only) Maximum number of documents
+    #[arg(long)]
+    limit: Option<u32>,
+    /// (find only) Sort order as JSON, e.g. '{"createdAt": -1}'
+    #[arg(long)]
+    sort: Option<String>,
+    /// (find only) Fields to include or exclude as JSON
+    #[arg(long)]
+    projection: Option<String>,
+    /// (distinct only) Field to get unique values from
+    #[arg(long)]
+    distinct_field: Option<String>,
+    /// Results to skip, to continue a truncated result
+    #[arg(long)]
+    skip: Option<u64>,
+    /// Time limit in seconds (default: the connection's default_timeout_secs)
+    #[arg(long)]
+    timeout_secs: Option<u64>,
+    #[arg(long, value_enum, default_value_t)]
+    format: OutputFormat,
+}
+
+fn parse_operation(value: &str) -> Result<QueryOperation, String> {
+    QueryOperation::from_str(value).map_err(|e| e.to_string())
+}
+
+fn parse_read_preference(value: &str) -> Result<ReadPreferenceMode, String> {
+    serde_json::from_value(Value::String(value.to_string())).map_err(|_| {
+        format!(
+            "Invalid read preference '{value}'. Must be one of: primary, primaryPreferred, \
+             secondary, secondaryPreferred, nearest"
+        )
+    })
+}
+
+fn parse_variable(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("Expected NAME=VALUE, got '{value}'"))
+}
+
+/// Run a subcommand, printing its output to stdout
+pub async fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Query(args) => query(args).await,
+        Command::Collections {
+            connection,
+            database,
+            format,
+        } => collections(&connection, database.as_deref(), format).await,
+        Command::Saved(command) => saved(command).await,
+        Command::ValidateConfig => validate_config(),
+    }
+}
+
+/// Load the config and build the registry. Connections connect lazily, so this is cheap.
+async fn load() -> Result<(Config, ConnectionRegistry)> {
+    let config = Config::load()?;
+    let (registry, _) = RegistryBuilder::new().build(&config).await?;
+    Ok((config, registry))
+}
+
+async fn registry() -> Result<ConnectionRegistry> {
+    Ok(load().await?.1)
+}
+
+fn connection(registry: &ConnectionRegistry, name: &str) -> Result<Arc<dyn MongoConnection>> {
+    registry.get(name).ok_or_else(|| {
+        anyhow!(
+            "Connection '{name}' not found. Available: {}",
+            registry.list_names().join(", ")
+        )
+    })
+}
+
+async fn query(args: QueryArgs) -> Result<()> {
+    let (config, registry) = load().await?;
+    let connection = connection(&registry, &args.connection)?;
+    let audit = AuditLog::new(&config.audit)?;
+
+    let query = if args.query == "-" {
+        let mut query = String::new();
+        std::io::stdin()
+            .read_to_string(&mut query)
+            .context("Failed to read the query from stdin")?;
+        query
+    } else {
+        args.query
+    };
+
+    if let Some(mode) = args.read_preference {
+        connection.check_read_preference(mode)?;
+    }
+    let read = args
+        .read_preference
+        .map(|mode| connection.settings().read.with_mode(mode));
+    run_query(
+        &*connection,
+        &audit,
+        &args.collection,
+        &args.operation,
+        &query,
+        args.overrides,
+        None,
+        read,
+    )
+    .await
+}
+
+/// Run a query with the options a tool call would use, record it in the audit
+/// log and print the result
+#[allow(clippy::too_many_arguments)]
+async fn run_query(
+    connection: &dyn MongoConnection,
+    audit: &AuditLog,
+    collection: &str,
+    operation: &QueryOperation,
+    query: &str,
+    overrides: QueryOverrides,
+    saved: Option<&SavedQuery>,
+    read: Option<crate::read_preference::ReadSettings>,
+) -> Result<()> {
+    let settings = connection.settings();
+    let database = connection.resolve_database(overrides.database.as_deref())?;
+    let timeout_secs = settings.timeout_secs(overrides.timeout_secs)?;
+
+    if !matches!(operation, QueryOperation::Find)
+        && (overrides.limit.is_some() || overrides.sort.is_some() || overrides.projection.is_some())
+    {
+        eprintln!("Note: limit/sort/projection only apply to find operations (ignored)");
+    }
+
+    let options = QueryOptions {
+        limit: overrides.limit,
+        sort: overrides.sort,
+        projection: overrides.projection,
+        distinct_field: overrides
+            .distinct_field
+            .or_else(|| saved.and_then(|saved| saved.distinct_field.clone())),
+        ejson_mode: settings.extended_json,
+        skip: overrides.skip,
+        limits: Some(settings.result_limits()),
+        max_time_ms: None,
+        read,
+    };
+
+    let started = Instant::now();
+    let result = mcp::execute_query(
+        connection,
+        &database,
+        collection,
+        operation,
+        query,
+        &options,
+        timeout_secs,
+    )
+    .await;
+
+    audit
+        .record(&AuditEntry {
+            timestamp: chrono::Utc::now(),
+            connection: connection.name().to_string(),
+            database,
+            collection: collection.to_string(),
+            operation: operation.as_str().to_string(),
+            query: query.to_string(),
+            distinct_field: options.distinct_field.clone(),
+            projection: options.projection.clone(),
+            sort: options.sort.clone(),
+            limit: options.limit,
+            saved_query: saved.map(|saved| saved.name.clone()),
+            skip: options.skip,
+            duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            documents: result.as_ref().ok().map(QueryResult::len),
+            bytes: result
+                .as_ref()
+                .ok()
+                .and_then(|result| result.to_json_string().ok())
+                .map(|text| text.len()),
+            error: result.as_ref().err().map(|e| e.message.to_string()),
+        })
+        .await;
+
+    let result = result.map_err(|e| anyhow!(e.message))?;
+
+    print_result(&result, overrides.format)?;
+
+    if result.is_truncated() {
+        let skip = overrides.skip.unwrap_or(0) + result.len() as u64;
+        eprintln!(
+            "Result truncated by the connection's max_documents/max_result_bytes. \
+             Continue with --skip {skip}"
+        );
+    }
+    Ok(())
+}
+
+fn print_result(result: &QueryResult, format: OutputFormat) -> Result<()> {
+    match (result, format) {
+        (QueryResult::Count(count), _) => println!("{count}"),
+        (QueryResult::Documents { items, .. }, OutputFormat::Json) => {
+            println!("{}", serde_json::to_string_pretty(items)?);
+        }
+        (QueryResult::Documents { items, .. }, OutputFormat::Table) => {
+            print!("{}", documents_table(items));
+        }
+    }
+    Ok(())
+}
+
+async fn collections(
+    connection_name: &str,
+    database: Option<&str>,
+    format: OutputFormat,
+) -> Result<()> {
+    let registry = registry().await?;
+    let connection = connection(&registry, connection_name)?;
+    let database = connection.resolve_database(database)?;
+    let timeout_secs = connection.settings().timeout_secs(None)?;
+
+    let collections = connection.list_collections(&database, timeout_secs).await?;
+    let collections = mcp::accessible_collections(connection.settings(), collections);
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&collections)?),
+        OutputFormat::Table => {
+            let rows = collections
+                .iter()
+                .map(|c| {
+                    let kind = serde_json::to_value(c.kind)?;
+                    Ok(vec![
+                        c.name.clone(),
+                        cell(&kind),
+                        c.view_on.clone().unwrap_or_default(),
+                    ])
+                })
+                .collect::<Result<Vec<_>>>()?;
+            print!("{}", table(&["name", "type", "view_on"], rows));
+        }
+    }
+    Ok(())
+}
+
+async fn saved(command: SavedCommand) -> Result<()> {
+    match command {
+        SavedCommand::List { connection, format } => {
+            let queries = load_saved(&connection).await?;
+            match format {
+                OutputFormat::Json => {
+                    println!("{}", serde_json::to_string_pretty(&queries.queries)?);
+                }
+                OutputFormat::Table => {
+                    let rows = queries
+                        .queries
+                        .iter()
+                        .map(|q| {
+                            vec![
+                                q.name.clone(),
+                                q.collection.clone(),
+                                q.operation.clone(),
+                                q.description.clone(),
+                            ]
+                        })
+                        .collect();
+                    print!(
+                        "{}",
+                        table(&["name", "collection", "operation", "description"], rows)
+                    );
+                }
+            }
+        }
+        SavedCommand::Show { connection, name } => {
+            let queries = load_saved(&connection).await?;
+            let query = queries
+                .get_query(&name)
+                .ok_or_else(|| not_found(&name, &connection))?;
+            println!("{}", serde_json::to_string_pretty(query)?);
+        }
+        SavedCommand::Run {
+            connection: connection_name,
+            name,
+            variables,
+            overrides,
+        } => {
+            let (config, registry) = load().await?;
+            let connection = connection(&registry, &connection_name)?;
+            let audit = AuditLog::new(&config.audit)?;
+            let queries = SavedQueries::load(&connection_name)?;
+            let saved = queries
+                .get_query(&name)
+                .ok_or_else(|| not_found(&name, &connection_name))?;
+
+            let variables: HashMap<String, String> = variables.into_iter().collect();
+            let query =
+                mcp::substitute_placeholders(&saved.query, &variables).map_err(|missing| {
+                    anyhow!(
+                        "Missing required variables for query '{name}': {} (pass --var NAME=VALUE)",
+                        missing.join(", ")
+                    )
+                })?;
+            let operation = QueryOperation::from_str(&saved.operation)?;
+
+            run_query(
+                &*connection,
+                &audit,
+                &saved.collection,
+                &operation,
+                &query,
+                overrides,
+                Some(saved),
+                None,
+            )
+            .await?;
+        }
+        SavedCommand::Delete { connection, name } => {
+            let mut queries = load_saved(&connection).await?;
+            if !queries.delete_query(&name) {
+                return Err(not_found(&name, &connection));
+            }
+            queries.save(&connection)?;
+            eprintln!("Query '{name}' deleted from connection '{connection}'");
+        }
+        SavedCommand::Export { connection, output } => {
+            let queries = load_saved(&connection).await?;
+            let yaml =
+                serde_yaml::to_string(&queries).context("Failed to serialize saved queries")?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, yaml)
+                        .with_context(|| format!("Failed to write {path}"))?;
+                    eprintln!(
+                        "Exported {} queries from connection '{connection}' to {path}",
+                        queries.queries.len()
+                    );
+                }
+                None => print!("{yaml}"),
+            }
+        }
+        SavedCommand::Import { connection, file } => {
+            let content =
+                std::fs::read_to_string(&file).with_context(|| format!("Failed to read {file}"))?;
+            let imported: SavedQueries = serde_yaml::from_str(&content)
+                .with_context(|| format!("Failed to parse saved queries from {file}"))?;
+
+            // Same checks as save_query, so imports can't store denied collections
+            let registry = registry().await?;
+            let target = self::connection(&registry, &connection)?;
+            for query in &imported.queries {
+                QueryOperation::from_str(&query.operation)
+                    .and_then(|_| {
+                        mcp::check_collection_access(
+                            &*target,
+                            &query.collection,
+                            Some(&query.query),
+                        )
+                    })
+                    .with_context(|| format!("Invalid saved query '{}'", query.name))?;
+            }
+
+            let mut queries = SavedQueries::load(&connection)?;
+            let (added, replaced) = queries.merge(imported);
+            queries.save(&connection)?;
+            eprintln!(
+                "Imported into connection '{connection}': {added} added, {replaced} replaced"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Saved queries of a configured connection
+async fn load_saved(connection_name: &str) -> Result<SavedQueries> {
+    // Same check as the tools: queries only exist for configured connections
+    connection(&registry().await?, connection_name)?;
+    SavedQueries::load(connection_name)
+}
+
+fn not_found(name: &str, connection: &str) -> anyhow::Error {
+    anyhow!("Query '{name}' not found in connection '{connection}'")
+}
+
+fn validate_config() -> Result<()> {
+    let config = Config::load()?;
+    config.validate_connections()?;
+    if config.http.enabled {
+        config.http.validate()?;
+    }
+    for source in &config.sources {
+        println!("Loaded {}", source.display());
+    }
+    println!(
+        "Configuration is valid: {} K8s namespaces, {} direct connections, {} SSH connections",
+        config.namespaces.len(),
+        config.connections.len(),
+        config.ssh_connections.len()
+    );
+    Ok(())
+}
+
+/// One row per document; columns are the top-level fields in order of appearance
+fn documents_table(items: &[Value]) -> String {
+    let mut columns: Vec<String> = Vec::new();
+    for item in items {
+        match item {
+            Value::Object(fields) => {
+                for key in fields.keys() {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+            }
+            // distinct values
+            _ if columns.is_empty() => columns.push("value".to_string()),
+            _ => {}
+        }
+    }
+
+    let rows = items
+        .iter()
+        .map(|item| match item {
+            Value::Object(fields) => columns
+                .iter()
+                .map(|column| fields.get(column).map(cell).unwrap_or_default())
+                .collect(),
+            value => vec![cell(value)],
+        })
+        .collect();
+    let headers: Vec<&str> = columns.iter().map(String::as_str).collect();
+    table(&headers, rows)
+}
+
+/// Strings as they are, anything else as compact JSON
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let clip = |value: &str| -> String {
+        let value = value.replace(['\n', '\t'], " ");
+        if value.chars().count() > MAX_COLUMN_WIDTH {
+            let mut clipped: String = value.chars().take(MAX_COLUMN_WIDTH - 1).collect();
+            clipped.push('…');
+            clipped
+        } else {
+            value
+        }
+    };
+    let rows: Vec<Vec<String>> = rows
+        .into_iter()
+        .map(|row| row.iter().map(|value| clip(value)).collect())
+        .collect();
+
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in &rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    let mut push_row = |values: &mut dyn Iterator<Item = &str>| {
+        let line: Vec<String> = values
+            .zip(&widths)
+            .map(|(value, width)| format!("{value:<width$}"))
+            .collect();
+        out.push_str(line.join("  ").trim_end());
+        out.push('\n');
+    };
+    push_row(&mut headers.iter().copied());
+    for row in &rows {
+        push_row(&mut row.iter().map(String::as_str));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_documents_table() {
+        let items = vec![
+            json!({ "_id": 1, "name": "Ada", "tags": ["a", "b"] }),
+            json!({ "_id": 2, "email": "bob@example.com" }),
+        ];
+        assert_eq!(
+            documents_table(&items),
+            "_id  name  tags       email\n\
+             1    Ada   [\"a\",\"b\"]\n\
+             2                     bob@example.com\n"
+        );
+
+        // distinct values
+        assert_eq!(
+            documents_table(&[json!("DE"), json!("FR")]),
+            "value\nDE\nFR\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        assert_eq!(
+            parse_variable("since=2024-01-01").unwrap(),
+            ("since".to_string(), "2024-01-01".to_string())
+        );
+        assert!(parse_variable("since").is_err());
+        assert_eq!(
+            parse_read_preference("secondaryPreferred").unwrap(),
+            ReadPreferenceMode::SecondaryPreferred
+        );
+        assert!(parse_read_preference("secondary_preferred").is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! Collection descriptions shared by both backends.
+//!
+//! `listCollections` (native driver) and `getCollectionInfos()` (mongosh) return
+//! the same documents; they are normalised here into one shape.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::access;
+
+/// Kind of namespace returned by `listCollections`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollectionKind {
+    Collection,
+    View,
+    Timeseries,
+}
+
+/// A collection, view or time-series collection as reported to tools
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CollectionInfo {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: CollectionKind,
+    /// Source collection of a view
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_on: Option<String>,
+    /// Pipeline of a view
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<Value>,
+    /// Validator (usually `{"$jsonSchema": {...}}`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validator: Option<Value>,
+    /// Time-series options (timeField, metaField, granularity)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeseries: Option<Value>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub capped: bool,
+    /// Maximum size in bytes of a capped collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capped_size: Option<i64>,
+    /// Maximum document count of a capped collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capped_max: Option<i64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub clustered: bool,
+}
+
+impl CollectionInfo {
+    /// Build from a `listCollections` entry (relaxed Extended JSON)
+    pub fn from_spec(spec: &Value) -> Result<Self> {
+        let name = spec
+            .get("name")
+            .and_then(Value::as_str)
+            .context("Collection specification has no name")?
+            .to_string();
+
+        let kind = match spec.get("type").and_then(Value::as_str) {
+            Some("view") => CollectionKind::View,
+            Some("timeseries") => CollectionKind::Timeseries,
+            _ => CollectionKind::Collection,
+        };
+
+        let options = spec.get("options");
+        let option = |key: &str| options.and_then(|o| o.get(key)).cloned();
+        let int_option = |key: &str| {
+            option(key).and_then(|v| v.as_i64().or_else(|| v.as_f64().map(|f| f as i64)))
+        };
+
+        Ok(Self {
+            name,
+            kind,
+            view_on: option("viewOn").and_then(|v| v.as_str().map(str::to_string)),
+            pipeline: option("pipeline"),
+            validator: option("validator"),
+            timeseries: option("timeseries"),
+            capped: option("capped").and_then(|v| v.as_bool()).unwrap_or(false),
+            capped_size: int_option("size"),
+            capped_max: int_option("max"),
+            clustered: option("clusteredIndex").is_some_and(|v| v != false),
+        })
+    }
+}
+
+/// Normalise `listCollections` entries, sorted by name.
+/// Time-series bucket collections are skipped; the time-series entry describes them.
+pub fn from_specs(specs: &[Value]) -> Result<Vec<CollectionInfo>> {
+    let mut collections = specs
+        .iter()
+        .map(CollectionInfo::from_spec)
+        .filter(|c| {
+            !c.as_ref()
+                .is_ok_and(|c| c.name.starts_with("system.buckets."))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Sort for deterministic output
+    collections.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(collections)
+}
+
+/// Collections a view reads: its `viewOn` and what its pipeline looks up, and in
+/// turn what those read when they are views. Empty if `name` is not a view.
+pub fn view_sources<'a>(collections: &'a [CollectionInfo], name: &str) -> Vec<&'a str> {
+    let mut sources: Vec<&str> = Vec::new();
+    let mut pending = vec![name];
+    while let Some(name) = pending.pop() {
+        let Some(view) = collections
+            .iter()
+            .find(|c| c.name == name && c.kind == CollectionKind::View)
+        else {
+            continue;
+        };
+        let read = view.view_on.as_deref().into_iter().chain(
+            view.pipeline
+                .iter()
+                .flat_map(access::referenced_collections)
+                .map(|ns| ns.collection),
+        );
+        for source in read {
+            // Views can't form cycles, but a source may be read more than once
+            if !sources.contains(&source) {
+                sources.push(source);
+                pending.push(source);
+            }
+        }
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_collection_kinds() {
+        let specs = vec![
+            json!({"name": "users", "type": "collection", "options": {
+                "validator": {"$jsonSchema": {"required": ["email"]}}
+            }}),
+            json!({"name": "active_users", "type": "view", "options": {
+                "viewOn": "users",
+                "pipeline": [{"$match": {"active": true}}]
+            }}),
+            json!({"name": "metrics", "type": "timeseries", "options": {
+                "timeseries": {"timeField": "ts", "metaField": "host", "granularity": "seconds"}
+            }}),
+            json!({"name": "system.buckets.metrics", "type": "collection", "options": {}}),
+        ];
+        let collections = from_specs(&specs).unwrap();
+
+        let names: Vec<_> = collections.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["active_users", "metrics", "users"]);
+
+        assert_eq!(collections[0].kind, CollectionKind::View);
+        assert_eq!(collections[0].view_on.as_deref(), Some("users"));
+        assert_eq!(collections[1].kind, CollectionKind::Timeseries);
+        assert_eq!(
+            collections[1].timeseries.as_ref().unwrap()["timeField"],
+            "ts"
+        );
+        assert!(collections[2].validator.is_some());
+    }
+
+    #[test]
+    fn test_view_sources() {
+        let specs = vec![
+            json!({"name": "users", "type": "collection", "options": {}}),
+            json!({"name": "secrets", "type": "collection", "options": {}}),
+            json!({"name": "active_users", "type": "view", "options": {
+                "viewOn": "users",
+                "pipeline": [{"$lookup": {"from": "secrets", "localField": "_id", "foreignField": "user", "as": "s"}}]
+            }}),
+            json!({"name": "recent_active", "type": "view", "options": {
+                "viewOn": "active_users",
+                "pipeline": [{"$match": {"recent": true}}]
+            }}),
+        ];
+        let collections = from_specs(&specs).unwrap();
+
+        assert_eq!(
+            view_sources(&collections, "recent_active"),
+            ["active_users", "users", "secrets"]
+        );
+        assert_eq!(
+            view_sources(&collections, "active_users"),
+            ["users", "secrets"]
+        );
+        assert!(view_sources(&collections, "users").is_empty());
+        assert!(view_sources(&collections, "missing").is_empty());
+    }
+
+    #[test]
+    fn test_capped_and_clustered() {
+        let spec = json!({"name": "log", "type": "collection", "options": {
+            "capped": true, "size": 1048576, "max": 1000.0,
+            "clusteredIndex": {"key": {"_id": 1}, "unique": true}
+        }});
+        let info = CollectionInfo::from_spec(&spec).unwrap();
+        assert!(info.capped);
+        assert_eq!(info.capped_size, Some(1048576));
+        assert_eq!(info.capped_max, Some(1000));
+        assert!(info.clustered);
+
+        // Unset flags and options are left out of the tool output
+        let plain = CollectionInfo::from_spec(&json!({"name": "users"})).unwrap();
+        assert_eq!(
+            serde_json::to_value(&plain).unwrap(),
+            json!({"name": "users", "type": "collection"})
+        );
+    }
+}
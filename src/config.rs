@@ -3,6 +3,18 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::access;
+use crate::audit::AuditConfig;
+use crate::credentials::{AuthMechanismSetting, DirectCredentials, K8sCredentials};
+use crate::ejson::ExtJsonMode;
+use crate::http::HttpConfig;
+use crate::read_preference::{ReadPreferenceMode, ReadSettings};
+use crate::redaction::Redaction;
+use crate::results::ResultLimits;
+use crate::ssh_tunnel::SshTunnelConfig;
+use crate::tls::TlsConfig;
 
 /// Expand environment variables and tilde in a path string.
 /// Supports: $HOME, ${VAR}, ~/path
@@ -15,13 +27,259 @@ fn expand_path(path: &str) -> String {
         })
 }
 
+/// Environment variable listing config files to load, separated like `PATH`
+pub const CONFIG_FILES_ENV: &str = "RO_MONGODB_MCP_CONFIG";
+
+/// Config files given with `--config`, which take precedence over the environment
+static CONFIG_FILES: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+const fn default_max_documents() -> usize {
+    1000
+}
+
+const fn default_max_result_bytes() -> usize {
+    512 * 1024
+}
+
+const fn default_timeout_secs() -> u64 {
+    30
+}
+
+const fn default_max_timeout_secs() -> u64 {
+    300
+}
+
+/// Query behaviour shared by all connection types.
+/// Flattened into each connection entry, so fields sit next to `database_name`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuerySettings {
+    /// Extended JSON mode for results: relaxed (default) or canonical
+    #[serde(default)]
+    pub extended_json: ExtJsonMode,
+    /// Maximum documents returned per call before the result is truncated
+    #[serde(default = "default_max_documents")]
+    pub max_documents: usize,
+    /// Maximum serialized result size per call, in bytes
+    #[serde(default = "default_max_result_bytes")]
+    pub max_result_bytes: usize,
+    /// Additional databases tools may select with `database_name`:
+    /// exact names or glob patterns such as `tenant_*`
+    #[serde(default)]
+    pub databases: Vec<String>,
+    /// Fields masked, hashed or dropped from every result
+    #[serde(default)]
+    pub redaction: Redaction,
+    /// Environment variable holding the HMAC key for `action: hash` rules
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction_hash_key_env: Option<String>,
+    /// Collections tools may access (names or glob patterns). Empty allows all.
+    #[serde(default)]
+    pub allowed_collections: Vec<String>,
+    /// Collections tools may never access, even when allowed (names or glob patterns)
+    #[serde(default)]
+    pub denied_collections: Vec<String>,
+    /// Time limit for queries and commands without a `timeout_secs` parameter
+    #[serde(default = "default_timeout_secs")]
+    pub default_timeout_secs: u64,
+    /// Largest `timeout_secs` a tool call may request
+    #[serde(default = "default_max_timeout_secs")]
+    pub max_timeout_secs: u64,
+    /// Read preference and read concern
+    #[serde(flatten)]
+    pub read: ReadSettings,
+}
+
+impl Default for QuerySettings {
+    fn default() -> Self {
+        Self {
+            extended_json: ExtJsonMode::default(),
+            max_documents: default_max_documents(),
+            max_result_bytes: default_max_result_bytes(),
+            databases: Vec::new(),
+            redaction: Redaction::default(),
+            redaction_hash_key_env: None,
+            allowed_collections: Vec::new(),
+            denied_collections: Vec::new(),
+            default_timeout_secs: default_timeout_secs(),
+            max_timeout_secs: default_max_timeout_secs(),
+            read: ReadSettings::default(),
+        }
+    }
+}
+
+impl QuerySettings {
+    pub fn validate(&self) -> Result<()> {
+        // A page must hold at least one document, or paging never advances
+        if self.max_documents == 0 {
+            bail!("max_documents must be at least 1");
+        }
+        if self.max_result_bytes == 0 {
+            bail!("max_result_bytes must be at least 1");
+        }
+        // An unkeyed digest of an SSN or phone number is reversed by hashing guesses
+        if self.redaction.hashes() && self.redaction_hash_key_env.is_none() {
+            bail!("Redaction rules with action: hash need redaction_hash_key_env");
+        }
+        self.read.validate()
+    }
+
+    /// Key for hashed redaction values, read from `redaction_hash_key_env` when set
+    pub fn redaction_hash_key(&self) -> Result<Option<Vec<u8>>> {
+        let Some(var) = &self.redaction_hash_key_env else {
+            return Ok(None);
+        };
+        let key = std::env::var(var).with_context(|| {
+            format!("Environment variable {var} (redaction_hash_key_env) is not set")
+        })?;
+        if key.trim().is_empty() {
+            bail!("The redaction hash key in {var} is empty");
+        }
+        Ok(Some(key.into_bytes()))
+    }
+
+    pub const fn result_limits(&self) -> ResultLimits {
+        ResultLimits {
+            max_documents: self.max_documents,
+            max_result_bytes: self.max_result_bytes,
+        }
+    }
+
+    pub fn collection_allowed(&self, name: &str) -> bool {
+        access::collection_allowed(&self.allowed_collections, &self.denied_collections, name)
+    }
+
+    /// Whether any allow or deny rule limits the collections tools may access
+    pub const fn has_collection_rules(&self) -> bool {
+        !self.allowed_collections.is_empty() || !self.denied_collections.is_empty()
+    }
+
+    pub fn check_collection(&self, name: &str) -> Result<()> {
+        access::check_collection(&self.allowed_collections, &self.denied_collections, name)
+    }
+
+    /// Time limit for a call: the requested one if within `max_timeout_secs`,
+    /// otherwise the connection default
+    pub fn timeout_secs(&self, requested: Option<u64>) -> Result<u64> {
+        match requested {
+            None => Ok(self
+                .default_timeout_secs
+                .clamp(1, self.max_timeout_secs.max(1))),
+            Some(0) => bail!("timeout_secs must be at least 1"),
+            Some(secs) if secs > self.max_timeout_secs => bail!(
+                "timeout_secs {secs} exceeds this connection's limit of {} seconds",
+                self.max_timeout_secs
+            ),
+            Some(secs) => Ok(secs),
+        }
+    }
+}
+
+/// How queries are executed for a Kubernetes connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionMode {
+    /// Run mongosh inside the pod via exec
+    #[default]
+    Mongosh,
+    /// Port-forward to the pod and query with the native driver
+    PortForward,
+}
+
+const fn default_mongodb_port() -> u16 {
+    27017
+}
+
+fn default_auth_source() -> String {
+    "admin".to_string()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NamespaceConfig {
     pub namespace_name: String,
-    pub deployment_name: String,
+    /// Selects pods labelled `app=<deployment_name>` and names their MongoDB container
+    #[serde(default)]
+    pub deployment_name: Option<String>,
+    /// Pod label selector, e.g. `app.kubernetes.io/name=mongodb` (overrides deployment_name)
+    #[serde(default)]
+    pub label_selector: Option<String>,
+    /// Container running MongoDB (default: deployment_name, else the pod's default container)
+    #[serde(default)]
+    pub container_name: Option<String>,
+    /// Run on a secondary member of the replica set when one is healthy
+    #[serde(default)]
+    pub prefer_secondary: bool,
     pub database_name: String,
     #[serde(default)]
     pub data_model_file_path: Option<String>,
+    #[serde(default)]
+    pub execution_mode: ExecutionMode,
+    /// MongoDB port inside the pod (port_forward mode only)
+    #[serde(default = "default_mongodb_port")]
+    pub mongodb_port: u16,
+    /// How credentials are discovered (default: MONGO_INITDB_ROOT_*_FILE env vars)
+    #[serde(default)]
+    pub credentials: K8sCredentials,
+    /// Database the credentials authenticate against
+    #[serde(default = "default_auth_source")]
+    pub auth_source: String,
+    #[serde(flatten)]
+    pub settings: QuerySettings,
+}
+
+impl NamespaceConfig {
+    /// Label selector used to find MongoDB pods
+    pub fn pod_selector(&self) -> Result<String> {
+        match (&self.label_selector, &self.deployment_name) {
+            (Some(selector), _) => Ok(selector.clone()),
+            (None, Some(deployment)) => Ok(format!("app={deployment}")),
+            (None, None) => bail!(
+                "Namespace '{}' needs deployment_name or label_selector",
+                self.namespace_name
+            ),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.pod_selector()?;
+        self.credentials.validate()?;
+        self.settings.validate()?;
+        if let Some(mode) = self.settings.read.read_preference {
+            self.check_read_preference(mode)?;
+        }
+        Ok(())
+    }
+
+    /// Reject read preferences the chosen pod can't honour. Both execution modes
+    /// reach one pod, picked by `prefer_secondary`, so the mode can't move reads
+    /// between members.
+    pub fn check_read_preference(&self, mode: ReadPreferenceMode) -> Result<()> {
+        let primary = matches!(
+            mode,
+            ReadPreferenceMode::Primary | ReadPreferenceMode::PrimaryPreferred
+        );
+        if !primary && !self.prefer_secondary {
+            bail!(
+                "read_preference {} needs prefer_secondary: true on Kubernetes connections \
+                 (queries only reach the MongoDB server of one pod)",
+                mode.as_str()
+            );
+        }
+        if primary && self.prefer_secondary {
+            bail!(
+                "read_preference {} can't be used with prefer_secondary: true \
+                 (queries run on a secondary pod)",
+                mode.as_str()
+            );
+        }
+        Ok(())
+    }
+
+    /// Container to exec into; None uses the pod's default container
+    pub fn container(&self) -> Option<&str> {
+        self.container_name
+            .as_deref()
+            .or(self.deployment_name.as_deref())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +289,65 @@ pub struct DirectConnectionConfig {
     pub database_name: String,
     #[serde(default)]
     pub data_model_file_path: Option<String>,
+    /// Credentials combined with `mongodb_url` at connect time
+    #[serde(flatten)]
+    pub credentials: DirectCredentials,
+    /// TLS settings; TLS is enabled when present
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+    #[serde(flatten)]
+    pub settings: QuerySettings,
+}
+
+impl DirectConnectionConfig {
+    pub fn validate(&self) -> Result<()> {
+        self.credentials.validate()?;
+        self.settings.validate()?;
+        if let Some(tls) = &self.tls {
+            tls.validate()?;
+        }
+        if self.credentials.auth_mechanism == Some(AuthMechanismSetting::X509)
+            && !self
+                .tls
+                .as_ref()
+                .is_some_and(TlsConfig::has_client_certificate)
+        {
+            bail!("auth_mechanism: x509 requires a tls client certificate");
+        }
+        Ok(())
+    }
+}
+
+/// MongoDB host reached through an SSH local forward on a bastion host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SshConnectionConfig {
+    pub name: String,
+    /// Bastion host the tunnel goes through
+    pub ssh: SshTunnelConfig,
+    /// MongoDB URL as seen from the bastion host; must name a single host
+    pub mongodb_url: String,
+    pub database_name: String,
+    #[serde(default)]
+    pub data_model_file_path: Option<String>,
+    /// Credentials combined with `mongodb_url` at connect time
+    #[serde(flatten)]
+    pub credentials: DirectCredentials,
+    #[serde(flatten)]
+    pub settings: QuerySettings,
+}
+
+impl SshConnectionConfig {
+    pub fn validate(&self) -> Result<()> {
+        self.credentials.validate()?;
+        self.settings.validate()?;
+        if matches!(
+            self.credentials.auth_mechanism,
+            Some(AuthMechanismSetting::X509)
+        ) {
+            bail!("auth_mechanism: x509 is not supported through an SSH tunnel");
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -43,6 +360,115 @@ pub struct Config {
 
     #[serde(default)]
     pub connections: Vec<DirectConnectionConfig>,
+
+    /// MongoDB hosts reached through an SSH bastion
+    #[serde(default)]
+    pub ssh_connections: Vec<SshConnectionConfig>,
+
+    /// Audit log of executed queries
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    /// Streamable HTTP transport (stdio when disabled)
+    #[serde(default)]
+    pub http: HttpConfig,
+
+    /// Files this config was loaded from, including includes, in load order
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
+}
+
+/// One config file before layering. Sections a file leaves out keep the
+/// values of earlier files.
+#[derive(Debug, Deserialize)]
+struct ConfigLayer {
+    /// Files loaded before this one, relative to its directory
+    #[serde(default)]
+    include: Vec<String>,
+    kubeconfig_path: Option<String>,
+    #[serde(default)]
+    namespaces: Vec<NamespaceConfig>,
+    #[serde(default)]
+    connections: Vec<DirectConnectionConfig>,
+    #[serde(default)]
+    ssh_connections: Vec<SshConnectionConfig>,
+    audit: Option<AuditConfig>,
+    http: Option<HttpConfig>,
+}
+
+impl ConfigLayer {
+    /// Expand environment variables and tilde in all path fields and resolve
+    /// relative paths against `dir`, the directory of the file they come from
+    fn resolve_paths(&mut self, dir: &Path) {
+        let resolve = |path: &str| {
+            // Absolute paths replace `dir` in join
+            dir.join(expand_path(path)).to_string_lossy().into_owned()
+        };
+
+        // kubeconfig_path
+        if let Some(path) = &self.kubeconfig_path {
+            self.kubeconfig_path = Some(resolve(path));
+        }
+
+        // Audit log path
+        if let Some(audit) = &mut self.audit
+            && let Some(path) = &audit.path
+        {
+            audit.path = Some(resolve(path));
+        }
+
+        // data_model_file_path and password files in namespaces
+        for ns in &mut self.namespaces {
+            if let Some(path) = &ns.data_model_file_path {
+                ns.data_model_file_path = Some(resolve(path));
+            }
+            if let K8sCredentials::User(user) = &mut ns.credentials
+                && let Some(path) = &user.password_file
+            {
+                user.password_file = Some(resolve(path));
+            }
+        }
+
+        // Paths in direct connections; the URL only has variables expanded
+        for conn in &mut self.connections {
+            conn.mongodb_url = expand_path(&conn.mongodb_url);
+            if let Some(path) = &conn.data_model_file_path {
+                conn.data_model_file_path = Some(resolve(path));
+            }
+            if let Some(path) = &conn.credentials.password_file {
+                conn.credentials.password_file = Some(resolve(path));
+            }
+            if let Some(tls) = &mut conn.tls {
+                for path in [
+                    &mut tls.ca_file,
+                    &mut tls.cert_key_file,
+                    &mut tls.cert_file,
+                    &mut tls.key_file,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    *path = resolve(path);
+                }
+            }
+        }
+
+        // Paths in SSH connections
+        for conn in &mut self.ssh_connections {
+            if let Some(path) = &conn.data_model_file_path {
+                conn.data_model_file_path = Some(resolve(path));
+            }
+            if let Some(path) = &conn.credentials.password_file {
+                conn.credentials.password_file = Some(resolve(path));
+            }
+            for path in [&mut conn.ssh.identity_file, &mut conn.ssh.known_hosts_file]
+                .into_iter()
+                .flatten()
+            {
+                *path = resolve(path);
+            }
+        }
+    }
 }
 
 impl Config {
@@ -70,30 +496,74 @@ impl Config {
         Ok(data_dir)
     }
 
+    /// Default config file: `~/.config/ro-mongodb-mcp-rs/config.yaml`
     pub fn config_file() -> Result<PathBuf> {
         Ok(Self::config_dir()?.join("config.yaml"))
     }
 
+    /// Use these files instead of the default or `RO_MONGODB_MCP_CONFIG` (`--config`).
+    /// Only the first call has an effect; an empty list keeps the default.
+    pub fn set_config_files(files: Vec<PathBuf>) {
+        let _ = CONFIG_FILES.set(files);
+    }
+
+    /// Files given with `--config` or `RO_MONGODB_MCP_CONFIG`, if any
+    fn configured_files() -> Option<Vec<PathBuf>> {
+        if let Some(files) = CONFIG_FILES.get().filter(|files| !files.is_empty()) {
+            return Some(files.clone());
+        }
+        let value = std::env::var_os(CONFIG_FILES_ENV)?;
+        let files: Vec<PathBuf> = std::env::split_paths(&value)
+            .filter(|path| !path.as_os_str().is_empty())
+            .map(|path| PathBuf::from(expand_path(&path.to_string_lossy())))
+            .collect();
+        (!files.is_empty()).then_some(files)
+    }
+
+    /// Config files to load, in order: `--config`, else `RO_MONGODB_MCP_CONFIG`, else the default
+    pub fn config_files() -> Result<Vec<PathBuf>> {
+        match Self::configured_files() {
+            Some(files) => Ok(files),
+            None => Ok(vec![Self::config_file()?]),
+        }
+    }
+
+    /// Load the config files, creating an example config if the default file is missing
     pub fn load() -> Result<Self> {
-        let config_file = Self::config_file()?;
-
-        if !config_file.exists() {
-            Self::create_example_config(&config_file)?;
-            anyhow::bail!(
-                "Configuration file not found. An example configuration has been created at:\n\
-                {}\n\n\
-                Please edit this file to configure your MongoDB namespace environments.",
-                config_file.display()
-            );
+        if Self::configured_files().is_none() {
+            let config_file = Self::config_file()?;
+            if !config_file.exists() {
+                Self::create_example_config(&config_file)?;
+                anyhow::bail!(
+                    "Configuration file not found. An example configuration has been created at:\n\
+                    {}\n\n\
+                    Please edit this file to configure your MongoDB namespace environments.",
+                    config_file.display()
+                );
+            }
         }
 
-        let content = fs::read_to_string(&config_file).context("Failed to read config file")?;
+        Self::load_existing()
+    }
+
+    /// Load the config files without writing anything; a missing file is an error
+    pub fn load_existing() -> Result<Self> {
+        let files = Self::config_files()?;
+        if let Some(missing) = files.iter().find(|file| !file.exists()) {
+            bail!("Configuration file not found: {}", missing.display());
+        }
 
-        let mut config: Self =
-            serde_yaml::from_str(&content).context("Failed to parse config file")?;
+        Self::load_files(&files)
+    }
 
-        // Expand environment variables and tilde in paths
-        config.expand_paths();
+    /// Load and layer `files`: a later file replaces connections of the same name
+    /// and any top-level section it sets (`kubeconfig_path`, `audit`, `http`)
+    pub fn load_files(files: &[PathBuf]) -> Result<Self> {
+        let mut config = Self::default();
+        let mut loading = Vec::new();
+        for file in files {
+            config.load_layer(file, &mut loading)?;
+        }
 
         // Validate data model files exist
         config.validate();
@@ -101,9 +571,72 @@ impl Config {
         Ok(config)
     }
 
+    /// Layer one file over the config, after the files it includes.
+    /// `loading` holds the files being loaded, to reject include cycles.
+    fn load_layer(&mut self, file: &Path, loading: &mut Vec<PathBuf>) -> Result<()> {
+        let content = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read config file {}", file.display()))?;
+        let mut layer: ConfigLayer = serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file {}", file.display()))?;
+
+        let canonical = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        if loading.contains(&canonical) {
+            bail!("Config file {} includes itself", file.display());
+        }
+        loading.push(canonical);
+        let dir = file.parent().unwrap_or_else(|| Path::new("."));
+        for include in &layer.include {
+            // Absolute paths replace `dir` in join
+            let path = dir.join(expand_path(include));
+            self.load_layer(&path, loading)
+                .with_context(|| format!("Failed to include {include} from {}", file.display()))?;
+        }
+        loading.pop();
+
+        layer.resolve_paths(dir);
+        self.sources.push(file.to_path_buf());
+        self.merge(layer);
+        Ok(())
+    }
+
+    /// Apply a layer: its connections replace earlier ones with the same name,
+    /// whatever their type, and the sections it sets replace earlier ones
+    fn merge(&mut self, layer: ConfigLayer) {
+        let names: HashSet<String> = layer
+            .namespaces
+            .iter()
+            .map(|ns| ns.namespace_name.clone())
+            .chain(layer.connections.iter().map(|conn| conn.name.clone()))
+            .chain(layer.ssh_connections.iter().map(|conn| conn.name.clone()))
+            .collect();
+        self.namespaces
+            .retain(|ns| !names.contains(&ns.namespace_name));
+        self.connections.retain(|conn| !names.contains(&conn.name));
+        self.ssh_connections
+            .retain(|conn| !names.contains(&conn.name));
+
+        self.namespaces.extend(layer.namespaces);
+        self.connections.extend(layer.connections);
+        self.ssh_connections.extend(layer.ssh_connections);
+        if layer.kubeconfig_path.is_some() {
+            self.kubeconfig_path = layer.kubeconfig_path;
+        }
+        if let Some(audit) = layer.audit {
+            self.audit = audit;
+        }
+        if let Some(http) = layer.http {
+            self.http = http;
+        }
+    }
+
     fn create_example_config(config_file: &Path) -> Result<()> {
         let example_content = r"# ro-mongodb-mcp-rs configuration
 
+# Optional: Files with more connections, loaded before this one (relative to
+# this file). Connections here replace included ones with the same name.
+# include:
+#   - ~/work/shop/mongodb-connections.yaml
+
 # Optional: Path to custom kubeconfig file
 # If not specified, will use default kubeconfig location
 # kubeconfig_path: /path/to/custom/kubeconfig
@@ -138,15 +671,46 @@ connections:
   #   database_name: analytics
   #   data_model_file_path: /path/to/data-models/analytics.md
 
+# MongoDB hosts only reachable through an SSH bastion (jump box)
+# ssh_connections:
+#   - name: legacy-orders
+#     ssh:
+#       host: bastion.example.com
+#       user: deploy
+#       identity_file: ~/.ssh/id_ed25519  # optional, default: SSH agent and default keys
+#     mongodb_url: mongodb://db1.internal:27017  # as seen from the bastion
+#     database_name: orders
+
+# Optional: Serve MCP over streamable HTTP instead of stdio (or pass --http)
+# http:
+#   enabled: true
+#   bind: 0.0.0.0:8080
+#   path: /mcp                      # default
+#   bearer_token_env: MCP_BEARER_TOKEN  # required unless bind is a loopback address
+
+# Optional: Audit log of every executed query (JSON Lines)
+# audit:
+#   enabled: true              # default: false, entries hold raw filter values
+#   path: /var/log/ro-mongodb-mcp-rs/audit.jsonl  # default: <data_dir>/audit.jsonl
+#   history: true              # let query_history read it (all clients' queries)
+
 # Configuration notes:
 #
 # For Kubernetes namespaces:
 # - namespace_name: The Kubernetes namespace where MongoDB is deployed (also used as connection name)
 # - deployment_name: The deployment label (app=<deployment_name>) to find MongoDB pods
+# - label_selector / container_name: (optional) Any pod selector and the MongoDB
+#   container, for StatefulSets and pods with sidecars
+# - prefer_secondary: (optional) Run on a healthy secondary instead of the primary
 # - database_name: The MongoDB database to query
 # - data_model_file_path: (optional) Local file containing data model documentation
 # - MongoDB credentials are automatically discovered from pod environment variables:
 #   MONGO_INITDB_ROOT_USERNAME_FILE and MONGO_INITDB_ROOT_PASSWORD_FILE
+# - credentials: (optional) strategy env (other variable names), secret (a Secret's
+#   keys) or user (a dedicated read-only user); auth_source defaults to admin
+# - execution_mode: (optional) mongosh (default, exec into the pod) or port_forward
+#   (native driver over a Kubernetes port-forward, no mongosh needed in the image)
+# - mongodb_port: (optional) MongoDB port inside the pod for port_forward (default: 27017)
 #
 # For direct connections:
 # - name: Unique connection name (must not conflict with namespace names)
@@ -154,6 +718,44 @@ connections:
 #   WARNING: This URL may contain sensitive credentials - keep config file secure!
 # - database_name: The MongoDB database to query
 # - data_model_file_path: (optional) Local file containing data model documentation
+# - username: (optional) Username, instead of putting credentials in mongodb_url
+# - password_env / password_file / password_command / password_secret: (optional)
+#   One password source, read at connect time; password_secret is a Kubernetes
+#   Secret key ({namespace, name, key}), as is username_secret
+# - auth_mechanism: (optional) x509, aws or oidc (with oidc_environment k8s, azure or gcp)
+# - tls: (optional) Enables TLS: ca_file, cert_key_file or cert_file + key_file,
+#   allow_invalid_hostnames (openssl-tls builds only)
+#
+# For SSH connections (ssh_connections):
+# - name, mongodb_url, database_name and the credential options work as for
+#   direct connections; mongodb_url names one host, resolved by the bastion
+# - ssh: host, port (default: 22), user, identity_file, known_hosts_file,
+#   accept_new_host_keys (default: false), connect_timeout_secs (default: 15)
+#   The system ssh client is used, so ~/.ssh/config and the SSH agent apply
+#
+# Options available on every connection:
+# - extended_json: (optional) Result format, relaxed (default) or canonical Extended JSON
+# - max_documents: (optional) Documents per result page before truncation (default: 1000)
+# - max_result_bytes: (optional) Serialized bytes per result page (default: 524288)
+# - default_timeout_secs: (optional) Query time limit, also sent as maxTimeMS (default: 30)
+# - max_timeout_secs: (optional) Largest timeout_secs a tool call may request (default: 300)
+# - read_preference: (optional) primary, primaryPreferred, secondary, secondaryPreferred
+#   or nearest, with optional read_preference_tags and max_staleness_secs (>= 90);
+#   K8s connections need prefer_secondary: true for modes other than primary
+#   and primaryPreferred, and reject those two with it
+# - read_concern: (optional) local, available, majority, linearizable or snapshot
+# - databases: (optional) Further databases tools may select, e.g. [tenant_*, reports]
+# - allowed_collections / denied_collections: (optional) Collection names or glob
+#   patterns tools may (not) access, e.g. denied_collections: [audit_*, secrets]
+# - redaction: (optional) Fields to mask, hash or drop in every result, e.g.
+#     redaction:
+#       - path: password
+#         action: drop
+#       - path: '*.ssn'
+#       - path: email
+#         action: hash     # needs redaction_hash_key_env
+# - redaction_hash_key_env: (optional) Environment variable with the secret key
+#   used to hash values, so hashes stay comparable but can't be reversed
 ";
 
         fs::write(config_file, example_content).context("Failed to write example config file")?;
@@ -183,30 +785,34 @@ connections:
             }
         }
 
+        for conn in &self.ssh_connections {
+            if !seen.insert(&conn.name) {
+                bail!(
+                    "Duplicate connection name '{}' found in ssh_connections (conflicts with another connection)",
+                    conn.name
+                );
+            }
+        }
+
         Ok(())
     }
 
-    /// Expand environment variables and tilde in all path fields
-    fn expand_paths(&mut self) {
-        // Expand kubeconfig_path
-        if let Some(path) = &self.kubeconfig_path {
-            self.kubeconfig_path = Some(expand_path(path));
+    /// Validate every connection's settings without connecting to anything
+    pub fn validate_connections(&self) -> Result<()> {
+        self.validate_unique_names()?;
+        for ns in &self.namespaces {
+            ns.validate()
+                .with_context(|| format!("Invalid connection '{}'", ns.namespace_name))?;
         }
-
-        // Expand data_model_file_path in namespaces
-        for ns in &mut self.namespaces {
-            if let Some(path) = &ns.data_model_file_path {
-                ns.data_model_file_path = Some(expand_path(path));
-            }
+        for conn in &self.connections {
+            conn.validate()
+                .with_context(|| format!("Invalid connection '{}'", conn.name))?;
         }
-
-        // Expand paths in direct connections
-        for conn in &mut self.connections {
-            conn.mongodb_url = expand_path(&conn.mongodb_url);
-            if let Some(path) = &conn.data_model_file_path {
-                conn.data_model_file_path = Some(expand_path(path));
-            }
+        for conn in &self.ssh_connections {
+            conn.validate()
+                .with_context(|| format!("Invalid connection '{}'", conn.name))?;
         }
+        Ok(())
     }
 
     pub fn validate(&self) {
@@ -235,5 +841,386 @@ connections:
                 }
             }
         }
+
+        for conn in &self.ssh_connections {
+            if let Some(path_str) = &conn.data_model_file_path
+                && !Path::new(path_str).exists()
+            {
+                tracing::warn!(
+                    "Data model file does not exist for SSH connection '{}': {}",
+                    conn.name,
+                    path_str
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespace_execution_mode() {
+        let config: Config = serde_yaml::from_str(
+            r"
+namespaces:
+  - namespace_name: prod
+    deployment_name: mongodb
+    database_name: app
+  - namespace_name: staging
+    deployment_name: mongodb
+    database_name: app
+    execution_mode: port_forward
+    mongodb_port: 27018
+",
+        )
+        .unwrap();
+
+        assert_eq!(config.namespaces[0].execution_mode, ExecutionMode::Mongosh);
+        assert_eq!(config.namespaces[0].mongodb_port, 27017);
+        assert_eq!(
+            config.namespaces[1].execution_mode,
+            ExecutionMode::PortForward
+        );
+        assert_eq!(config.namespaces[1].mongodb_port, 27018);
+    }
+
+    #[test]
+    fn test_audit_is_opt_in() {
+        let config: Config = serde_yaml::from_str("connections: []").unwrap();
+        assert!(!config.audit.enabled);
+        assert!(!config.audit.history);
+
+        let config: Config = serde_yaml::from_str("audit:\n  enabled: true").unwrap();
+        assert!(config.audit.enabled);
+        assert!(!config.audit.history);
+    }
+
+    #[test]
+    fn test_result_limits_must_be_positive() {
+        let connection = |extra: &str| -> DirectConnectionConfig {
+            serde_yaml::from_str(&format!(
+                "name: local\nmongodb_url: mongodb://localhost:27017\ndatabase_name: app\n{extra}"
+            ))
+            .unwrap()
+        };
+
+        assert!(connection("max_documents: 0").validate().is_err());
+        assert!(connection("max_result_bytes: 0").validate().is_err());
+        assert!(connection("max_documents: 1").validate().is_ok());
+    }
+
+    #[test]
+    fn test_read_preference_needs_prefer_secondary() {
+        let namespace = |extra: &str| -> NamespaceConfig {
+            serde_yaml::from_str(&format!(
+                "namespace_name: prod\ndeployment_name: mongodb\ndatabase_name: app\n{extra}"
+            ))
+            .unwrap()
+        };
+
+        assert!(
+            namespace("read_preference: secondaryPreferred")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            namespace("read_preference: secondaryPreferred\nprefer_secondary: true")
+                .validate()
+                .is_ok()
+        );
+        // Port-forwards also reach a single pod
+        assert!(
+            namespace("read_preference: secondaryPreferred\nexecution_mode: port_forward")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            namespace(
+                "read_preference: secondaryPreferred\nexecution_mode: port_forward\n\
+                 prefer_secondary: true"
+            )
+            .validate()
+            .is_ok()
+        );
+        assert!(namespace("read_preference: primary").validate().is_ok());
+        assert!(
+            namespace("read_preference: primary\nprefer_secondary: true")
+                .validate()
+                .is_err()
+        );
+
+        // Per-call overrides get the same check
+        let ns = namespace("prefer_secondary: true");
+        assert!(ns.validate().is_ok());
+        assert!(
+            ns.check_read_preference(ReadPreferenceMode::Nearest)
+                .is_ok()
+        );
+        assert!(
+            ns.check_read_preference(ReadPreferenceMode::Primary)
+                .is_err()
+        );
+        assert!(
+            namespace("")
+                .check_read_preference(ReadPreferenceMode::Secondary)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_result_limit_defaults() {
+        let config: Config = serde_yaml::from_str(
+            r"
+connections:
+  - name: local
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+  - name: small
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+    max_documents: 50
+    max_result_bytes: 4096
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.connections[0].settings.result_limits(),
+            QuerySettings::default().result_limits()
+        );
+        assert_eq!(
+            config.connections[1].settings.result_limits(),
+            ResultLimits {
+                max_documents: 50,
+                max_result_bytes: 4096
+            }
+        );
+    }
+
+    #[test]
+    fn test_timeout_settings() {
+        let config: Config = serde_yaml::from_str(
+            r"
+connections:
+  - name: local
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+  - name: reports
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+    default_timeout_secs: 60
+    max_timeout_secs: 120
+",
+        )
+        .unwrap();
+
+        let local = &config.connections[0].settings;
+        assert_eq!(local.timeout_secs(None).unwrap(), 30);
+        assert_eq!(local.timeout_secs(Some(300)).unwrap(), 300);
+        assert!(local.timeout_secs(Some(301)).is_err());
+        assert!(local.timeout_secs(Some(0)).is_err());
+
+        let reports = &config.connections[1].settings;
+        assert_eq!(reports.timeout_secs(None).unwrap(), 60);
+        assert_eq!(reports.timeout_secs(Some(90)).unwrap(), 90);
+        assert!(reports.timeout_secs(Some(121)).is_err());
+    }
+
+    #[test]
+    fn test_ssh_connections() {
+        let config: Config = serde_yaml::from_str(
+            r"
+connections:
+  - name: local
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+ssh_connections:
+  - name: legacy
+    ssh:
+      host: bastion.example.com
+      user: deploy
+    mongodb_url: mongodb://db1.internal:27017
+    database_name: orders
+    username: reader
+    password_env: LEGACY_MONGO_PASSWORD
+",
+        )
+        .unwrap();
+
+        let legacy = &config.ssh_connections[0];
+        assert_eq!(legacy.ssh.port, 22);
+        assert!(legacy.validate().is_ok());
+        assert!(config.validate_unique_names().is_ok());
+
+        let mut duplicate = config.clone();
+        duplicate.ssh_connections[0].name = "local".to_string();
+        assert!(duplicate.validate_unique_names().is_err());
+    }
+
+    #[test]
+    fn test_redaction_rules_in_connection() {
+        let config: Config = serde_yaml::from_str(
+            r"
+connections:
+  - name: local
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+    redaction:
+      - path: password
+        action: drop
+",
+        )
+        .unwrap();
+        assert!(!config.connections[0].settings.redaction.is_empty());
+
+        let invalid = serde_yaml::from_str::<Config>(
+            r"
+connections:
+  - name: local
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+    redaction:
+      - pattern: '('
+",
+        );
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_hash_redaction_needs_key() {
+        let mut config: Config = serde_yaml::from_str(
+            r"
+connections:
+  - name: local
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+    redaction:
+      - path: email
+        action: hash
+",
+        )
+        .unwrap();
+        assert!(config.validate_connections().is_err());
+
+        let settings = &mut config.connections[0].settings;
+        settings.redaction_hash_key_env = Some("RO_MONGODB_MCP_TEST_UNSET_KEY".to_string());
+        assert!(config.validate_connections().is_ok());
+        assert!(config.connections[0].settings.redaction_hash_key().is_err());
+    }
+
+    #[test]
+    fn test_load_layers_and_includes() {
+        let dir =
+            std::env::temp_dir().join(format!("ro-mongodb-mcp-rs-config-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("project")).unwrap();
+        let global = dir.join("config.yaml");
+        let project = dir.join("project/mcp.yaml");
+        std::fs::write(
+            &global,
+            r"
+connections:
+  - name: local
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+  - name: reports
+    mongodb_url: mongodb://localhost:27017
+    database_name: reports
+audit:
+  enabled: true
+",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("project/shared.yaml"),
+            r"
+ssh_connections:
+  - name: legacy
+    ssh:
+      host: bastion
+      identity_file: keys/bastion
+      known_hosts_file: /etc/ssh/ssh_known_hosts
+    mongodb_url: mongodb://db1.internal:27017
+    database_name: orders
+    data_model_file_path: models/orders.md
+",
+        )
+        .unwrap();
+        std::fs::write(
+            &project,
+            r"
+include:
+  - shared.yaml
+connections:
+  - name: reports
+    mongodb_url: mongodb://localhost:27018
+    database_name: reports
+    username: reader
+    password_file: secrets/reports
+    tls:
+      ca_file: certs/ca.pem
+ssh_connections:
+  - name: local
+    ssh:
+      host: bastion
+    mongodb_url: mongodb://db2.internal:27017
+    database_name: app
+",
+        )
+        .unwrap();
+
+        let config = Config::load_files(&[global.clone(), project.clone()]).unwrap();
+        // Later files replace connections by name, even across connection types
+        let direct: Vec<_> = config.connections.iter().map(|c| c.name.as_str()).collect();
+        let ssh: Vec<_> = config
+            .ssh_connections
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(direct, ["reports"]);
+        assert_eq!(
+            config.connections[0].mongodb_url,
+            "mongodb://localhost:27018"
+        );
+        assert_eq!(ssh, ["legacy", "local"]);
+        // Relative paths resolve against the directory of the file they are in
+        let path = |rel: &str| Some(dir.join(rel).to_string_lossy().into_owned());
+        let reports = &config.connections[0];
+        assert_eq!(
+            reports.credentials.password_file,
+            path("project/secrets/reports")
+        );
+        assert_eq!(
+            reports.tls.as_ref().unwrap().ca_file,
+            path("project/certs/ca.pem")
+        );
+        let legacy = &config.ssh_connections[0];
+        assert_eq!(
+            legacy.data_model_file_path,
+            path("project/models/orders.md")
+        );
+        assert_eq!(legacy.ssh.identity_file, path("project/keys/bastion"));
+        assert_eq!(
+            legacy.ssh.known_hosts_file.as_deref(),
+            Some("/etc/ssh/ssh_known_hosts")
+        );
+        // Sections a later file leaves out keep their earlier values
+        assert!(config.audit.enabled);
+        assert_eq!(
+            config.sources,
+            [
+                global.clone(),
+                dir.join("project/shared.yaml"),
+                project.clone()
+            ]
+        );
+        config.validate_connections().unwrap();
+
+        std::fs::write(dir.join("project/shared.yaml"), "include: [mcp.yaml]\n").unwrap();
+        let err = Config::load_files(&[project]).unwrap_err();
+        assert!(format!("{err:#}").contains("includes itself"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
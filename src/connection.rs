@@ -4,43 +4,103 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
+use crate::access;
+use crate::collections::CollectionInfo;
+use crate::config::QuerySettings;
+use crate::diagnostics::CheckStep;
+use crate::explain::ExplainVerbosity;
+use crate::indexes::IndexInfo;
 use crate::mongodb::{QueryOperation, QueryOptions};
+use crate::read_preference::ReadPreferenceMode;
+use crate::results::QueryResult;
 
 /// Unified abstraction for MongoDB connections.
-/// Both K8s namespace connections and direct URL connections implement this trait.
+/// Implemented by K8s namespace, direct URL and SSH tunnel connections.
 #[async_trait]
 pub trait MongoConnection: Send + Sync {
     /// Unique identifier for this connection
     fn name(&self) -> &str;
 
-    /// Human-readable connection type for error messages ("kubernetes" or "direct")
+    /// Human-readable connection type for error messages ("kubernetes", "direct" or "ssh")
     fn connection_type(&self) -> &str;
 
     /// Path to the data model file (if configured)
     fn data_model_path(&self) -> Option<&str>;
 
-    /// Database name for this connection
+    /// Default database for this connection
     fn database_name(&self) -> &str;
 
-    /// List all collections in the database
-    async fn list_collections(&self) -> Result<Vec<String>>;
+    /// Query behaviour settings (result format, ...)
+    fn settings(&self) -> &QuerySettings;
 
-    /// Execute a MongoDB query and return the result as a string
+    /// Resolve the database for a call: `requested` if the connection allows it,
+    /// otherwise the configured `database_name`
+    fn resolve_database(&self, requested: Option<&str>) -> Result<String> {
+        access::resolve_database(self.database_name(), &self.settings().databases, requested)
+    }
+
+    /// Reject a per-call read preference this connection can't honour
+    fn check_read_preference(&self, _mode: ReadPreferenceMode) -> Result<()> {
+        Ok(())
+    }
+
+    /// Run `listDatabases` and return the raw reply
+    async fn list_databases(&self, timeout_secs: u64) -> Result<serde_json::Value>;
+
+    /// List all collections and views in a database
+    async fn list_collections(
+        &self,
+        database: &str,
+        timeout_secs: u64,
+    ) -> Result<Vec<CollectionInfo>>;
+
+    /// List the indexes of a collection
+    async fn list_indexes(
+        &self,
+        database: &str,
+        collection: &str,
+        timeout_secs: u64,
+    ) -> Result<Vec<IndexInfo>>;
+
+    /// Run `dbStats` for a database and return the raw reply
+    async fn database_stats(&self, database: &str, timeout_secs: u64) -> Result<serde_json::Value>;
+
+    /// Execute a MongoDB query, honouring `options.skip` and `options.limits`.
+    /// `timeout_secs` is also sent to the server as `maxTimeMS`.
     async fn execute_query(
         &self,
+        database: &str,
         collection: &str,
         operation: &QueryOperation,
         query: &str,
         options: &QueryOptions,
         timeout_secs: u64,
-    ) -> Result<String>;
+    ) -> Result<QueryResult>;
+
+    /// Run `explain` for a query and return the raw explain output
+    #[allow(clippy::too_many_arguments)]
+    async fn explain_query(
+        &self,
+        database: &str,
+        collection: &str,
+        operation: &QueryOperation,
+        query: &str,
+        options: &QueryOptions,
+        verbosity: ExplainVerbosity,
+        timeout_secs: u64,
+    ) -> Result<serde_json::Value>;
+
+    /// Run this connection's health check steps against `database`, stopping at the
+    /// first failure. Kubernetes pods and credentials are discovered afresh.
+    async fn check(&self, database: &str, timeout_secs: u64) -> Vec<CheckStep>;
 }
 
 /// Registry holding all configured connections
 #[derive(Default)]
 pub struct ConnectionRegistry {
-    connections: HashMap<String, Box<dyn MongoConnection>>,
+    connections: HashMap<String, Arc<dyn MongoConnection>>,
 }
 
 impl ConnectionRegistry {
@@ -50,13 +110,13 @@ impl ConnectionRegistry {
         }
     }
 
-    pub fn register(&mut self, conn: Box<dyn MongoConnection>) {
+    pub fn register(&mut self, conn: Arc<dyn MongoConnection>) {
         let name = conn.name().to_string();
         self.connections.insert(name, conn);
     }
 
-    pub fn get(&self, name: &str) -> Option<&dyn MongoConnection> {
-        self.connections.get(name).map(|c| c.as_ref())
+    pub fn get(&self, name: &str) -> Option<Arc<dyn MongoConnection>> {
+        self.connections.get(name).cloned()
     }
 
     pub fn list_names(&self) -> Vec<String> {
@@ -75,3 +135,22 @@ impl ConnectionRegistry {
         list
     }
 }
+
+/// The current registry, replaced as a whole when the config is reloaded.
+/// Callers take a snapshot, so a request never sees a half-applied reload.
+#[derive(Clone, Default)]
+pub struct SharedRegistry(Arc<RwLock<Arc<ConnectionRegistry>>>);
+
+impl SharedRegistry {
+    pub fn new(registry: ConnectionRegistry) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(registry))))
+    }
+
+    pub fn current(&self) -> Arc<ConnectionRegistry> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn replace(&self, registry: ConnectionRegistry) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(registry);
+    }
+}
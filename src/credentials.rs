@@ -0,0 +1,518 @@
+//! Credential sources for direct and Kubernetes connections.
+//!
+//! Direct connections keep credentials out of `mongodb_url`: the password comes
+//! from an environment variable, a file, a command or a Kubernetes Secret and is
+//! only read when the connection is first used. Kubernetes connections choose how
+//! credentials are discovered from the MongoDB pod with a [`K8sCredentials`] strategy.
+
+use anyhow::{Context, Result, anyhow, bail};
+use mongodb::bson::{Document, doc};
+use mongodb::options::{AuthMechanism, ClientOptions, Credential};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::k8s_client::K8sClient;
+
+/// Time allowed for `password_command`
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A key of a Kubernetes Secret
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretKeyRef {
+    pub namespace: String,
+    pub name: String,
+    pub key: String,
+}
+
+/// Authentication mechanisms that don't use a SCRAM password
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMechanismSetting {
+    /// MONGODB-X509: the TLS client certificate identifies the user
+    X509,
+    /// MONGODB-AWS: AWS IAM credentials from the environment, or username
+    /// (access key ID) plus a password source (secret access key)
+    Aws,
+    /// MONGODB-OIDC: tokens from the `oidc_environment`
+    Oidc,
+}
+
+impl AuthMechanismSetting {
+    const fn to_driver(self) -> AuthMechanism {
+        match self {
+            Self::X509 => AuthMechanism::MongoDbX509,
+            Self::Aws => AuthMechanism::MongoDbAws,
+            Self::Oidc => AuthMechanism::MongoDbOidc,
+        }
+    }
+}
+
+/// Where MONGODB-OIDC tokens come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OidcEnvironment {
+    /// The pod's projected service account token
+    K8s,
+    /// Azure managed identity (requires `oidc_token_resource`)
+    Azure,
+    /// GCP service account (requires `oidc_token_resource`)
+    Gcp,
+}
+
+impl OidcEnvironment {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::K8s => "k8s",
+            Self::Azure => "azure",
+            Self::Gcp => "gcp",
+        }
+    }
+}
+
+/// Credential fields of a direct connection (flattened next to `mongodb_url`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirectCredentials {
+    /// Username, combined with one of the password sources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Username read from a Kubernetes Secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username_secret: Option<SecretKeyRef>,
+    /// Environment variable holding the password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_env: Option<String>,
+    /// File containing the password (trailing newline ignored)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_file: Option<String>,
+    /// Shell command printing the password, e.g. `pass show mongo/prod`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_command: Option<String>,
+    /// Password read from a Kubernetes Secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_secret: Option<SecretKeyRef>,
+    /// Authentication mechanism instead of username and password: x509, aws or oidc
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_mechanism: Option<AuthMechanismSetting>,
+    /// Token source for `auth_mechanism: oidc`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oidc_environment: Option<OidcEnvironment>,
+    /// Token audience for the azure and gcp OIDC environments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oidc_token_resource: Option<String>,
+}
+
+impl DirectCredentials {
+    fn password_sources(&self) -> usize {
+        [
+            self.password_env.is_some(),
+            self.password_file.is_some(),
+            self.password_command.is_some(),
+            self.password_secret.is_some(),
+        ]
+        .into_iter()
+        .filter(|set| *set)
+        .count()
+    }
+
+    const fn has_username(&self) -> bool {
+        self.username.is_some() || self.username_secret.is_some()
+    }
+
+    /// Whether any credential field is set
+    pub fn is_configured(&self) -> bool {
+        self.has_username() || self.password_sources() > 0 || self.auth_mechanism.is_some()
+    }
+
+    /// Whether resolving needs the Kubernetes API
+    pub const fn uses_k8s_secrets(&self) -> bool {
+        self.username_secret.is_some() || self.password_secret.is_some()
+    }
+
+    /// Reject ambiguous or incomplete combinations
+    pub fn validate(&self) -> Result<()> {
+        if self.username.is_some() && self.username_secret.is_some() {
+            bail!("Set either username or username_secret, not both");
+        }
+        if self.password_sources() > 1 {
+            bail!(
+                "Set only one of password_env, password_file, password_command or password_secret"
+            );
+        }
+        if self.password_sources() == 1 && !self.has_username() {
+            bail!("A password source requires username or username_secret");
+        }
+
+        let oidc = self.auth_mechanism == Some(AuthMechanismSetting::Oidc);
+        if !oidc && (self.oidc_environment.is_some() || self.oidc_token_resource.is_some()) {
+            bail!("oidc_environment and oidc_token_resource require auth_mechanism: oidc");
+        }
+        match self.auth_mechanism {
+            Some(AuthMechanismSetting::X509 | AuthMechanismSetting::Oidc)
+                if self.password_sources() > 0 =>
+            {
+                bail!("The x509 and oidc auth mechanisms don't use a password");
+            }
+            Some(AuthMechanismSetting::Oidc) => match self.oidc_environment {
+                None => bail!("auth_mechanism: oidc requires oidc_environment (k8s, azure or gcp)"),
+                Some(OidcEnvironment::Azure | OidcEnvironment::Gcp)
+                    if self.oidc_token_resource.is_none() =>
+                {
+                    bail!("The azure and gcp OIDC environments require oidc_token_resource");
+                }
+                Some(_) => {}
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Read the username and password from their sources
+    pub async fn resolve(
+        &self,
+        k8s_client: Option<&K8sClient>,
+    ) -> Result<(Option<String>, Option<String>)> {
+        let username = match (&self.username, &self.username_secret) {
+            (Some(username), _) => Some(username.clone()),
+            (None, Some(secret)) => Some(read_secret(k8s_client, secret).await?),
+            (None, None) => None,
+        };
+        let password = self.password(k8s_client).await?;
+        Ok((username, password))
+    }
+
+    /// Add the configured username and password to client options parsed from `url`.
+    /// Options from the URL, such as authSource or authMechanism, are kept.
+    pub async fn apply(
+        &self,
+        url: &str,
+        client_options: &mut ClientOptions,
+        k8s_client: Option<&K8sClient>,
+    ) -> Result<()> {
+        if !self.is_configured() {
+            return Ok(());
+        }
+        self.validate()?;
+
+        if client_options
+            .credential
+            .as_ref()
+            .is_some_and(|c| c.username.is_some())
+        {
+            bail!("mongodb_url already contains credentials; remove them or the username fields");
+        }
+        if self.auth_mechanism.is_some()
+            && client_options
+                .credential
+                .as_ref()
+                .is_some_and(|c| c.mechanism.is_some())
+        {
+            bail!("mongodb_url already sets authMechanism; remove it or auth_mechanism");
+        }
+
+        let (username, password) = self.resolve(k8s_client).await?;
+
+        let credential = client_options
+            .credential
+            .get_or_insert_with(Credential::default);
+        credential.username = username;
+        credential.password = password;
+        // The driver drops authSource when the URL has no username
+        if credential.source.is_none() {
+            credential.source = url_auth_source(url);
+        }
+        if let Some(mechanism) = self.auth_mechanism {
+            credential.mechanism = Some(mechanism.to_driver());
+            credential.mechanism_properties = self.mechanism_properties();
+            // External mechanisms authenticate against the virtual $external database
+            credential.source = Some("$external".to_string());
+        }
+        Ok(())
+    }
+
+    fn mechanism_properties(&self) -> Option<Document> {
+        let environment = self.oidc_environment?;
+        let mut properties = doc! { "ENVIRONMENT": environment.as_str() };
+        if let Some(resource) = &self.oidc_token_resource {
+            properties.insert("TOKEN_RESOURCE", resource);
+        }
+        Some(properties)
+    }
+
+    async fn password(&self, k8s_client: Option<&K8sClient>) -> Result<Option<String>> {
+        if let Some(var) = &self.password_env {
+            let password = std::env::var(var)
+                .with_context(|| format!("Password environment variable '{var}' is not set"))?;
+            return Ok(Some(password));
+        }
+        if let Some(path) = &self.password_file {
+            let content = tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| format!("Failed to read password file '{path}'"))?;
+            return Ok(Some(trim_line_end(content)));
+        }
+        if let Some(command) = &self.password_command {
+            return run_password_command(command).await.map(Some);
+        }
+        if let Some(secret) = &self.password_secret {
+            return read_secret(k8s_client, secret).await.map(Some);
+        }
+        Ok(None)
+    }
+}
+
+fn default_username_env() -> String {
+    "MONGO_INITDB_ROOT_USERNAME_FILE".to_string()
+}
+
+fn default_password_env() -> String {
+    "MONGO_INITDB_ROOT_PASSWORD_FILE".to_string()
+}
+
+fn default_username_key() -> String {
+    "username".to_string()
+}
+
+fn default_password_key() -> String {
+    "password".to_string()
+}
+
+/// How a Kubernetes connection finds MongoDB credentials (`credentials` of a namespace)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum K8sCredentials {
+    /// Environment variables of the MongoDB container. Names ending in `_FILE`
+    /// hold the path of a file in the container, others the value itself, set
+    /// literally or through `valueFrom.secretKeyRef`.
+    Env {
+        #[serde(default = "default_username_env")]
+        username_env: String,
+        #[serde(default = "default_password_env")]
+        password_env: String,
+    },
+    /// Keys of a Secret in the connection's namespace
+    Secret {
+        name: String,
+        #[serde(default = "default_username_key")]
+        username_key: String,
+        #[serde(default = "default_password_key")]
+        password_key: String,
+    },
+    /// A dedicated (e.g. read-only) user, with the same fields as a direct connection
+    User(DirectCredentials),
+}
+
+impl Default for K8sCredentials {
+    /// The official image's `MONGO_INITDB_ROOT_*_FILE` convention
+    fn default() -> Self {
+        Self::Env {
+            username_env: default_username_env(),
+            password_env: default_password_env(),
+        }
+    }
+}
+
+impl K8sCredentials {
+    pub fn validate(&self) -> Result<()> {
+        if let Self::User(user) = self {
+            user.validate()?;
+            if user.auth_mechanism.is_some() {
+                bail!("The user strategy supports username and password only");
+            }
+            if !user.has_username() || user.password_sources() != 1 {
+                bail!("The user strategy requires a username and one password source");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The `authSource` option of a connection string, if present
+fn url_auth_source(url: &str) -> Option<String> {
+    let (_, query) = url.split_once('?')?;
+    query
+        .split(['&', ';'])
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| key.eq_ignore_ascii_case("authSource"))
+        .map(|(_, value)| value.to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Drop a single trailing newline, as left by editors and `echo`
+fn trim_line_end(mut value: String) -> String {
+    if value.ends_with('\n') {
+        value.pop();
+        if value.ends_with('\r') {
+            value.pop();
+        }
+    }
+    value
+}
+
+async fn run_password_command(command: &str) -> Result<String> {
+    let output = tokio::time::timeout(
+        COMMAND_TIMEOUT,
+        tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .map_err(|_| {
+        anyhow!(
+            "password_command timed out after {} seconds",
+            COMMAND_TIMEOUT.as_secs()
+        )
+    })?
+    .context("Failed to run password_command")?;
+
+    if !output.status.success() {
+        // stderr may help diagnose the failure; stdout could hold a partial secret
+        bail!(
+            "password_command failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let password =
+        String::from_utf8(output.stdout).context("password_command output is not UTF-8")?;
+    Ok(trim_line_end(password))
+}
+
+async fn read_secret(k8s_client: Option<&K8sClient>, secret: &SecretKeyRef) -> Result<String> {
+    let k8s_client =
+        k8s_client.ok_or_else(|| anyhow!("Kubernetes client unavailable for secret lookup"))?;
+    k8s_client
+        .read_secret_key(&secret.namespace, &secret.name, &secret.key)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let ok = DirectCredentials {
+            username: Some("reader".to_string()),
+            password_env: Some("MONGO_PASSWORD".to_string()),
+            ..Default::default()
+        };
+        assert!(ok.validate().is_ok());
+
+        let two_sources = DirectCredentials {
+            password_file: Some("/run/secrets/mongo".to_string()),
+            ..ok.clone()
+        };
+        assert!(two_sources.validate().is_err());
+
+        let no_username = DirectCredentials {
+            password_env: Some("MONGO_PASSWORD".to_string()),
+            ..Default::default()
+        };
+        assert!(no_username.validate().is_err());
+
+        assert!(!DirectCredentials::default().is_configured());
+
+        let x509_with_password = DirectCredentials {
+            auth_mechanism: Some(AuthMechanismSetting::X509),
+            ..ok.clone()
+        };
+        assert!(x509_with_password.validate().is_err());
+
+        let gcp_without_resource = DirectCredentials {
+            auth_mechanism: Some(AuthMechanismSetting::Oidc),
+            oidc_environment: Some(OidcEnvironment::Gcp),
+            ..Default::default()
+        };
+        assert!(gcp_without_resource.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_apply_external_mechanism() {
+        let credentials = DirectCredentials {
+            auth_mechanism: Some(AuthMechanismSetting::Oidc),
+            oidc_environment: Some(OidcEnvironment::Azure),
+            oidc_token_resource: Some("api://mongodb".to_string()),
+            ..Default::default()
+        };
+        assert!(credentials.validate().is_ok());
+
+        let url = "mongodb://localhost:27017";
+        let mut options = ClientOptions::parse(url).await.unwrap();
+        credentials.apply(url, &mut options, None).await.unwrap();
+
+        let credential = options.credential.unwrap();
+        assert_eq!(credential.mechanism, Some(AuthMechanism::MongoDbOidc));
+        assert_eq!(credential.source.as_deref(), Some("$external"));
+        assert_eq!(
+            credential.mechanism_properties,
+            Some(doc! { "ENVIRONMENT": "azure", "TOKEN_RESOURCE": "api://mongodb" })
+        );
+    }
+
+    #[test]
+    fn test_k8s_credentials_strategies() {
+        let parse = |yaml: &str| serde_yaml::from_str::<K8sCredentials>(yaml).unwrap();
+
+        assert!(matches!(
+            parse("strategy: env\nusername_env: MONGODB_ROOT_USER"),
+            K8sCredentials::Env { username_env, password_env }
+                if username_env == "MONGODB_ROOT_USER" && password_env == "MONGO_INITDB_ROOT_PASSWORD_FILE"
+        ));
+        assert!(matches!(
+            parse("strategy: secret\nname: mongodb-auth\npassword_key: mongodb-root-password"),
+            K8sCredentials::Secret { name, username_key, password_key }
+                if name == "mongodb-auth" && username_key == "username" && password_key == "mongodb-root-password"
+        ));
+
+        let user = parse("strategy: user\nusername: reader\npassword_env: READER_PASSWORD");
+        assert!(user.validate().is_ok());
+        let incomplete = parse("strategy: user\nusername: reader");
+        assert!(incomplete.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_apply_keeps_url_options() {
+        let credentials = DirectCredentials {
+            username: Some("reader".to_string()),
+            password_command: Some("printf 's3cret\\n'".to_string()),
+            ..Default::default()
+        };
+
+        let url = "mongodb://localhost:27017/app?authSource=admin";
+        let mut options = ClientOptions::parse(url).await.unwrap();
+        credentials.apply(url, &mut options, None).await.unwrap();
+
+        let credential = options.credential.unwrap();
+        assert_eq!(credential.username.as_deref(), Some("reader"));
+        assert_eq!(credential.password.as_deref(), Some("s3cret"));
+        assert_eq!(credential.source.as_deref(), Some("admin"));
+
+        // Credentials in the URL and in fields are ambiguous
+        let url = "mongodb://user:pw@localhost:27017";
+        let mut options = ClientOptions::parse(url).await.unwrap();
+        assert!(credentials.apply(url, &mut options, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_password_file() {
+        let path =
+            std::env::temp_dir().join(format!("ro-mongodb-mcp-rs-password-{}", std::process::id()));
+        std::fs::write(&path, "from-file\n").unwrap();
+
+        let credentials = DirectCredentials {
+            username: Some("reader".to_string()),
+            password_file: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            credentials.password(None).await.unwrap().as_deref(),
+            Some("from-file")
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
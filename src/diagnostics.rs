@@ -0,0 +1,554 @@
+//! Step-by-step connection health checks.
+//!
+//! Used by the `check_connection` tool and `--check`. Each connection type runs
+//! its own steps (pod discovery, SSH tunnel, ...) and reports how long each took,
+//! so a failure points at the step that broke instead of surfacing on the first query.
+
+use anyhow::{Result, bail};
+use mongodb::{Client, bson::doc};
+use serde::Serialize;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::connection::MongoConnection;
+use crate::native;
+use crate::reload::RegistryBuilder;
+
+/// Outcome of one check step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Ok,
+    Failed,
+    /// Not run because an earlier step failed
+    Skipped,
+}
+
+impl StepStatus {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckStep {
+    pub name: &'static str,
+    pub status: StepStatus,
+    pub duration_ms: u64,
+    /// What the step found, or why it failed
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+}
+
+impl CheckStep {
+    fn new(name: &'static str, status: StepStatus, elapsed: Duration, detail: String) -> Self {
+        Self {
+            name,
+            status,
+            duration_ms: u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX),
+            detail,
+        }
+    }
+}
+
+/// Runs the steps of one check in order. Steps after a failure are reported as skipped.
+pub struct Checker {
+    planned: &'static [&'static str],
+    steps: Vec<CheckStep>,
+}
+
+impl Checker {
+    /// A checker for the given steps, in the order they run
+    pub const fn new(planned: &'static [&'static str]) -> Self {
+        Self {
+            planned,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Run one step, timing it. The future returns its value and a detail line.
+    /// Returns None if the step failed, so callers can stop with `?`.
+    pub async fn run<T>(
+        &mut self,
+        name: &'static str,
+        step: impl Future<Output = Result<(T, String)>>,
+    ) -> Option<T> {
+        debug_assert!(self.planned.contains(&name), "unplanned check step {name}");
+
+        let started = Instant::now();
+        let result = step.await;
+        let elapsed = started.elapsed();
+        match result {
+            Ok((value, detail)) => {
+                self.steps
+                    .push(CheckStep::new(name, StepStatus::Ok, elapsed, detail));
+                Some(value)
+            }
+            Err(e) => {
+                self.steps.push(CheckStep::new(
+                    name,
+                    StepStatus::Failed,
+                    elapsed,
+                    format!("{e:#}"),
+                ));
+                None
+            }
+        }
+    }
+
+    /// All planned steps: those that ran, then the rest as skipped
+    pub fn into_steps(mut self) -> Vec<CheckStep> {
+        for name in self.planned {
+            if !self.steps.iter().any(|step| step.name == *name) {
+                self.steps.push(CheckStep::new(
+                    name,
+                    StepStatus::Skipped,
+                    Duration::ZERO,
+                    String::new(),
+                ));
+            }
+        }
+        self.steps
+    }
+}
+
+/// Authenticate, ping and look for the database with a native driver client
+pub async fn check_native(
+    checker: &mut Checker,
+    client: &Client,
+    database: &str,
+    timeout_secs: u64,
+) -> Option<()> {
+    checker
+        .run("auth", async {
+            let status = native::run_command(
+                client,
+                "admin",
+                doc! { "connectionStatus": 1 },
+                timeout_secs,
+            )
+            .await?;
+            Ok(((), auth_detail(&status)))
+        })
+        .await?;
+    checker
+        .run("ping", async {
+            native::run_command(client, "admin", doc! { "ping": 1 }, timeout_secs).await?;
+            Ok(((), String::new()))
+        })
+        .await?;
+    checker
+        .run("database", async {
+            let collections = native::list_collections(client, database, timeout_secs).await?;
+            database_detail(database, collections.len())
+        })
+        .await
+}
+
+/// Who a `connectionStatus` reply says is authenticated
+pub fn auth_detail(status: &serde_json::Value) -> String {
+    let users: Vec<String> = status
+        .pointer("/authInfo/authenticatedUsers")
+        .and_then(serde_json::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|user| {
+            let name = user.get("user")?.as_str()?;
+            let db = user.get("db")?.as_str()?;
+            Some(format!("{name}@{db}"))
+        })
+        .collect();
+
+    if users.is_empty() {
+        "not authenticated (no credentials configured)".to_string()
+    } else {
+        format!("authenticated as {}", users.join(", "))
+    }
+}
+
+/// A database exists once it has a collection; MongoDB creates them implicitly
+pub fn database_detail(database: &str, collections: usize) -> Result<((), String)> {
+    if collections == 0 {
+        bail!("Database '{database}' does not exist or has no collections");
+    }
+    Ok(((), format!("'{database}' has {collections} collections")))
+}
+
+/// Health check result for one connection
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckReport {
+    pub connection: String,
+    pub connection_type: String,
+    pub database: String,
+    pub ok: bool,
+    pub steps: Vec<CheckStep>,
+}
+
+impl CheckReport {
+    pub fn new(connection: &dyn MongoConnection, database: &str, steps: Vec<CheckStep>) -> Self {
+        Self {
+            connection: connection.name().to_string(),
+            connection_type: connection.connection_type().to_string(),
+            database: database.to_string(),
+            ok: steps.iter().all(|step| step.status == StepStatus::Ok),
+            steps,
+        }
+    }
+
+    /// Plain text for the terminal
+    pub fn to_text(&self) -> String {
+        let status = if self.ok { "OK" } else { "FAILED" };
+        let mut text = if self.database.is_empty() {
+            format!("{} ({}): {status}\n", self.connection, self.connection_type)
+        } else {
+            format!(
+                "{} ({}, database {}): {status}\n",
+                self.connection, self.connection_type, self.database
+            )
+        };
+        for step in &self.steps {
+            let duration = match step.status {
+                StepStatus::Skipped => String::new(),
+                _ => format!("{} ms", step.duration_ms),
+            };
+            let line = format!(
+                "  {:<8} {:<14} {:>9}  {}",
+                step.status.as_str(),
+                step.name,
+                duration,
+                step.detail
+            );
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// Run the checks of one connection against `database`
+pub async fn check_connection(
+    connection: &dyn MongoConnection,
+    database: &str,
+    timeout_secs: u64,
+) -> CheckReport {
+    let steps = connection.check(database, timeout_secs).await;
+    CheckReport::new(connection, database, steps)
+}
+
+/// Check a running connection (`check_connection` tool). The `config` step re-reads
+/// the config files, so an edit that breaks them, or drops the connection, shows up
+/// before the next reload or restart.
+pub async fn check_running(
+    connection: &dyn MongoConnection,
+    database: &str,
+    timeout_secs: u64,
+) -> CheckReport {
+    let started = Instant::now();
+    let config = tokio::task::spawn_blocking(Config::load_existing)
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|config| config);
+    check_running_with(config, started, connection, database, timeout_secs).await
+}
+
+async fn check_running_with(
+    config: Result<Config>,
+    started: Instant,
+    connection: &dyn MongoConnection,
+    database: &str,
+    timeout_secs: u64,
+) -> CheckReport {
+    let name = connection.name();
+    let config_step = match config.and_then(|config| {
+        config.validate_unique_names()?;
+        Ok(config)
+    }) {
+        Ok(config)
+            if configured_connections(&config)
+                .iter()
+                .any(|(n, _, _)| *n == name) =>
+        {
+            CheckStep::new(
+                "config",
+                StepStatus::Ok,
+                started.elapsed(),
+                format!("{} connections", configured_count(&config)),
+            )
+        }
+        Ok(_) => CheckStep::new(
+            "config",
+            StepStatus::Failed,
+            started.elapsed(),
+            format!("Connection '{name}' is no longer in the config files"),
+        ),
+        Err(e) => CheckStep::new(
+            "config",
+            StepStatus::Failed,
+            started.elapsed(),
+            format!("{e:#}"),
+        ),
+    };
+    if config_step.status != StepStatus::Ok {
+        return CheckReport::new(connection, database, vec![config_step]);
+    }
+
+    let mut report = check_connection(connection, database, timeout_secs).await;
+    report.steps.insert(0, config_step);
+    report
+}
+
+/// Load the config and check every connection, or only `name` (`--check`).
+/// Loading the config and building the connection, including the kubeconfig, is
+/// reported as the `config` step. Each connection is built on its own, so a broken
+/// kubeconfig only fails the connections that need the cluster. A missing config
+/// file fails the `config` step; unlike at startup, no example config is written.
+pub async fn check_configured(name: Option<&str>) -> Result<Vec<CheckReport>> {
+    let started = Instant::now();
+    check_config(Config::load_existing(), name, started).await
+}
+
+async fn check_config(
+    config: Result<Config>,
+    name: Option<&str>,
+    started: Instant,
+) -> Result<Vec<CheckReport>> {
+    let config = match config.and_then(|config| {
+        config.validate_unique_names()?;
+        Ok(config)
+    }) {
+        Ok(config) => config,
+        Err(e) => {
+            let step = CheckStep::new(
+                "config",
+                StepStatus::Failed,
+                started.elapsed(),
+                format!("{e:#}"),
+            );
+            return Ok(vec![CheckReport {
+                connection: name.unwrap_or("config").to_string(),
+                connection_type: "unknown".to_string(),
+                database: String::new(),
+                ok: false,
+                steps: vec![step],
+            }]);
+        }
+    };
+    let load_time = started.elapsed();
+
+    let configured = configured_connections(&config);
+    let names: Vec<&str> = match name {
+        Some(name) if !configured.iter().any(|(n, _, _)| *n == name) => bail!(
+            "Connection '{name}' not found. Available: {}",
+            configured
+                .iter()
+                .map(|(n, _, _)| *n)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Some(name) => vec![name],
+        None => configured.iter().map(|(n, _, _)| *n).collect(),
+    };
+
+    let mut builder = RegistryBuilder::new();
+    let mut reports = Vec::with_capacity(names.len());
+    for (name, connection_type, database) in configured {
+        if !names.contains(&name) {
+            continue;
+        }
+        let started = Instant::now();
+        let built = builder.build(&single_connection(&config, name)).await;
+        let connection = built.and_then(|(registry, _)| {
+            registry
+                .get(name)
+                .ok_or_else(|| anyhow::anyhow!("Connection '{name}' was not built"))
+        });
+        let report = match connection {
+            Ok(connection) => {
+                let config_step = CheckStep::new(
+                    "config",
+                    StepStatus::Ok,
+                    load_time + started.elapsed(),
+                    format!("{} connections", configured_count(&config)),
+                );
+                let timeout_secs = connection.settings().timeout_secs(None)?;
+                let mut report =
+                    check_connection(&*connection, connection.database_name(), timeout_secs).await;
+                report.steps.insert(0, config_step);
+                report
+            }
+            Err(e) => {
+                let steps = vec![CheckStep::new(
+                    "config",
+                    StepStatus::Failed,
+                    load_time + started.elapsed(),
+                    format!("{e:#}"),
+                )];
+                CheckReport {
+                    connection: name.to_string(),
+                    connection_type: connection_type.to_string(),
+                    database: database.to_string(),
+                    ok: false,
+                    steps,
+                }
+            }
+        };
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+/// Name, type and default database of every configured connection, in config order
+fn configured_connections(config: &Config) -> Vec<(&str, &'static str, &str)> {
+    let namespaces = config.namespaces.iter().map(|ns| {
+        (
+            ns.namespace_name.as_str(),
+            "kubernetes",
+            ns.database_name.as_str(),
+        )
+    });
+    let direct = config
+        .connections
+        .iter()
+        .map(|conn| (conn.name.as_str(), "direct", conn.database_name.as_str()));
+    let ssh = config
+        .ssh_connections
+        .iter()
+        .map(|conn| (conn.name.as_str(), "ssh", conn.database_name.as_str()));
+    namespaces.chain(direct).chain(ssh).collect()
+}
+
+const fn configured_count(config: &Config) -> usize {
+    config.namespaces.len() + config.connections.len() + config.ssh_connections.len()
+}
+
+/// `config` with only the connection called `name`
+fn single_connection(config: &Config, name: &str) -> Config {
+    let mut single = config.clone();
+    single.namespaces.retain(|ns| ns.namespace_name == name);
+    single.connections.retain(|conn| conn.name == name);
+    single.ssh_connections.retain(|conn| conn.name == name);
+    single
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_checker_skips_after_failure() {
+        let mut checker = Checker::new(&["first", "second", "third"]);
+        let value = checker
+            .run("first", async { Ok((42, "found".to_string())) })
+            .await;
+        assert_eq!(value, Some(42));
+        let failed: Option<()> = checker.run("second", async { bail!("unreachable") }).await;
+        assert!(failed.is_none());
+
+        let steps = checker.into_steps();
+        let statuses: Vec<_> = steps.iter().map(|step| (step.name, step.status)).collect();
+        assert_eq!(
+            statuses,
+            [
+                ("first", StepStatus::Ok),
+                ("second", StepStatus::Failed),
+                ("third", StepStatus::Skipped),
+            ]
+        );
+        assert_eq!(steps[1].detail, "unreachable");
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_config_failures() {
+        let reports = check_config(Err(anyhow::anyhow!("bad yaml")), None, Instant::now())
+            .await
+            .unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(!reports[0].ok);
+        assert_eq!(reports[0].steps[0].status, StepStatus::Failed);
+        assert_eq!(reports[0].steps[0].detail, "bad yaml");
+
+        // A broken kubeconfig fails only the connections that need the cluster
+        let config: Config = serde_yaml::from_str(
+            r"
+kubeconfig_path: /nonexistent/kubeconfig
+namespaces:
+  - namespace_name: prod
+    deployment_name: mongodb
+    database_name: app
+connections:
+  - name: local
+    mongodb_url: mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=200
+    database_name: app
+    default_timeout_secs: 1
+",
+        )
+        .unwrap();
+        let reports = check_config(Ok(config), None, Instant::now())
+            .await
+            .unwrap();
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].connection, "prod");
+        assert_eq!(reports[0].steps[0].status, StepStatus::Failed);
+        assert_eq!(reports[1].connection, "local");
+        assert_eq!(reports[1].steps[0].status, StepStatus::Ok);
+        assert!(reports[1].steps.len() > 1);
+    }
+
+    #[tokio::test]
+    async fn test_check_running_reports_config_step() {
+        let yaml = |name: &str| {
+            format!(
+                "connections:\n  - name: {name}\n    mongodb_url: mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=200\n    database_name: app\n    default_timeout_secs: 1\n"
+            )
+        };
+        let config: Config = serde_yaml::from_str(&yaml("local")).unwrap();
+        let (registry, _) = RegistryBuilder::new().build(&config).await.unwrap();
+        let connection = registry.get("local").unwrap();
+        let check = |config: Result<Config>| {
+            check_running_with(config, Instant::now(), &*connection, "app", 1)
+        };
+
+        let report = check(Ok(config.clone())).await;
+        assert!(!report.ok);
+        assert_eq!(report.steps[0].name, "config");
+        assert_eq!(report.steps[0].status, StepStatus::Ok);
+        assert_eq!(report.steps[0].detail, "1 connections");
+        assert!(report.steps.len() > 1);
+
+        let report = check(Err(anyhow::anyhow!("Configuration file not found: x.yaml"))).await;
+        assert_eq!(report.steps.len(), 1);
+        assert_eq!(report.steps[0].status, StepStatus::Failed);
+        assert_eq!(
+            report.steps[0].detail,
+            "Configuration file not found: x.yaml"
+        );
+
+        let renamed: Config = serde_yaml::from_str(&yaml("other")).unwrap();
+        let report = check(Ok(renamed)).await;
+        assert_eq!(report.steps.len(), 1);
+        assert!(
+            report.steps[0]
+                .detail
+                .contains("no longer in the config files")
+        );
+    }
+
+    #[test]
+    fn test_auth_detail() {
+        let status = json!({
+            "authInfo": { "authenticatedUsers": [{ "user": "reader", "db": "admin" }] },
+            "ok": 1
+        });
+        assert_eq!(auth_detail(&status), "authenticated as reader@admin");
+        assert_eq!(
+            auth_detail(&json!({ "authInfo": { "authenticatedUsers": [] } })),
+            "not authenticated (no credentials configured)"
+        );
+    }
+}
@@ -1,27 +1,37 @@
 //! Direct MongoDB connection implementation.
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result};
 use async_trait::async_trait;
-use futures::TryStreamExt;
-use mongodb::{Client, bson::Document, options::ClientOptions};
-use std::time::Duration;
+use mongodb::{Client, bson::doc, options::ClientOptions};
+use std::sync::Arc;
 use tokio::sync::OnceCell;
 
-use crate::config::DirectConnectionConfig;
+use crate::access;
+use crate::collections::CollectionInfo;
+use crate::config::{DirectConnectionConfig, QuerySettings};
 use crate::connection::MongoConnection;
-use crate::mongodb::{QueryOperation, QueryOptions};
+use crate::diagnostics::{self, CheckStep, Checker};
+use crate::explain::{self, ExplainVerbosity};
+use crate::indexes::IndexInfo;
+use crate::k8s_client::K8sClient;
+use crate::mongodb::{CLIENT_TIMEOUT_GRACE_SECS, QueryOperation, QueryOptions};
+use crate::native;
+use crate::results::QueryResult;
 
 /// Direct MongoDB connection via URL.
 /// Uses the mongodb crate to connect directly without Kubernetes.
 pub struct DirectConnection {
     config: DirectConnectionConfig,
+    /// Needed only when credentials come from Kubernetes Secrets
+    k8s_client: Option<Arc<K8sClient>>,
     client: OnceCell<Client>,
 }
 
 impl DirectConnection {
-    pub fn new(config: DirectConnectionConfig) -> Self {
+    pub fn new(config: DirectConnectionConfig, k8s_client: Option<Arc<K8sClient>>) -> Self {
         Self {
             config,
+            k8s_client,
             client: OnceCell::new(),
         }
     }
@@ -39,116 +49,26 @@ impl DirectConnection {
                     .await
                     .context("Failed to parse MongoDB connection URL")?;
 
-                // Set reasonable defaults
-                client_options.connect_timeout = Some(Duration::from_secs(10));
-                client_options.server_selection_timeout = Some(Duration::from_secs(30));
-
-                Client::with_options(client_options).context("Failed to create MongoDB client")
-            })
-            .await
-    }
-
-    async fn execute_operation(
-        &self,
-        collection: &mongodb::Collection<Document>,
-        operation: &QueryOperation,
-        query_str: &str,
-        options: &QueryOptions,
-    ) -> Result<String> {
-        match operation {
-            QueryOperation::Find => {
-                let filter: Document = serde_json::from_str(query_str)
-                    .context("Invalid query JSON for find operation")?;
-
-                // Build find options
-                let mut find_options = mongodb::options::FindOptions::default();
-
-                if let Some(limit) = options.limit {
-                    find_options.limit = Some(i64::from(limit));
-                }
-
-                if let Some(sort_str) = &options.sort {
-                    let sort: Document =
-                        serde_json::from_str(sort_str).context("Invalid sort JSON")?;
-                    find_options.sort = Some(sort);
-                }
-
-                if let Some(projection_str) = &options.projection {
-                    let projection: Document =
-                        serde_json::from_str(projection_str).context("Invalid projection JSON")?;
-                    find_options.projection = Some(projection);
-                }
-
-                let cursor = collection
-                    .find(filter)
-                    .with_options(find_options)
-                    .await
-                    .context("Find query failed")?;
-                let docs: Vec<Document> = cursor
-                    .try_collect()
-                    .await
-                    .context("Failed to collect find results")?;
-                serde_json::to_string(&docs).context("Failed to serialize find results")
-            }
-            QueryOperation::Aggregate => {
-                let pipeline: Vec<Document> =
-                    serde_json::from_str(query_str).context("Invalid aggregation pipeline JSON")?;
-                let cursor = collection
-                    .aggregate(pipeline)
+                self.config
+                    .credentials
+                    .apply(
+                        &self.config.mongodb_url,
+                        &mut client_options,
+                        self.k8s_client.as_deref(),
+                    )
                     .await
-                    .context("Aggregate query failed")?;
-                let docs: Vec<Document> = cursor
-                    .try_collect()
-                    .await
-                    .context("Failed to collect aggregate results")?;
-                serde_json::to_string(&docs).context("Failed to serialize aggregate results")
-            }
-            QueryOperation::CountDocuments => {
-                let filter: Document = serde_json::from_str(query_str)
-                    .context("Invalid query JSON for countDocuments")?;
-                let count = collection
-                    .count_documents(filter)
-                    .await
-                    .context("CountDocuments query failed")?;
-                Ok(count.to_string())
-            }
-            QueryOperation::Distinct => {
-                // Get field from options or legacy format
-                let field = if let Some(field) = &options.distinct_field {
-                    field.clone()
-                } else {
-                    let params: serde_json::Value =
-                        serde_json::from_str(query_str).context("Invalid distinct query JSON")?;
-                    params
-                        .get("field")
-                        .and_then(|v| v.as_str())
-                        .ok_or_else(|| anyhow!("Distinct requires 'distinct_field' parameter"))?
-                        .to_string()
-                };
+                    .context("Failed to resolve connection credentials")?;
+                self.config.settings.read.apply(&mut client_options);
 
-                // Get filter from query or legacy format
-                let filter: Document = if options.distinct_field.is_some() {
-                    // Query is the filter directly
-                    serde_json::from_str(query_str).context("Invalid filter JSON for distinct")?
-                } else {
-                    // Legacy format - extract from {"field": ..., "query": ...}
-                    let params: serde_json::Value = serde_json::from_str(query_str)?;
-                    params
-                        .get("query")
-                        .map(|v| serde_json::from_value(v.clone()))
-                        .transpose()
-                        .context("Invalid filter in distinct query")?
-                        .unwrap_or_default()
+                // A combined cert/key file only needs to exist while the client is created
+                let _combined_pem = match &self.config.tls {
+                    Some(tls) => tls.apply(&mut client_options)?,
+                    None => None,
                 };
 
-                let values = collection
-                    .distinct(&field, filter)
-                    .await
-                    .context("Distinct query failed")?;
-
-                serde_json::to_string(&values).context("Failed to serialize distinct results")
-            }
-        }
+                native::connect(client_options)
+            })
+            .await
     }
 }
 
@@ -170,51 +90,108 @@ impl MongoConnection for DirectConnection {
         &self.config.database_name
     }
 
-    async fn list_collections(&self) -> Result<Vec<String>> {
+    fn settings(&self) -> &QuerySettings {
+        &self.config.settings
+    }
+
+    async fn list_databases(&self, timeout_secs: u64) -> Result<serde_json::Value> {
         let client = self.get_client().await?;
-        let db = client.database(&self.config.database_name);
+        native::run_command(
+            client,
+            "admin",
+            access::list_databases_command(),
+            timeout_secs,
+        )
+        .await
+    }
 
-        let mut collections = db
-            .list_collection_names()
-            .await
-            .context("Failed to list collections")?;
+    async fn list_collections(
+        &self,
+        database: &str,
+        timeout_secs: u64,
+    ) -> Result<Vec<CollectionInfo>> {
+        let client = self.get_client().await?;
+        native::list_collections(client, database, timeout_secs).await
+    }
 
-        // Sort for deterministic output
-        collections.sort();
+    async fn list_indexes(
+        &self,
+        database: &str,
+        collection: &str,
+        timeout_secs: u64,
+    ) -> Result<Vec<IndexInfo>> {
+        let client = self.get_client().await?;
+        native::list_indexes(client, database, collection, timeout_secs).await
+    }
 
-        Ok(collections)
+    async fn database_stats(&self, database: &str, timeout_secs: u64) -> Result<serde_json::Value> {
+        let client = self.get_client().await?;
+        native::run_command(client, database, doc! { "dbStats": 1 }, timeout_secs).await
     }
 
     async fn execute_query(
         &self,
+        database: &str,
         collection: &str,
         operation: &QueryOperation,
         query: &str,
         options: &QueryOptions,
         timeout_secs: u64,
-    ) -> Result<String> {
+    ) -> Result<QueryResult> {
         let client = self.get_client().await?;
-        let db = client.database(&self.config.database_name);
-        let coll = db.collection::<Document>(collection);
 
         tracing::info!(
             "Executing {:?} on {}.{} via direct connection '{}'",
             operation,
-            self.config.database_name,
+            database,
             collection,
             self.config.name
         );
 
-        // Execute with timeout
-        match tokio::time::timeout(
-            Duration::from_secs(timeout_secs),
-            self.execute_operation(&coll, operation, query, options),
+        native::execute_query(
+            client,
+            database,
+            collection,
+            operation,
+            query,
+            options,
+            timeout_secs,
         )
         .await
-        {
-            Ok(Ok(result)) => Ok(result),
-            Ok(Err(e)) => Err(e), // Preserve original error
-            Err(_) => Err(anyhow!("Query timed out after {} seconds", timeout_secs)),
+    }
+
+    async fn explain_query(
+        &self,
+        database: &str,
+        collection: &str,
+        operation: &QueryOperation,
+        query: &str,
+        options: &QueryOptions,
+        verbosity: ExplainVerbosity,
+        timeout_secs: u64,
+    ) -> Result<serde_json::Value> {
+        let options = options.with_max_time(timeout_secs);
+        let command = explain::explain_command(collection, operation, query, &options, verbosity)?;
+        let client = self.get_client().await?;
+        native::run_command(
+            client,
+            database,
+            command,
+            timeout_secs + CLIENT_TIMEOUT_GRACE_SECS,
+        )
+        .await
+    }
+
+    async fn check(&self, database: &str, timeout_secs: u64) -> Vec<CheckStep> {
+        let mut checker = Checker::new(&["client", "auth", "ping", "database"]);
+        let client = checker
+            .run("client", async {
+                Ok((self.get_client().await?.clone(), String::new()))
+            })
+            .await;
+        if let Some(client) = client {
+            diagnostics::check_native(&mut checker, &client, database, timeout_secs).await;
         }
+        checker.into_steps()
     }
 }
@@ -0,0 +1,130 @@
+//! MongoDB Extended JSON parsing and formatting.
+//!
+//! Queries accept both canonical and relaxed Extended JSON, so `{"$oid": ...}` and
+//! `{"$date": ...}` become real BSON values. Results are rendered in the configured
+//! mode so both backends produce identical output.
+
+use anyhow::{Context, Result, anyhow};
+use mongodb::bson::{Bson, Document};
+use serde::{Deserialize, Serialize};
+
+/// Extended JSON flavour used when rendering query results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtJsonMode {
+    /// Native JSON numbers and ISO-8601 dates where lossless (`{"$date": "2024-01-01T00:00:00Z"}`)
+    #[default]
+    Relaxed,
+    /// Type-preserving wrappers for every value (`{"$numberInt": "1"}`)
+    Canonical,
+}
+
+impl ExtJsonMode {
+    /// Options object passed to mongosh's `EJSON.stringify`
+    pub const fn mongosh_options(self) -> &'static str {
+        match self {
+            Self::Relaxed => "{relaxed: true}",
+            Self::Canonical => "{relaxed: false}",
+        }
+    }
+
+    /// Convert a BSON value into Extended JSON in this mode
+    pub fn to_json(self, value: Bson) -> serde_json::Value {
+        match self {
+            Self::Relaxed => value.into_relaxed_extjson(),
+            Self::Canonical => value.into_canonical_extjson(),
+        }
+    }
+}
+
+/// Parse an Extended JSON string into a BSON value
+pub fn parse_value(json: &str, what: &str) -> Result<Bson> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).with_context(|| format!("Invalid {what} JSON: '{json}'"))?;
+    Bson::try_from(value).with_context(|| format!("Invalid Extended JSON in {what}: '{json}'"))
+}
+
+/// Parse an Extended JSON object into a BSON document
+pub fn parse_document(json: &str, what: &str) -> Result<Document> {
+    match parse_value(json, what)? {
+        Bson::Document(doc) => Ok(doc),
+        other => Err(anyhow!(
+            "{what} must be a JSON object, got {:?}",
+            other.element_type()
+        )),
+    }
+}
+
+/// Parse an Extended JSON array of pipeline stages
+pub fn parse_pipeline(json: &str) -> Result<Vec<Document>> {
+    match parse_value(json, "aggregation pipeline")? {
+        Bson::Array(stages) => stages
+            .into_iter()
+            .enumerate()
+            .map(|(i, stage)| match stage {
+                Bson::Document(doc) => Ok(doc),
+                _ => Err(anyhow!("Pipeline stage {i} must be a JSON object")),
+            })
+            .collect(),
+        _ => Err(anyhow!(
+            "Aggregation pipeline must be a JSON array of stages"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::oid::ObjectId;
+
+    #[test]
+    fn test_parse_document_extended_types() {
+        let doc = parse_document(
+            r#"{"_id": {"$oid": "65a1b2c3d4e5f60718293a4b"}, "at": {"$date": "2024-01-01T00:00:00Z"}}"#,
+            "query",
+        )
+        .unwrap();
+        assert_eq!(
+            doc.get_object_id("_id").unwrap(),
+            ObjectId::parse_str("65a1b2c3d4e5f60718293a4b").unwrap()
+        );
+        assert!(doc.get_datetime("at").is_ok());
+
+        // Canonical form is accepted as well
+        let doc = parse_document(r#"{"n": {"$numberLong": "42"}}"#, "query").unwrap();
+        assert_eq!(doc.get_i64("n").unwrap(), 42);
+    }
+
+    #[test]
+    fn test_parse_document_rejects_non_objects() {
+        assert!(parse_document("[]", "query").is_err());
+        assert!(parse_document("not json", "query").is_err());
+    }
+
+    #[test]
+    fn test_parse_pipeline() {
+        let pipeline = parse_pipeline(r#"[{"$match": {}}, {"$limit": 5}]"#).unwrap();
+        assert_eq!(pipeline.len(), 2);
+        assert!(parse_pipeline(r#"{"$match": {}}"#).is_err());
+        assert!(parse_pipeline("[1]").is_err());
+    }
+
+    #[test]
+    fn test_output_modes() {
+        let value = Bson::Int32(7);
+        assert_eq!(
+            ExtJsonMode::Relaxed.to_json(value.clone()),
+            serde_json::json!(7)
+        );
+        assert_eq!(
+            ExtJsonMode::Canonical.to_json(value),
+            serde_json::json!({"$numberInt": "7"})
+        );
+
+        let oid = ObjectId::parse_str("65a1b2c3d4e5f60718293a4b").unwrap();
+        assert_eq!(
+            ExtJsonMode::Relaxed.to_json(Bson::ObjectId(oid)),
+            serde_json::json!({"$oid": "65a1b2c3d4e5f60718293a4b"})
+        );
+    }
+}
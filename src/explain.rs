@@ -0,0 +1,255 @@
+//! Query plan explanation and summarisation.
+
+use anyhow::{Context, Result};
+use mongodb::bson::{Bson, Document, doc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::ejson;
+use crate::mongodb::{QueryOperation, QueryOptions};
+use crate::native::distinct_parts;
+use crate::validation::validate_read_only;
+
+/// Explain verbosity mode
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ExplainVerbosity {
+    /// Chosen plan only. Does not execute the query.
+    QueryPlanner,
+    /// Chosen plan plus execution statistics (docs examined, time). Executes the query.
+    #[default]
+    ExecutionStats,
+    /// Execution statistics for the chosen plan and all rejected candidate plans.
+    AllPlansExecution,
+}
+
+impl ExplainVerbosity {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::QueryPlanner => "queryPlanner",
+            Self::ExecutionStats => "executionStats",
+            Self::AllPlansExecution => "allPlansExecution",
+        }
+    }
+}
+
+/// Build the `explain` command document.
+/// The same document is run by the native driver and by mongosh `db.runCommand`.
+pub fn explain_command(
+    collection: &str,
+    operation: &QueryOperation,
+    query: &str,
+    options: &QueryOptions,
+    verbosity: ExplainVerbosity,
+) -> Result<Document> {
+    let parsed: Value = serde_json::from_str(query).context("Query is not valid JSON")?;
+    validate_read_only(&parsed, "query")?;
+
+    let mut explained = match operation {
+        QueryOperation::Find => {
+            let mut find = doc! {
+                "find": collection,
+                "filter": ejson::parse_document(query, "query")?,
+            };
+            if let Some(projection) = &options.projection {
+                let parsed: Value =
+                    serde_json::from_str(projection).context("Invalid projection JSON")?;
+                validate_read_only(&parsed, "projection")?;
+                find.insert(
+                    "projection",
+                    ejson::parse_document(projection, "projection")?,
+                );
+            }
+            if let Some(sort) = &options.sort {
+                find.insert("sort", ejson::parse_document(sort, "sort")?);
+            }
+            if let Some(limit) = options.limit {
+                find.insert("limit", i64::from(limit));
+            }
+            find
+        }
+        QueryOperation::Aggregate => {
+            let pipeline: Vec<Bson> = ejson::parse_pipeline(query)?
+                .into_iter()
+                .map(Bson::Document)
+                .collect();
+            doc! { "aggregate": collection, "pipeline": pipeline, "cursor": {} }
+        }
+        QueryOperation::CountDocuments => doc! {
+            "count": collection,
+            "query": ejson::parse_document(query, "query")?,
+        },
+        QueryOperation::Distinct => {
+            let (field, filter) = distinct_parts(query, options)?;
+            doc! { "distinct": collection, "key": field, "query": filter }
+        }
+    };
+
+    if let Some(ms) = options.max_time_ms {
+        explained.insert("maxTimeMS", i64::try_from(ms).unwrap_or(i64::MAX));
+    }
+
+    Ok(doc! { "explain": explained, "verbosity": verbosity.as_str() })
+}
+
+/// Find the first object stored under `key` anywhere in the explain output.
+/// Aggregations nest the planner under `stages[0].$cursor`, sharded clusters under `shards`.
+fn find_key<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => map
+            .get(key)
+            .or_else(|| map.values().find_map(|v| find_key(v, key))),
+        Value::Array(items) => items.iter().find_map(|v| find_key(v, key)),
+        _ => None,
+    }
+}
+
+/// Walk a plan tree collecting stage names (outermost first) and index names
+fn walk_plan(plan: &Value, stages: &mut Vec<String>, indexes: &mut Vec<String>) {
+    // Slot-based engine plans wrap the classic tree in `queryPlan`
+    let plan = plan.get("queryPlan").unwrap_or(plan);
+
+    if let Some(stage) = plan.get("stage").and_then(Value::as_str) {
+        stages.push(stage.to_string());
+    }
+    if let Some(index) = plan.get("indexName").and_then(Value::as_str)
+        && !indexes.iter().any(|i| i == index)
+    {
+        indexes.push(index.to_string());
+    }
+    if let Some(input) = plan.get("inputStage") {
+        walk_plan(input, stages, indexes);
+    }
+    if let Some(inputs) = plan.get("inputStages").and_then(Value::as_array) {
+        for input in inputs {
+            walk_plan(input, stages, indexes);
+        }
+    }
+}
+
+/// Condense raw explain output into the facts needed to judge a plan
+pub fn summarize(explain: &Value) -> Value {
+    let mut stages = Vec::new();
+    let mut indexes = Vec::new();
+    if let Some(plan) = find_key(explain, "winningPlan") {
+        walk_plan(plan, &mut stages, &mut indexes);
+    }
+
+    let stats = find_key(explain, "executionStats");
+    let stat = |key: &str| stats.and_then(|s| s.get(key)).cloned();
+
+    let collscan = stages.iter().any(|s| s == "COLLSCAN");
+    let mut warnings = Vec::new();
+    if collscan {
+        warnings.push(
+            "COLLSCAN: the query scans the whole collection. Add a filter on an indexed field \
+             (see list_indexes) or expect slow execution on large collections.",
+        );
+    }
+    if stages.iter().any(|s| s == "SORT") {
+        warnings.push("In-memory SORT: no index supports the requested sort order.");
+    }
+
+    serde_json::json!({
+        "winning_plan": stages.join(" <- "),
+        "indexes_used": indexes,
+        "collection_scan": collscan,
+        "docs_examined": stat("totalDocsExamined"),
+        "keys_examined": stat("totalKeysExamined"),
+        "docs_returned": stat("nReturned"),
+        "execution_time_ms": stat("executionTimeMillis"),
+        "warnings": warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_explain_command_find() {
+        let opts = QueryOptions {
+            sort: Some(r#"{"createdAt": -1}"#.to_string()),
+            limit: Some(5),
+            max_time_ms: Some(30_000),
+            ..Default::default()
+        };
+        let cmd = explain_command(
+            "users",
+            &QueryOperation::Find,
+            r#"{"status": "active"}"#,
+            &opts,
+            ExplainVerbosity::ExecutionStats,
+        )
+        .unwrap();
+
+        // The command name must be the first key of the explained document
+        let explained = cmd.get_document("explain").unwrap();
+        assert_eq!(explained.keys().next().unwrap(), "find");
+        assert_eq!(explained.get_str("find").unwrap(), "users");
+        assert_eq!(explained.get_i64("limit").unwrap(), 5);
+        assert_eq!(explained.get_i64("maxTimeMS").unwrap(), 30_000);
+        assert_eq!(cmd.get_str("verbosity").unwrap(), "executionStats");
+    }
+
+    #[test]
+    fn test_explain_command_rejects_write_stages() {
+        let opts = QueryOptions::default();
+        assert!(
+            explain_command(
+                "users",
+                &QueryOperation::Aggregate,
+                r#"[{"$out": "copy"}]"#,
+                &opts,
+                ExplainVerbosity::QueryPlanner,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_summarize_collscan() {
+        let explain = json!({
+            "queryPlanner": {
+                "winningPlan": {"stage": "COLLSCAN", "filter": {"status": {"$eq": "active"}}}
+            },
+            "executionStats": {
+                "nReturned": 10,
+                "executionTimeMillis": 42,
+                "totalKeysExamined": 0,
+                "totalDocsExamined": 50000
+            }
+        });
+        let summary = summarize(&explain);
+        assert_eq!(summary["winning_plan"], "COLLSCAN");
+        assert_eq!(summary["collection_scan"], true);
+        assert_eq!(summary["docs_examined"], 50000);
+        assert_eq!(summary["docs_returned"], 10);
+        assert_eq!(summary["warnings"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_summarize_index_scan_in_aggregate() {
+        let explain = json!({
+            "stages": [{
+                "$cursor": {
+                    "queryPlanner": {
+                        "winningPlan": {
+                            "queryPlan": {
+                                "stage": "FETCH",
+                                "inputStage": {"stage": "IXSCAN", "indexName": "status_1"}
+                            }
+                        }
+                    }
+                }
+            }]
+        });
+        let summary = summarize(&explain);
+        assert_eq!(summary["winning_plan"], "FETCH <- IXSCAN");
+        assert_eq!(summary["indexes_used"], json!(["status_1"]));
+        assert_eq!(summary["collection_scan"], false);
+        assert!(summary["docs_examined"].is_null());
+    }
+}
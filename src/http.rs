@@ -0,0 +1,397 @@
+//! Streamable HTTP transport (MCP over HTTP with SSE).
+//!
+//! Every client session gets its own `McpServer` handle; all of them share the
+//! connection registry, page tokens, schema cache and audit log.
+//!
+//! Requests must name an allowed `Host` and, when browsers send one, `Origin`,
+//! so a web page can't reach the server through DNS rebinding.
+
+use anyhow::{Context, Result, bail};
+use axum::{
+    Router,
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use rmcp::transport::streamable_http_server::{
+    StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use crate::mcp::McpServer;
+
+fn default_bind() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 8080))
+}
+
+fn default_path() -> String {
+    "/mcp".to_string()
+}
+
+/// HTTP transport settings (top-level `http` section)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// Serve over HTTP instead of stdio (also enabled with `--http`)
+    #[serde(default)]
+    pub enabled: bool,
+    /// Address to listen on (default 127.0.0.1:8080)
+    #[serde(default = "default_bind")]
+    pub bind: SocketAddr,
+    /// URL path of the MCP endpoint (default /mcp)
+    #[serde(default = "default_path")]
+    pub path: String,
+    /// Token clients must send as `Authorization: Bearer <token>`, taken literally
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+    /// Environment variable holding the token, instead of `bearer_token`
+    #[serde(default)]
+    pub bearer_token_env: Option<String>,
+    /// Host names clients may use besides loopback names and the bind address.
+    /// Unchecked when empty and bound to a non-loopback address.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// Browser origins allowed besides loopback ones, e.g. `https://app.example.com`
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: default_bind(),
+            path: default_path(),
+            bearer_token: None,
+            bearer_token_env: None,
+            allowed_hosts: Vec::new(),
+            allowed_origins: Vec::new(),
+        }
+    }
+}
+
+impl HttpConfig {
+    /// The token clients must send, if any. A variable named by `bearer_token_env`
+    /// must be set: falling back to no token or a placeholder would open the server.
+    pub fn token(&self) -> Result<Option<String>> {
+        let token = match (&self.bearer_token, &self.bearer_token_env) {
+            (Some(_), Some(_)) => {
+                bail!("Set only one of http.bearer_token and http.bearer_token_env")
+            }
+            (Some(token), None) => token.clone(),
+            (None, Some(var)) => std::env::var(var).with_context(|| {
+                format!("Environment variable {var} (http.bearer_token_env) is not set")
+            })?,
+            (None, None) => return Ok(None),
+        };
+        if token.trim().is_empty() {
+            bail!("The HTTP bearer token is empty");
+        }
+        Ok(Some(token))
+    }
+
+    /// Refuse to expose an unauthenticated server beyond the local machine
+    pub fn validate(&self) -> Result<()> {
+        if !self.path.starts_with('/') {
+            bail!("http.path must start with '/', got '{}'", self.path);
+        }
+        if self.token()?.is_none() && !self.bind.ip().is_loopback() {
+            bail!(
+                "http.bearer_token is required when binding to a non-loopback address ({})",
+                self.bind
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Expected token, stored as a digest so comparisons take the same time for any input
+#[derive(Clone)]
+struct BearerAuth {
+    digest: Option<[u8; 32]>,
+}
+
+impl BearerAuth {
+    fn new(token: Option<&str>) -> Self {
+        Self {
+            digest: token.map(|t| Sha256::digest(t.as_bytes()).into()),
+        }
+    }
+
+    /// Whether an `Authorization` header value is accepted
+    fn accepts(&self, authorization: Option<&str>) -> bool {
+        let Some(expected) = &self.digest else {
+            return true;
+        };
+        let Some(token) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+            return false;
+        };
+        let actual: [u8; 32] = Sha256::digest(token.trim().as_bytes()).into();
+        actual
+            .iter()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    }
+}
+
+/// Host and Origin headers accepted from clients
+#[derive(Clone)]
+struct AllowedOrigins {
+    /// None accepts any host
+    hosts: Option<Vec<String>>,
+    origins: Vec<String>,
+}
+
+impl AllowedOrigins {
+    fn new(config: &HttpConfig) -> Self {
+        let hosts =
+            (config.bind.ip().is_loopback() || !config.allowed_hosts.is_empty()).then(|| {
+                config
+                    .allowed_hosts
+                    .iter()
+                    .map(|host| host.to_ascii_lowercase())
+                    .chain(std::iter::once(config.bind.ip().to_string()))
+                    .collect()
+            });
+        Self {
+            hosts,
+            origins: config
+                .allowed_origins
+                .iter()
+                .map(|origin| origin.trim_end_matches('/').to_ascii_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Whether a `Host` header value is accepted
+    fn accepts_host(&self, host: Option<&str>) -> bool {
+        let Some(hosts) = &self.hosts else {
+            return true;
+        };
+        let Some(name) = host.map(host_name) else {
+            return false;
+        };
+        is_loopback_name(&name) || hosts.contains(&name)
+    }
+
+    /// Whether an `Origin` header value is accepted; clients other than browsers send none
+    fn accepts_origin(&self, origin: Option<&str>) -> bool {
+        let Some(origin) = origin else {
+            return true;
+        };
+        let origin = origin.trim_end_matches('/').to_ascii_lowercase();
+        if self.origins.contains(&origin) {
+            return true;
+        }
+        origin.split_once("://").is_some_and(|(scheme, host)| {
+            matches!(scheme, "http" | "https") && is_loopback_name(&host_name(host))
+        })
+    }
+}
+
+/// Host name of a `host[:port]` value, lowercased, without IPv6 brackets
+fn host_name(host: &str) -> String {
+    let host = host.trim();
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or(rest),
+        None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+    };
+    name.to_ascii_lowercase()
+}
+
+fn is_loopback_name(name: &str) -> bool {
+    name == "localhost" || name.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+async fn check_origin(
+    State(allowed): State<Arc<AllowedOrigins>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    // HTTP/2 clients send the host as the URI authority instead
+    let host = headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| {
+            request
+                .uri()
+                .authority()
+                .map(|authority| authority.as_str())
+        });
+    let origin = headers
+        .get(header::ORIGIN)
+        .and_then(|value| value.to_str().ok());
+
+    if !allowed.accepts_host(host) {
+        return (StatusCode::FORBIDDEN, "Host not allowed").into_response();
+    }
+    if !allowed.accepts_origin(origin) {
+        return (StatusCode::FORBIDDEN, "Origin not allowed").into_response();
+    }
+    next.run(request).await
+}
+
+async fn require_bearer(
+    State(auth): State<Arc<BearerAuth>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+
+    if auth.accepts(authorization) {
+        next.run(request).await
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "Missing or invalid bearer token",
+        )
+            .into_response()
+    }
+}
+
+/// Serve MCP over streamable HTTP until Ctrl-C
+pub async fn serve(server: McpServer, config: &HttpConfig) -> Result<()> {
+    config.validate()?;
+    let token = config.token()?;
+
+    let service_config = StreamableHttpServerConfig::default();
+    // Open SSE streams would otherwise keep graceful shutdown waiting
+    let sessions = service_config.cancellation_token.clone();
+    let service = StreamableHttpService::new(
+        move || Ok(server.clone()),
+        Arc::new(LocalSessionManager::default()),
+        service_config,
+    );
+
+    let auth = Arc::new(BearerAuth::new(token.as_deref()));
+    let allowed = Arc::new(AllowedOrigins::new(config));
+    let router = Router::new()
+        .nest_service(&config.path, service)
+        .layer(middleware::from_fn_with_state(auth, require_bearer))
+        .layer(middleware::from_fn_with_state(allowed, check_origin));
+
+    let listener = tokio::net::TcpListener::bind(config.bind)
+        .await
+        .with_context(|| format!("Failed to bind HTTP transport to {}", config.bind))?;
+
+    tracing::info!(
+        "Serving MCP over HTTP at http://{}{} ({})",
+        config.bind,
+        config.path,
+        if token.is_some() {
+            "bearer token required"
+        } else {
+            "no authentication"
+        }
+    );
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move {
+            let _ = tokio::signal::ctrl_c().await;
+            sessions.cancel();
+        })
+        .await
+        .context("HTTP server failed")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bearer_auth() {
+        let auth = BearerAuth::new(Some("s3cret"));
+        assert!(auth.accepts(Some("Bearer s3cret")));
+        assert!(!auth.accepts(Some("Bearer wrong")));
+        assert!(!auth.accepts(Some("Basic s3cret")));
+        assert!(!auth.accepts(None));
+
+        let open = BearerAuth::new(None);
+        assert!(open.accepts(None));
+    }
+
+    #[test]
+    fn test_allowed_hosts_and_origins() {
+        let local = AllowedOrigins::new(&HttpConfig::default());
+        assert!(local.accepts_host(Some("localhost:8080")));
+        assert!(local.accepts_host(Some("127.0.0.1:8080")));
+        assert!(local.accepts_host(Some("[::1]:8080")));
+        // DNS rebinding: a page on evil.example resolving to 127.0.0.1
+        assert!(!local.accepts_host(Some("evil.example:8080")));
+        assert!(!local.accepts_host(None));
+
+        assert!(local.accepts_origin(None));
+        assert!(local.accepts_origin(Some("http://localhost:3000")));
+        assert!(!local.accepts_origin(Some("http://evil.example:8080")));
+        assert!(!local.accepts_origin(Some("null")));
+
+        let public = AllowedOrigins::new(&HttpConfig {
+            bind: "0.0.0.0:8080".parse().unwrap(),
+            allowed_origins: vec!["https://app.example.com/".to_string()],
+            ..Default::default()
+        });
+        assert!(public.accepts_host(Some("mcp.example.com")));
+        assert!(public.accepts_origin(Some("https://app.example.com")));
+        assert!(!public.accepts_origin(Some("https://evil.example")));
+
+        let named = AllowedOrigins::new(&HttpConfig {
+            bind: "0.0.0.0:8080".parse().unwrap(),
+            allowed_hosts: vec!["MCP.example.com".to_string()],
+            ..Default::default()
+        });
+        assert!(named.accepts_host(Some("mcp.example.com:8080")));
+        assert!(!named.accepts_host(Some("evil.example")));
+    }
+
+    #[test]
+    fn test_http_config_validate() {
+        let local = HttpConfig::default();
+        assert!(local.validate().is_ok());
+
+        let public = HttpConfig {
+            bind: "0.0.0.0:8080".parse().unwrap(),
+            ..Default::default()
+        };
+        assert!(public.validate().is_err());
+
+        let public_with_token = HttpConfig {
+            bearer_token: Some("s3cret".to_string()),
+            ..public
+        };
+        assert!(public_with_token.validate().is_ok());
+    }
+
+    #[test]
+    fn test_bearer_token_sources() {
+        let literal = HttpConfig {
+            bearer_token: Some("a$b~c".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(literal.token().unwrap().as_deref(), Some("a$b~c"));
+
+        let unset = HttpConfig {
+            bind: "0.0.0.0:8080".parse().unwrap(),
+            bearer_token_env: Some("RO_MONGODB_MCP_TEST_UNSET_TOKEN".to_string()),
+            ..Default::default()
+        };
+        assert!(unset.token().is_err());
+        assert!(unset.validate().is_err());
+
+        let empty = HttpConfig {
+            bearer_token: Some(String::new()),
+            ..Default::default()
+        };
+        assert!(empty.token().is_err());
+    }
+}
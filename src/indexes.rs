@@ -0,0 +1,101 @@
+//! Index descriptions shared by both backends.
+//!
+//! The native driver and mongosh `getIndexes()` both yield the server's index
+//! specification documents; they are normalised here into one shape.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+/// A collection index as reported to tools
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexInfo {
+    pub name: String,
+    /// Key pattern in index order, e.g. `{"status": 1, "createdAt": -1}`
+    pub keys: Value,
+    pub unique: bool,
+    pub sparse: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_filter: Option<Value>,
+    /// TTL in seconds for TTL indexes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expire_after_seconds: Option<i64>,
+    pub hidden: bool,
+}
+
+impl IndexInfo {
+    /// Build from an index specification (relaxed Extended JSON)
+    pub fn from_spec(spec: &Value) -> Result<Self> {
+        let name = spec
+            .get("name")
+            .and_then(Value::as_str)
+            .context("Index specification has no name")?
+            .to_string();
+        let flag = |key: &str| spec.get(key).and_then(Value::as_bool).unwrap_or(false);
+
+        Ok(Self {
+            keys: spec.get("key").cloned().unwrap_or(Value::Null),
+            unique: flag("unique"),
+            sparse: flag("sparse"),
+            hidden: flag("hidden"),
+            partial_filter: spec.get("partialFilterExpression").cloned(),
+            // Stored as int, long or double depending on how the index was created
+            expire_after_seconds: spec
+                .get("expireAfterSeconds")
+                .and_then(|v| v.as_i64().or_else(|| v.as_f64().map(|f| f as i64))),
+            name,
+        })
+    }
+}
+
+/// Normalise a list of index specifications
+pub fn from_specs(specs: &[Value]) -> Result<Vec<IndexInfo>> {
+    specs.iter().map(IndexInfo::from_spec).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_index_from_spec() {
+        let specs = vec![
+            json!({"v": 2, "key": {"_id": 1}, "name": "_id_"}),
+            json!({
+                "v": 2,
+                "key": {"email": 1},
+                "name": "email_1",
+                "unique": true,
+                "partialFilterExpression": {"deleted": false}
+            }),
+            json!({
+                "v": 2,
+                "key": {"createdAt": 1},
+                "name": "createdAt_1",
+                "expireAfterSeconds": 86400.0,
+                "sparse": true
+            }),
+        ];
+        let indexes = from_specs(&specs).unwrap();
+
+        assert_eq!(indexes[0].name, "_id_");
+        assert!(!indexes[0].unique);
+        assert!(indexes[1].unique);
+        assert_eq!(indexes[1].partial_filter, Some(json!({"deleted": false})));
+        assert_eq!(indexes[2].expire_after_seconds, Some(86400));
+        assert!(indexes[2].sparse);
+    }
+
+    #[test]
+    fn test_index_keys_keep_order() {
+        let spec = json!({"key": {"status": 1, "createdAt": -1}, "name": "status_1_createdAt_-1"});
+        let index = IndexInfo::from_spec(&spec).unwrap();
+        let keys: Vec<_> = index.keys.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["status", "createdAt"]);
+
+        let serialized = serde_json::to_value(&index).unwrap();
+        assert!(serialized.get("partial_filter").is_none());
+        assert!(serialized.get("expire_after_seconds").is_none());
+    }
+}
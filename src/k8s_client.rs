@@ -1,19 +1,50 @@
 //! Kubernetes API client for pod operations.
 
 use anyhow::{Context, Result, anyhow, bail};
-use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::api::core::v1::{EnvVar, Pod, Secret};
 use kube::{
     Client, Config,
     api::{Api, AttachedProcess, ListParams},
     config::KubeConfigOptions,
 };
+use std::net::SocketAddr;
 use std::time::Duration;
 use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
 
 pub struct K8sClient {
     client: Client,
 }
 
+/// A running pod whose containers are all ready
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthyPod {
+    pub name: String,
+    /// Cluster IP of the pod, once assigned
+    pub ip: Option<String>,
+}
+
+/// A local TCP listener forwarding each accepted connection to a pod port.
+/// The listener stops when this handle is dropped; connections already open keep running.
+pub struct PortForward {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl PortForward {
+    /// Local address clients should connect to
+    pub const fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for PortForward {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
 impl K8sClient {
     pub async fn new(kubeconfig_path: Option<String>) -> Result<Self> {
         let config = if let Some(path) = kubeconfig_path {
@@ -33,24 +64,36 @@ impl K8sClient {
         Ok(Self { client })
     }
 
-    /// Find a healthy pod for a deployment in a namespace
-    pub async fn find_healthy_pod(&self, namespace: &str, deployment_name: &str) -> Result<String> {
+    /// Version of the API server, which also proves the kubeconfig credentials work
+    pub async fn server_version(&self) -> Result<String> {
+        let info = self
+            .client
+            .apiserver_version()
+            .await
+            .context("Failed to reach the Kubernetes API server")?;
+        Ok(info.git_version)
+    }
+
+    /// Find the healthy pods matching a label selector in a namespace, sorted by name
+    pub async fn find_healthy_pods(
+        &self,
+        namespace: &str,
+        label_selector: &str,
+    ) -> Result<Vec<HealthyPod>> {
         let pods: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
 
-        // List pods with label selector
-        let label_selector = format!("app={deployment_name}");
-        let lp = ListParams::default().labels(&label_selector);
+        let lp = ListParams::default().labels(label_selector);
 
         let pod_list = pods.list(&lp).await.context("Failed to list pods")?;
 
         tracing::debug!(
-            "Found {} pods for deployment {} in namespace {}",
+            "Found {} pods for selector {} in namespace {}",
             pod_list.items.len(),
-            deployment_name,
+            label_selector,
             namespace
         );
 
-        // Find first healthy pod
+        let mut healthy = Vec::new();
         for pod in pod_list.items {
             let pod_name = pod.metadata.name.as_ref().context("Pod has no name")?;
 
@@ -68,23 +111,35 @@ impl K8sClient {
                     let all_ready = container_statuses.iter().all(|cs| cs.ready);
 
                     if all_ready {
-                        tracing::info!("Found healthy pod: {}", pod_name);
-                        return Ok(pod_name.clone());
+                        tracing::debug!("Found healthy pod: {}", pod_name);
+                        healthy.push(HealthyPod {
+                            name: pod_name.clone(),
+                            ip: status.pod_ip.clone(),
+                        });
+                        continue;
                     }
                     tracing::debug!("Pod {} has containers that are not ready", pod_name);
                 }
             }
         }
 
-        bail!("No healthy pods found for deployment '{deployment_name}' in namespace '{namespace}'")
+        if healthy.is_empty() {
+            bail!(
+                "No healthy pods found for selector '{label_selector}' in namespace '{namespace}'"
+            );
+        }
+        // StatefulSet pods sort by ordinal, so the choice is stable across calls
+        healthy.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(healthy)
     }
 
-    /// Execute a command in a pod with timeout
+    /// Execute a command in a pod with timeout.
+    /// Without a container name the pod's default container is used.
     pub async fn exec_command_in_pod(
         &self,
         namespace: &str,
         pod_name: &str,
-        container_name: &str,
+        container_name: Option<&str>,
         command: Vec<String>,
         timeout_secs: u64,
     ) -> Result<String> {
@@ -94,20 +149,18 @@ impl K8sClient {
             "Executing command in pod {}/{} container {}: {:?}",
             namespace,
             pod_name,
-            container_name,
+            container_name.unwrap_or("(default)"),
             command
         );
 
+        let mut params = kube::api::AttachParams::default().stdout(true).stderr(true);
+        if let Some(container_name) = container_name {
+            params = params.container(container_name);
+        }
+
         let attached = tokio::time::timeout(
             Duration::from_secs(timeout_secs),
-            pods.exec(
-                pod_name,
-                command,
-                &kube::api::AttachParams::default()
-                    .container(container_name)
-                    .stdout(true)
-                    .stderr(true),
-            ),
+            pods.exec(pod_name, command, &params),
         )
         .await
         .context("Command execution timed out")?
@@ -124,7 +177,7 @@ impl K8sClient {
         &self,
         namespace: &str,
         pod_name: &str,
-        container_name: &str,
+        container_name: Option<&str>,
         file_path: &str,
     ) -> Result<String> {
         let command = vec!["cat".to_string(), file_path.to_string()];
@@ -135,36 +188,140 @@ impl K8sClient {
     }
 
     /// Get multiple environment variable values from pod spec in a single API call.
+    /// Only the named container is searched; without a name, the pod's first container.
+    /// Values set through `valueFrom.secretKeyRef` are read from the Secret.
     /// Returns values in the same order as the requested env_var_names.
     pub async fn get_pod_env_vars(
         &self,
         namespace: &str,
         pod_name: &str,
+        container_name: Option<&str>,
         env_var_names: &[&str],
     ) -> Result<Vec<Option<String>>> {
         let pods: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
 
         let pod = pods.get(pod_name).await.context("Failed to get pod")?;
 
-        let mut results: Vec<Option<String>> = vec![None; env_var_names.len()];
-
-        if let Some(spec) = &pod.spec {
-            for container in &spec.containers {
-                if let Some(env_vars) = &container.env {
-                    for env_var in env_vars {
-                        for (i, name) in env_var_names.iter().enumerate() {
-                            if env_var.name == *name && results[i].is_none() {
-                                results[i] = env_var.value.clone();
-                            }
-                        }
-                    }
+        let containers = pod.spec.as_ref().map_or(&[][..], |spec| &spec.containers);
+        let container = match container_name {
+            Some(name) => containers
+                .iter()
+                .find(|c| c.name == name)
+                .with_context(|| {
+                    format!("Container '{name}' not found in pod '{namespace}/{pod_name}'")
+                })?,
+            None => containers
+                .first()
+                .with_context(|| format!("Pod '{namespace}/{pod_name}' has no containers"))?,
+        };
+
+        let mut env_vars: Vec<Option<&EnvVar>> = vec![None; env_var_names.len()];
+        for env_var in container.env.iter().flatten() {
+            for (i, name) in env_var_names.iter().enumerate() {
+                if env_var.name == *name && env_vars[i].is_none() {
+                    env_vars[i] = Some(env_var);
                 }
             }
         }
 
+        let mut results = Vec::with_capacity(env_vars.len());
+        for env_var in env_vars {
+            let value = match env_var {
+                Some(EnvVar {
+                    value: Some(value), ..
+                }) => Some(value.clone()),
+                Some(EnvVar {
+                    value_from: Some(source),
+                    ..
+                }) => match &source.secret_key_ref {
+                    Some(secret) => Some(
+                        self.read_secret_key(namespace, &secret.name, &secret.key)
+                            .await?,
+                    ),
+                    None => None,
+                },
+                _ => None,
+            };
+            results.push(value);
+        }
+
         Ok(results)
     }
 
+    /// Read one key of a Secret as UTF-8 text
+    pub async fn read_secret_key(&self, namespace: &str, name: &str, key: &str) -> Result<String> {
+        let secrets: Api<Secret> = Api::namespaced(self.client.clone(), namespace);
+
+        let secret = secrets
+            .get(name)
+            .await
+            .with_context(|| format!("Failed to get secret '{namespace}/{name}'"))?;
+
+        let bytes = secret
+            .data
+            .and_then(|mut data| data.remove(key))
+            .map(|value| value.0)
+            .or_else(|| {
+                secret
+                    .string_data
+                    .and_then(|mut data| data.remove(key))
+                    .map(String::into_bytes)
+            })
+            .ok_or_else(|| anyhow!("Secret '{namespace}/{name}' has no key '{key}'"))?;
+
+        String::from_utf8(bytes)
+            .with_context(|| format!("Secret '{namespace}/{name}' key '{key}' is not UTF-8"))
+    }
+
+    /// Forward a pod port to an ephemeral port on 127.0.0.1.
+    /// Every accepted local connection opens its own port-forward stream to the pod.
+    pub async fn port_forward(
+        &self,
+        namespace: &str,
+        pod_name: &str,
+        port: u16,
+    ) -> Result<PortForward> {
+        let pods: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
+
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .context("Failed to bind local port-forward listener")?;
+        let local_addr = listener
+            .local_addr()
+            .context("Failed to get port-forward listener address")?;
+
+        tracing::info!(
+            "Port-forwarding {} -> {}/{}:{}",
+            local_addr,
+            namespace,
+            pod_name,
+            port
+        );
+
+        let pod_name = pod_name.to_string();
+        let task = tokio::spawn(async move {
+            loop {
+                let socket = match listener.accept().await {
+                    Ok((socket, _)) => socket,
+                    Err(e) => {
+                        tracing::warn!("Port-forward listener failed: {}", e);
+                        break;
+                    }
+                };
+
+                let pods = pods.clone();
+                let pod_name = pod_name.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = forward_connection(&pods, &pod_name, port, socket).await {
+                        tracing::warn!("Port-forward connection to {} failed: {:#}", pod_name, e);
+                    }
+                });
+            }
+        });
+
+        Ok(PortForward { local_addr, task })
+    }
+
     /// Helper to get output from attached process (captures both stdout and stderr)
     async fn get_output(&self, mut attached: AttachedProcess) -> Result<String> {
         let stdout = attached
@@ -211,3 +368,142 @@ impl K8sClient {
         }
     }
 }
+
+/// Pipe one local connection through a new port-forward stream
+async fn forward_connection(
+    pods: &Api<Pod>,
+    pod_name: &str,
+    port: u16,
+    mut socket: TcpStream,
+) -> Result<()> {
+    let mut forwarder = pods
+        .portforward(pod_name, &[port])
+        .await
+        .context("Failed to open port-forward")?;
+    let mut upstream = forwarder
+        .take_stream(port)
+        .ok_or_else(|| anyhow!("Port-forward stream for port {port} unavailable"))?;
+
+    tokio::io::copy_bidirectional(&mut socket, &mut upstream)
+        .await
+        .context("Port-forward stream closed with error")?;
+
+    drop(upstream);
+    forwarder.join().await.context("Port-forward task failed")?;
+
+    Ok(())
+}
+
+/// Kubernetes client backed by a canned API server, for tests
+#[cfg(test)]
+pub mod mock {
+    use super::K8sClient;
+    use kube::client::Body;
+    use std::sync::{Arc, Mutex};
+
+    /// Client answering GET requests for the given paths (without query string)
+    /// with their JSON bodies, and any other request with 404.
+    /// The returned list records the path of every request received.
+    pub fn client(
+        responses: Vec<(&str, serde_json::Value)>,
+    ) -> (K8sClient, Arc<Mutex<Vec<String>>>) {
+        let responses: Vec<(String, Vec<u8>)> = responses
+            .into_iter()
+            .map(|(path, body)| (path.to_string(), body.to_string().into_bytes()))
+            .collect();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let (service, mut handle) =
+            tower_test::mock::pair::<http::Request<Body>, http::Response<Body>>();
+
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            while let Some((request, send)) = handle.next_request().await {
+                let path = request.uri().path().to_string();
+                seen.lock().unwrap().push(path.clone());
+                let body = responses
+                    .iter()
+                    .find(|(p, _)| *p == path && request.method() == http::Method::GET)
+                    .map(|(_, body)| body.clone());
+                let response = match body {
+                    Some(body) => http::Response::new(Body::from(body)),
+                    None => http::Response::builder()
+                        .status(404)
+                        .body(Body::from(
+                            serde_json::json!({
+                                "kind": "Status",
+                                "apiVersion": "v1",
+                                "status": "Failure",
+                                "reason": "NotFound",
+                                "code": 404
+                            })
+                            .to_string()
+                            .into_bytes(),
+                        ))
+                        .unwrap(),
+                };
+                send.send_response(response);
+            }
+        });
+
+        let client = K8sClient {
+            client: kube::Client::new(service, "default"),
+        };
+        (client, requests)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_pod_env_vars_from_configured_container() {
+        let pod = json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {"name": "mongodb-0", "namespace": "prod"},
+            "spec": {"containers": [
+                {
+                    "name": "metrics",
+                    "env": [{"name": "MONGO_USER", "value": "exporter"}]
+                },
+                {
+                    "name": "mongod",
+                    "env": [
+                        {"name": "MONGO_USER", "value": "root"},
+                        {"name": "MONGO_PASS", "value": "secret"}
+                    ]
+                }
+            ]}
+        });
+        let (client, _) =
+            super::mock::client(vec![("/api/v1/namespaces/prod/pods/mongodb-0", pod)]);
+        let names = ["MONGO_USER", "MONGO_PASS"];
+
+        let values = client
+            .get_pod_env_vars("prod", "mongodb-0", Some("mongod"), &names)
+            .await
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![Some("root".to_string()), Some("secret".to_string())]
+        );
+
+        // Without a configured container only the first one is read
+        let values = client
+            .get_pod_env_vars("prod", "mongodb-0", None, &names)
+            .await
+            .unwrap();
+        assert_eq!(values, vec![Some("exporter".to_string()), None]);
+
+        let missing = client
+            .get_pod_env_vars("prod", "mongodb-0", Some("mongodb"), &names)
+            .await
+            .unwrap_err();
+        assert!(
+            missing
+                .to_string()
+                .contains("Container 'mongodb' not found")
+        );
+    }
+}
@@ -1,32 +1,96 @@
 //! Kubernetes-based MongoDB connection implementation.
 
+use ::mongodb::{
+    Client,
+    bson::{Document, doc},
+    options::{ClientOptions, Credential, ServerAddress},
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
-use crate::config::NamespaceConfig;
+use crate::access;
+use crate::collections::CollectionInfo;
+use crate::config::{ExecutionMode, NamespaceConfig, QuerySettings};
 use crate::connection::MongoConnection;
-use crate::k8s_client::K8sClient;
-use crate::mongodb::{self, MongoCredentials, MongoQuery, QueryOperation, QueryOptions};
+use crate::diagnostics::{self, CheckStep, Checker};
+use crate::explain::{self, ExplainVerbosity};
+use crate::indexes::IndexInfo;
+use crate::k8s_client::{HealthyPod, K8sClient, PortForward};
+use crate::mongodb::{
+    self, CLIENT_TIMEOUT_GRACE_SECS, MongoCredentials, MongoQuery, MongoshTarget, QueryOperation,
+    QueryOptions,
+};
+use crate::native;
+use crate::read_preference::ReadPreferenceMode;
+use crate::results::QueryResult;
 
 /// TTL for cached pod info (pod name + credentials)
 const CACHE_TTL: Duration = Duration::from_secs(300);
 
+/// Time limit for the `hello` used to find a secondary
+const HELLO_TIMEOUT_SECS: u64 = 10;
+
+/// Health check steps in mongosh mode
+const MONGOSH_CHECK_STEPS: [&str; 7] = [
+    "kubeconfig",
+    "pod_discovery",
+    "credentials",
+    "mongosh",
+    "auth",
+    "ping",
+    "database",
+];
+
+/// Health check steps in port_forward mode
+const PORT_FORWARD_CHECK_STEPS: [&str; 7] = [
+    "kubeconfig",
+    "pod_discovery",
+    "credentials",
+    "port_forward",
+    "auth",
+    "ping",
+    "database",
+];
+
 struct CachedPodInfo {
     pod_name: String,
     credentials: MongoCredentials,
+    /// Native driver client over a port-forward (port_forward mode only)
+    native: Option<Arc<NativeClient>>,
     cached_at: Instant,
 }
 
+/// Execution backend resolved for a single call
+enum Backend {
+    /// Run mongosh in the pod with discovered credentials
+    Mongosh {
+        pod_name: String,
+        credentials: MongoCredentials,
+    },
+    /// Native driver over a port-forward, kept open while the call runs
+    Native(Arc<NativeClient>),
+}
+
+/// Native driver client bound to a port-forward tunnel.
+/// The tunnel lives as long as the cache entry or any call still holding it.
+struct NativeClient {
+    client: Client,
+    _tunnel: PortForward,
+}
+
 /// Kubernetes-based MongoDB connection.
-/// Executes queries by running mongosh inside a MongoDB pod.
+/// Executes queries by running mongosh inside a MongoDB pod, or with the native
+/// driver over a port-forward when `execution_mode: port_forward` is configured.
 /// Caches pod discovery and credentials to avoid redundant K8s API calls.
 pub struct K8sConnection {
     config: NamespaceConfig,
     k8s_client: Arc<K8sClient>,
     pod_cache: RwLock<Option<CachedPodInfo>>,
+    /// How long pod info and its tunnel are reused (`CACHE_TTL`)
+    cache_ttl: Duration,
 }
 
 impl K8sConnection {
@@ -35,6 +99,7 @@ impl K8sConnection {
             config,
             k8s_client,
             pod_cache: RwLock::new(None),
+            cache_ttl: CACHE_TTL,
         }
     }
 
@@ -44,7 +109,7 @@ impl K8sConnection {
         {
             let cache = self.pod_cache.read().await;
             if let Some(ref cached) = *cache
-                && cached.cached_at.elapsed() < CACHE_TTL
+                && cached.cached_at.elapsed() < self.cache_ttl
             {
                 tracing::debug!(
                     "Using cached pod info for connection '{}'",
@@ -55,18 +120,17 @@ impl K8sConnection {
         }
 
         // Cache miss or expired — discover pod and credentials
-        let pod_name = self
+        let pods = self
             .k8s_client
-            .find_healthy_pod(&self.config.namespace_name, &self.config.deployment_name)
+            .find_healthy_pods(&self.config.namespace_name, &self.config.pod_selector()?)
             .await?;
-
-        let container_name = &self.config.deployment_name;
+        let pod_name = self.choose_pod(pods).await;
 
         let credentials = mongodb::get_mongodb_credentials(
             &self.k8s_client,
-            &self.config.namespace_name,
+            &self.config,
             &pod_name,
-            container_name,
+            self.config.container(),
         )
         .await?;
 
@@ -76,6 +140,7 @@ impl K8sConnection {
             *cache = Some(CachedPodInfo {
                 pod_name: pod_name.clone(),
                 credentials: credentials.clone(),
+                native: None,
                 cached_at: Instant::now(),
             });
         }
@@ -88,6 +153,287 @@ impl K8sConnection {
 
         Ok((pod_name, credentials))
     }
+
+    /// Pick one of the healthy pods: the first, or a secondary with `prefer_secondary`.
+    /// Falls back to the first pod if the replica set can't be inspected.
+    async fn choose_pod(&self, mut pods: Vec<HealthyPod>) -> String {
+        if !self.config.prefer_secondary || pods.len() < 2 {
+            return pods.swap_remove(0).name;
+        }
+
+        match self.hello(&pods[0].name).await {
+            Ok(hello) => {
+                let pod = secondary_pod(&pods, &hello).to_string();
+                tracing::info!(
+                    "Selected pod {} for connection '{}' (primary: {})",
+                    pod,
+                    self.config.namespace_name,
+                    hello
+                        .get("primary")
+                        .and_then(serde_json::Value::as_str)
+                        .unwrap_or("unknown")
+                );
+                pod
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Could not determine replica set primary for '{}', using pod {}: {:#}",
+                    self.config.namespace_name,
+                    pods[0].name,
+                    e
+                );
+                pods.swap_remove(0).name
+            }
+        }
+    }
+
+    /// Run `hello` on a pod, without credentials, over the configured execution mode
+    async fn hello(&self, pod_name: &str) -> Result<serde_json::Value> {
+        match self.config.execution_mode {
+            ExecutionMode::Mongosh => {
+                mongodb::hello(
+                    &self.k8s_client,
+                    &self.config.namespace_name,
+                    pod_name,
+                    self.config.container(),
+                    HELLO_TIMEOUT_SECS,
+                )
+                .await
+            }
+            ExecutionMode::PortForward => {
+                let tunnel = self
+                    .k8s_client
+                    .port_forward(
+                        &self.config.namespace_name,
+                        pod_name,
+                        self.config.mongodb_port,
+                    )
+                    .await?;
+                let client = native::connect(tunnel_client_options(&tunnel))?;
+                native::run_command(&client, "admin", doc! { "hello": 1 }, HELLO_TIMEOUT_SECS).await
+            }
+        }
+    }
+
+    /// Resolve the backend for this call according to the configured execution mode
+    async fn backend(&self) -> Result<Backend> {
+        match self.config.execution_mode {
+            ExecutionMode::PortForward => Ok(Backend::Native(self.get_native_client().await?)),
+            ExecutionMode::Mongosh => {
+                let (pod_name, credentials) = self.get_pod_info().await?;
+                Ok(Backend::Mongosh {
+                    pod_name,
+                    credentials,
+                })
+            }
+        }
+    }
+
+    fn mongosh_target<'a>(
+        &'a self,
+        pod_name: &'a str,
+        credentials: &'a MongoCredentials,
+    ) -> MongoshTarget<'a> {
+        MongoshTarget {
+            k8s_client: &self.k8s_client,
+            namespace: &self.config.namespace_name,
+            pod_name,
+            container_name: self.config.container(),
+            credentials,
+            read: &self.config.settings.read,
+        }
+    }
+
+    /// Run a server-built command on whichever backend is configured
+    async fn run_command(
+        &self,
+        database: &str,
+        command: Document,
+        timeout_secs: u64,
+    ) -> Result<serde_json::Value> {
+        match self.backend().await? {
+            Backend::Native(native) => {
+                native::run_command(&native.client, database, command, timeout_secs).await
+            }
+            Backend::Mongosh {
+                pod_name,
+                credentials,
+            } => {
+                let target = self.mongosh_target(&pod_name, &credentials);
+                mongodb::run_command(&target, database, command, timeout_secs).await
+            }
+        }
+    }
+
+    /// Get a native driver client connected through a port-forward to the current pod.
+    /// The tunnel is created on first use and shared through the pod cache; callers
+    /// keep it open until they drop the returned handle, even if the cache moves on.
+    async fn get_native_client(&self) -> Result<Arc<NativeClient>> {
+        {
+            let cache = self.pod_cache.read().await;
+            if let Some(ref cached) = *cache
+                && cached.cached_at.elapsed() < self.cache_ttl
+                && let Some(ref native) = cached.native
+            {
+                return Ok(native.clone());
+            }
+        }
+
+        let (pod_name, credentials) = self.get_pod_info().await?;
+
+        // Open the tunnel under the write lock so concurrent first callers share one
+        let mut cache = self.pod_cache.write().await;
+        if let Some(ref cached) = *cache
+            && cached.pod_name == pod_name
+            && let Some(ref native) = cached.native
+        {
+            return Ok(native.clone());
+        }
+        let native = Arc::new(self.open_native_client(&pod_name, credentials).await?);
+        match cache.as_mut() {
+            Some(cached) if cached.pod_name == pod_name => {
+                cached.native = Some(native.clone());
+            }
+            // Cache was refreshed concurrently; this tunnel serves this call only
+            _ => tracing::debug!(
+                "Pod cache changed while opening port-forward for '{}'",
+                self.config.namespace_name
+            ),
+        }
+
+        Ok(native)
+    }
+
+    /// Port-forward to a pod and create a native driver client authenticating with `credentials`
+    async fn open_native_client(
+        &self,
+        pod_name: &str,
+        credentials: MongoCredentials,
+    ) -> Result<NativeClient> {
+        let tunnel = self
+            .k8s_client
+            .port_forward(
+                &self.config.namespace_name,
+                pod_name,
+                self.config.mongodb_port,
+            )
+            .await?;
+
+        let mut client_options = tunnel_client_options(&tunnel);
+        self.config.settings.read.apply(&mut client_options);
+        client_options.credential = Some(
+            Credential::builder()
+                .username(credentials.username)
+                .password(credentials.password)
+                .source(credentials.auth_source)
+                .build(),
+        );
+        let client = native::connect(client_options)?;
+
+        Ok(NativeClient {
+            client,
+            _tunnel: tunnel,
+        })
+    }
+
+    /// Health check steps up to the execution backend, which differs per mode
+    async fn run_checks(
+        &self,
+        checker: &mut Checker,
+        database: &str,
+        timeout_secs: u64,
+    ) -> Option<()> {
+        checker
+            .run("kubeconfig", async {
+                let version = self.k8s_client.server_version().await?;
+                Ok(((), format!("API server {version}")))
+            })
+            .await?;
+        let pod_name = checker
+            .run("pod_discovery", async {
+                let selector = self.config.pod_selector()?;
+                let pods = self
+                    .k8s_client
+                    .find_healthy_pods(&self.config.namespace_name, &selector)
+                    .await?;
+                let healthy = pods.len();
+                let pod_name = self.choose_pod(pods).await;
+                let detail = format!("{pod_name} ({healthy} healthy for {selector})");
+                Ok((pod_name, detail))
+            })
+            .await?;
+        let credentials = checker
+            .run("credentials", async {
+                let credentials = mongodb::get_mongodb_credentials(
+                    &self.k8s_client,
+                    &self.config,
+                    &pod_name,
+                    self.config.container(),
+                )
+                .await?;
+                let detail = format!(
+                    "user {} (auth source {})",
+                    credentials.username, credentials.auth_source
+                );
+                Ok((credentials, detail))
+            })
+            .await?;
+
+        match self.config.execution_mode {
+            ExecutionMode::PortForward => {
+                let native = checker
+                    .run("port_forward", async {
+                        let native = self.open_native_client(&pod_name, credentials).await?;
+                        Ok((native, format!("{pod_name}:{}", self.config.mongodb_port)))
+                    })
+                    .await?;
+                diagnostics::check_native(checker, &native.client, database, timeout_secs).await
+            }
+            ExecutionMode::Mongosh => {
+                checker
+                    .run("mongosh", async {
+                        let version = mongodb::mongosh_version(
+                            &self.k8s_client,
+                            &self.config.namespace_name,
+                            &pod_name,
+                            self.config.container(),
+                            timeout_secs,
+                        )
+                        .await?;
+                        Ok(((), format!("mongosh {version}")))
+                    })
+                    .await?;
+
+                let target = self.mongosh_target(&pod_name, &credentials);
+                checker
+                    .run("auth", async {
+                        let status = mongodb::run_command(
+                            &target,
+                            "admin",
+                            doc! { "connectionStatus": 1 },
+                            timeout_secs,
+                        )
+                        .await?;
+                        Ok(((), diagnostics::auth_detail(&status)))
+                    })
+                    .await?;
+                checker
+                    .run("ping", async {
+                        mongodb::run_command(&target, "admin", doc! { "ping": 1 }, timeout_secs)
+                            .await?;
+                        Ok(((), String::new()))
+                    })
+                    .await?;
+                checker
+                    .run("database", async {
+                        let collections =
+                            mongodb::list_collections(&target, database, timeout_secs).await?;
+                        diagnostics::database_detail(database, collections.len())
+                    })
+                    .await
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -108,56 +454,343 @@ impl MongoConnection for K8sConnection {
         &self.config.database_name
     }
 
-    async fn list_collections(&self) -> Result<Vec<String>> {
-        let (pod_name, credentials) = self.get_pod_info().await?;
-        let container_name = &self.config.deployment_name;
+    fn settings(&self) -> &QuerySettings {
+        &self.config.settings
+    }
 
-        mongodb::list_collections(
-            &self.k8s_client,
-            &self.config.namespace_name,
-            &pod_name,
-            container_name,
-            &credentials,
-            &self.config.database_name,
-        )
-        .await
+    fn check_read_preference(&self, mode: ReadPreferenceMode) -> Result<()> {
+        self.config.check_read_preference(mode)
+    }
+
+    async fn list_databases(&self, timeout_secs: u64) -> Result<serde_json::Value> {
+        self.run_command("admin", access::list_databases_command(), timeout_secs)
+            .await
+    }
+
+    async fn list_collections(
+        &self,
+        database: &str,
+        timeout_secs: u64,
+    ) -> Result<Vec<CollectionInfo>> {
+        match self.backend().await? {
+            Backend::Native(native) => {
+                native::list_collections(&native.client, database, timeout_secs).await
+            }
+            Backend::Mongosh {
+                pod_name,
+                credentials,
+            } => {
+                let target = self.mongosh_target(&pod_name, &credentials);
+                mongodb::list_collections(&target, database, timeout_secs).await
+            }
+        }
+    }
+
+    async fn list_indexes(
+        &self,
+        database: &str,
+        collection: &str,
+        timeout_secs: u64,
+    ) -> Result<Vec<IndexInfo>> {
+        match self.backend().await? {
+            Backend::Native(native) => {
+                native::list_indexes(&native.client, database, collection, timeout_secs).await
+            }
+            Backend::Mongosh {
+                pod_name,
+                credentials,
+            } => {
+                let target = self.mongosh_target(&pod_name, &credentials);
+                mongodb::list_indexes(&target, database, collection, timeout_secs).await
+            }
+        }
+    }
+
+    async fn database_stats(&self, database: &str, timeout_secs: u64) -> Result<serde_json::Value> {
+        self.run_command(database, doc! { "dbStats": 1 }, timeout_secs)
+            .await
     }
 
     async fn execute_query(
         &self,
+        database: &str,
         collection: &str,
         operation: &QueryOperation,
         query: &str,
         options: &QueryOptions,
         timeout_secs: u64,
-    ) -> Result<String> {
-        let (pod_name, credentials) = self.get_pod_info().await?;
-        let container_name = &self.config.deployment_name;
+    ) -> Result<QueryResult> {
+        match self.backend().await? {
+            Backend::Native(native) => {
+                tracing::info!(
+                    "Executing {:?} on {}.{} via port-forward for connection '{}'",
+                    operation,
+                    database,
+                    collection,
+                    self.config.namespace_name
+                );
 
-        tracing::info!(
-            "Using pod: {} container: {} for connection '{}'",
-            pod_name,
-            container_name,
-            self.config.namespace_name
+                native::execute_query(
+                    &native.client,
+                    database,
+                    collection,
+                    operation,
+                    query,
+                    options,
+                    timeout_secs,
+                )
+                .await
+            }
+            Backend::Mongosh {
+                pod_name,
+                credentials,
+            } => {
+                tracing::info!(
+                    "Using pod: {} container: {} for connection '{}'",
+                    pod_name,
+                    self.config.container().unwrap_or("(default)"),
+                    self.config.namespace_name
+                );
+
+                let mongo_query = MongoQuery {
+                    database: database.to_string(),
+                    collection: collection.to_string(),
+                    operation: operation.clone(),
+                    query: query.to_string(),
+                    options: options.clone(),
+                };
+
+                let target = self.mongosh_target(&pod_name, &credentials);
+                mongodb::execute_mongosh_query(&target, &mongo_query, timeout_secs).await
+            }
+        }
+    }
+
+    async fn explain_query(
+        &self,
+        database: &str,
+        collection: &str,
+        operation: &QueryOperation,
+        query: &str,
+        options: &QueryOptions,
+        verbosity: ExplainVerbosity,
+        timeout_secs: u64,
+    ) -> Result<serde_json::Value> {
+        let options = options.with_max_time(timeout_secs);
+        let command = explain::explain_command(collection, operation, query, &options, verbosity)?;
+        self.run_command(database, command, timeout_secs + CLIENT_TIMEOUT_GRACE_SECS)
+            .await
+    }
+
+    async fn check(&self, database: &str, timeout_secs: u64) -> Vec<CheckStep> {
+        let mut checker = Checker::new(match self.config.execution_mode {
+            ExecutionMode::Mongosh => &MONGOSH_CHECK_STEPS,
+            ExecutionMode::PortForward => &PORT_FORWARD_CHECK_STEPS,
+        });
+        self.run_checks(&mut checker, database, timeout_secs).await;
+        checker.into_steps()
+    }
+}
+
+/// Direct-connection client options for a port-forward tunnel, without credentials
+fn tunnel_client_options(tunnel: &PortForward) -> ClientOptions {
+    let addr = tunnel.local_addr();
+    ClientOptions::builder()
+        .hosts(vec![ServerAddress::Tcp {
+            host: addr.ip().to_string(),
+            port: Some(addr.port()),
+        }])
+        .direct_connection(true)
+        .build()
+}
+
+/// The first healthy pod that is not the replica set primary named in a `hello`
+/// reply sent to `pods[0]`. Members may be addressed by pod host name
+/// (`mongodb-1.mongodb-svc.ns.svc.cluster.local:27017`) or by pod IP
+/// (`10.0.0.5:27017`); `hello.me` identifies `pods[0]` when neither matches.
+/// Without a known primary the first pod is used.
+fn secondary_pod<'a>(pods: &'a [HealthyPod], hello: &serde_json::Value) -> &'a str {
+    let member = |field: &str| {
+        hello
+            .get(field)
+            .and_then(serde_json::Value::as_str)
+            .map(member_host)
+    };
+    let Some(primary) = member("primary") else {
+        return &pods[0].name;
+    };
+    let first_is_primary = member("me") == Some(primary);
+    let is_primary =
+        |i: usize, pod: &HealthyPod| pod_has_host(pod, primary) || (i == 0 && first_is_primary);
+
+    pods.iter()
+        .enumerate()
+        .find(|(i, pod)| !is_primary(*i, pod))
+        .map_or(&pods[0].name, |(_, pod)| &pod.name)
+}
+
+/// Host part of a replica set member address (`host:port` or `[ipv6]:port`)
+fn member_host(address: &str) -> &str {
+    if let Some(rest) = address.strip_prefix('[') {
+        return rest.split(']').next().unwrap_or(rest);
+    }
+    address.rsplit_once(':').map_or(address, |(host, _)| host)
+}
+
+/// Whether a member host name or IP refers to `pod`
+fn pod_has_host(pod: &HealthyPod, host: &str) -> bool {
+    pod.ip.as_deref() == Some(host)
+        || host
+            .strip_prefix(pod.name.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_native_client_reuse_and_expiry() {
+        let pod = json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {"name": "mongodb-0", "namespace": "prod"},
+            "spec": {"containers": [{
+                "name": "mongodb",
+                "env": [
+                    {"name": "MONGO_USER", "value": "root"},
+                    {"name": "MONGO_PASS", "value": "secret"}
+                ]
+            }]},
+            "status": {
+                "phase": "Running",
+                "podIP": "10.0.0.5",
+                "containerStatuses": [{
+                    "name": "mongodb",
+                    "ready": true,
+                    "restartCount": 0,
+                    "image": "mongo:7",
+                    "imageID": ""
+                }]
+            }
+        });
+        let pods = json!({
+            "apiVersion": "v1",
+            "kind": "PodList",
+            "metadata": {},
+            "items": [pod.clone()]
+        });
+        let (k8s_client, requests) = crate::k8s_client::mock::client(vec![
+            ("/api/v1/namespaces/prod/pods", pods),
+            ("/api/v1/namespaces/prod/pods/mongodb-0", pod),
+        ]);
+        let config: NamespaceConfig = serde_yaml::from_str(
+            "namespace_name: prod\ndeployment_name: mongodb\ndatabase_name: app\n\
+             execution_mode: port_forward\n\
+             credentials:\n  strategy: env\n  username_env: MONGO_USER\n  password_env: MONGO_PASS\n",
+        )
+        .unwrap();
+        let mut connection = K8sConnection::new(config, Arc::new(k8s_client));
+        // Pod discovery only; the driver's own traffic goes to the portforward endpoint
+        let discovery = || {
+            requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|path| !path.ends_with("/portforward"))
+                .count()
+        };
+
+        // The first call discovers the pod and opens a tunnel, later calls share it
+        let first = connection.get_native_client().await.unwrap();
+        assert_eq!(discovery(), 2);
+        let again = connection.get_native_client().await.unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(discovery(), 2);
+
+        // Once the cache expires the pod is rediscovered and a new tunnel opened
+        connection.cache_ttl = Duration::ZERO;
+        let refreshed = connection.get_native_client().await.unwrap();
+        assert!(!Arc::ptr_eq(&first, &refreshed));
+        assert_eq!(discovery(), 4);
+        assert_ne!(first._tunnel.local_addr(), refreshed._tunnel.local_addr());
+
+        // The old tunnel stays open for callers still holding it
+        assert!(
+            tokio::net::TcpStream::connect(first._tunnel.local_addr())
+                .await
+                .is_ok()
         );
+    }
 
-        let mongo_query = MongoQuery {
-            database: self.config.database_name.clone(),
-            collection: collection.to_string(),
-            operation: operation.clone(),
-            query: query.to_string(),
-            options: options.clone(),
+    #[test]
+    fn test_secondary_pod() {
+        let pod = |name: &str, ip: &str| HealthyPod {
+            name: name.to_string(),
+            ip: Some(ip.to_string()),
         };
+        let pods = vec![
+            pod("mongodb-0", "10.0.0.5"),
+            pod("mongodb-1", "10.0.0.6"),
+            pod("mongodb-2", "10.0.0.7"),
+        ];
 
-        mongodb::execute_mongosh_query(
-            &self.k8s_client,
-            &self.config.namespace_name,
-            &pod_name,
-            container_name,
-            &credentials,
-            &mongo_query,
-            timeout_secs,
-        )
-        .await
+        let hello = json!({ "primary": "mongodb-0.mongodb-svc.data.svc.cluster.local:27017" });
+        assert_eq!(secondary_pod(&pods, &hello), "mongodb-1");
+
+        let hello = json!({ "primary": "mongodb-1.mongodb-svc:27017" });
+        assert_eq!(secondary_pod(&pods, &hello), "mongodb-0");
+
+        // A pod name that is a prefix of another pod's name doesn't match it
+        let hello = json!({ "primary": "mongodb-10.mongodb-svc:27017" });
+        assert_eq!(secondary_pod(&pods, &hello), "mongodb-0");
+
+        // Standalone servers report no primary
+        assert_eq!(
+            secondary_pod(&pods, &json!({ "isWritablePrimary": true })),
+            "mongodb-0"
+        );
+
+        // Only the primary is healthy
+        let primary_only = vec![pod("mongodb-0", "10.0.0.5")];
+        let hello = json!({ "primary": "mongodb-0.mongodb-svc:27017" });
+        assert_eq!(secondary_pod(&primary_only, &hello), "mongodb-0");
+    }
+
+    #[test]
+    fn test_secondary_pod_by_ip() {
+        let pods = vec![
+            HealthyPod {
+                name: "mongodb-0".to_string(),
+                ip: Some("10.0.0.5".to_string()),
+            },
+            HealthyPod {
+                name: "mongodb-1".to_string(),
+                ip: None,
+            },
+        ];
+
+        // Members addressed by IP are matched against pod IPs
+        let hello = json!({ "primary": "10.0.0.5:27017", "me": "10.0.0.5:27017" });
+        assert_eq!(secondary_pod(&pods, &hello), "mongodb-1");
+
+        // `me` identifies the pod that was asked when its IP isn't known
+        let pods = vec![
+            HealthyPod {
+                name: "mongodb-0".to_string(),
+                ip: None,
+            },
+            HealthyPod {
+                name: "mongodb-1".to_string(),
+                ip: None,
+            },
+        ];
+        let hello = json!({ "primary": "10.0.0.5:27017", "me": "10.0.0.5:27017" });
+        assert_eq!(secondary_pod(&pods, &hello), "mongodb-1");
+        let hello = json!({ "primary": "10.0.0.6:27017", "me": "10.0.0.5:27017" });
+        assert_eq!(secondary_pod(&pods, &hello), "mongodb-0");
+
+        assert_eq!(member_host("[fd00::5]:27017"), "fd00::5");
     }
 }
@@ -1,85 +1,177 @@
+mod access;
+mod audit;
+mod cli;
+mod collections;
 mod config;
 mod connection;
+mod credentials;
+mod diagnostics;
 mod direct_connection;
+mod ejson;
+mod explain;
+mod http;
+mod indexes;
 mod k8s_client;
 mod k8s_connection;
 mod mcp;
 mod mongodb;
+mod native;
+mod read_preference;
+mod redaction;
+mod reload;
+mod results;
 mod saved_queries;
+mod schema;
+mod ssh_connection;
+mod ssh_tunnel;
+mod stats;
+mod tls;
 mod tools;
+mod validation;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use connection::ConnectionRegistry;
-use direct_connection::DirectConnection;
-use k8s_connection::K8sConnection;
+use connection::SharedRegistry;
 
 /// A Model Context Protocol (MCP) server for querying `MongoDB` databases.
 ///
 /// This server enables LLMs to execute read-only `MongoDB` queries against configured connections.
-/// Supports both Kubernetes namespace connections and direct MongoDB URL connections.
-/// It communicates via JSON-RPC 2.0 over stdin/stdout.
+/// Supports Kubernetes namespace connections, direct MongoDB URL connections and
+/// connections through an SSH bastion host.
+/// It communicates via JSON-RPC 2.0 over stdin/stdout, or over streamable HTTP with `--http`.
 #[derive(Parser)]
 #[command(name = "ro-mongodb-mcp-rs")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
-#[command(about = "Read-only MCP server for MongoDB queries (Kubernetes and direct connections)", long_about = None)]
-struct Cli {}
+#[command(about = "Read-only MCP server for MongoDB queries (Kubernetes, direct and SSH connections)", long_about = None)]
+struct Cli {
+    /// Run a single command instead of the MCP server
+    #[command(subcommand)]
+    command: Option<cli::Command>,
+
+    /// Serve MCP over streamable HTTP (SSE) instead of stdio
+    #[arg(long)]
+    http: bool,
+
+    /// Address for the HTTP transport, overriding http.bind (implies --http)
+    #[arg(long, value_name = "ADDR")]
+    bind: Option<std::net::SocketAddr>,
+
+    /// Config file to load instead of the default (repeatable; later files override
+    /// connections of the same name). Also read from RO_MONGODB_MCP_CONFIG
+    #[arg(long = "config", value_name = "FILE")]
+    config_files: Vec<std::path::PathBuf>,
+
+    /// Check every connection (or only CONNECTION) step by step, print the results and exit
+    #[arg(long, value_name = "CONNECTION")]
+    check: Option<Option<String>>,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Parse CLI arguments (handles --version and --help automatically)
-    let _cli = Cli::parse();
+    let cli = Cli::parse();
+    // Not args_conflicts_with_subcommands: --config applies to subcommands too
+    if cli.command.is_some() && (cli.http || cli.bind.is_some() || cli.check.is_some()) {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--http, --bind and --check cannot be used with a subcommand",
+            )
+            .exit();
+    }
+    // One-shot commands print their own output; keep the log to warnings
+    let default_level = if cli.command.is_some() || cli.check.is_some() {
+        "ro_mongodb_mcp_rs=warn"
+    } else {
+        "ro_mongodb_mcp_rs=info"
+    };
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "ro_mongodb_mcp_rs=info".into()),
+                .unwrap_or_else(|_| default_level.into()),
         )
         .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .init();
 
-    let config = config::Config::load()?;
-    tracing::info!(
-        "Configuration loaded from {:?}",
-        config::Config::config_file()?
-    );
-    tracing::info!("Data directory: {:?}", config::Config::data_dir()?);
+    config::Config::set_config_files(cli.config_files);
+
+    if let Some(command) = cli.command {
+        return cli::run(command).await;
+    }
 
-    // Validate no duplicate connection names
-    config.validate_unique_names()?;
+    if let Some(name) = cli.check {
+        let reports = diagnostics::check_configured(name.as_deref()).await?;
+        for report in &reports {
+            println!("{}", report.to_text());
+        }
+        if reports.iter().any(|report| !report.ok) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let mut config = config::Config::load()?;
+    if let Some(bind) = cli.bind {
+        config.http.bind = bind;
+    }
+    let serve_http = cli.http || cli.bind.is_some() || config.http.enabled;
+    if serve_http {
+        // Fail before connecting anything if the HTTP settings are unsafe
+        config.http.validate()?;
+    }
+    tracing::info!("Configuration loaded from {:?}", config.sources);
+    tracing::info!("Data directory: {:?}", config::Config::data_dir()?);
 
     tracing::info!(
-        "Configured connections: {} K8s namespaces, {} direct connections",
+        "Configured connections: {} K8s namespaces, {} direct connections, {} SSH connections",
         config.namespaces.len(),
-        config.connections.len()
+        config.connections.len(),
+        config.ssh_connections.len()
     );
 
-    // Build connection registry
-    let mut registry = ConnectionRegistry::new();
+    // Build connection registry (rejects duplicate connection names)
+    let mut builder = reload::RegistryBuilder::new();
+    let (registry, _) = builder.build(&config).await?;
+    let connections = SharedRegistry::new(registry);
 
-    // Register K8s namespace connections (requires K8s client)
-    if !config.namespaces.is_empty() {
-        let k8s_client =
-            Arc::new(k8s_client::K8sClient::new(config.kubeconfig_path.clone()).await?);
-        tracing::info!("Kubernetes client initialized");
+    // Page tokens and schemas are shared with the reloader, which drops those of
+    // changed connections
+    let pages = Arc::new(results::PageStore::new());
+    let schemas = Arc::new(schema::SchemaCache::new());
 
-        for ns in &config.namespaces {
-            tracing::info!("Registering K8s connection: {}", ns.namespace_name);
-            registry.register(Box::new(K8sConnection::new(ns.clone(), k8s_client.clone())));
-        }
-    }
+    // Pick up config.yaml edits (and SIGHUP) without restarting
+    let peers = reload::ClientPeers::default();
+    let reloader = Arc::new(reload::Reloader::new(
+        builder,
+        connections.clone(),
+        pages.clone(),
+        schemas.clone(),
+        peers.clone(),
+    ));
+    tokio::spawn(reloader.watch(config.sources.clone()));
 
-    // Register direct MongoDB connections (lazy init, no connection yet)
-    for conn in &config.connections {
-        tracing::info!("Registering direct connection: {}", conn.name);
-        registry.register(Box::new(DirectConnection::new(conn.clone())));
+    let audit = audit::AuditLog::new(&config.audit)?;
+    if audit.is_enabled() {
+        tracing::info!("Audit log enabled");
     }
 
-    let mcp_server =
-        mcp::McpServer::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"), registry);
-    mcp_server.run().await?;
+    let mcp_server = mcp::McpServer::new(
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        connections,
+        pages,
+        schemas,
+        audit,
+        peers,
+    );
+    if serve_http {
+        http::serve(mcp_server, &config.http).await?;
+    } else {
+        mcp_server.run().await?;
+    }
 
     Ok(())
 }
@@ -1,18 +1,33 @@
 //! MCP server implementation with tool handlers.
 
-use anyhow::Result;
+use anyhow::{Context, Result, bail};
 use rmcp::{
     ServerHandler,
     handler::server::{tool::ToolRouter, wrapper::Parameters},
     model::{CallToolResult, Content, ServerCapabilities, ServerInfo},
+    service::{NotificationContext, RoleServer},
     tool, tool_handler, tool_router,
 };
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-
-use crate::connection::ConnectionRegistry;
+use std::time::Instant;
+
+use crate::access;
+use crate::audit::{AuditEntry, AuditLog};
+use crate::collections::{CollectionInfo, view_sources};
+use crate::config::QuerySettings;
+use crate::connection::{MongoConnection, SharedRegistry};
+use crate::diagnostics;
+use crate::ejson::ExtJsonMode;
+use crate::explain;
 use crate::mongodb::{self, QueryOptions};
+use crate::native;
+use crate::redaction::Redaction;
+use crate::reload::ClientPeers;
+use crate::results::{PageCursor, PageStore, QueryResult, ResultLimits};
 use crate::saved_queries::SavedQueries;
+use crate::schema::{self, SchemaCache};
+use crate::stats;
 use crate::tools::*;
 
 /// Format anyhow error with full cause chain
@@ -26,7 +41,7 @@ fn format_error(e: &anyhow::Error) -> String {
 }
 
 /// Find all {{placeholder}} patterns in a query string
-fn find_placeholders(query: &str) -> HashSet<String> {
+pub fn find_placeholders(query: &str) -> HashSet<String> {
     let mut placeholders = HashSet::new();
     let bytes = query.as_bytes();
     let mut i = 0;
@@ -56,7 +71,7 @@ fn find_placeholders(query: &str) -> HashSet<String> {
 }
 
 /// Substitute {{placeholder}} patterns with provided values
-fn substitute_placeholders(
+pub fn substitute_placeholders(
     query: &str,
     variables: &HashMap<String, String>,
 ) -> std::result::Result<String, Vec<String>> {
@@ -83,10 +98,181 @@ fn substitute_placeholders(
     Ok(result)
 }
 
+/// Check a query against a redaction policy before it runs.
+/// Returns the distinct field, whose values are redacted as that field.
+fn check_redaction(
+    redaction: &Redaction,
+    operation: &mongodb::QueryOperation,
+    query: &str,
+    options: &QueryOptions,
+) -> Result<Option<String>> {
+    if redaction.is_empty() {
+        return Ok(None);
+    }
+
+    let parsed: serde_json::Value =
+        serde_json::from_str(query).context("Query is not valid JSON")?;
+    redaction.check_query(&parsed, "query")?;
+    if let Some(projection) = &options.projection {
+        let parsed: serde_json::Value =
+            serde_json::from_str(projection).context("Invalid projection JSON")?;
+        redaction.check_query(&parsed, "projection")?;
+    }
+
+    if matches!(operation, mongodb::QueryOperation::Distinct) {
+        let (field, _) = native::distinct_parts(query, options)?;
+        redaction.check_distinct_field(&field)?;
+        return Ok(Some(field));
+    }
+    Ok(None)
+}
+
+/// Enforce the connection's collection rules for `collection` and for every
+/// collection its pipeline reads through `$lookup`, `$graphLookup` or `$unionWith`
+pub fn check_collection_access(
+    connection: &dyn MongoConnection,
+    collection: &str,
+    query: Option<&str>,
+) -> Result<()> {
+    let settings = connection.settings();
+    settings.check_collection(collection)?;
+
+    // Unparseable queries are rejected by the backend; templates are checked when run
+    let Some(parsed) = query.and_then(|q| serde_json::from_str::<serde_json::Value>(q).ok()) else {
+        return Ok(());
+    };
+    for ns in access::referenced_collections(&parsed) {
+        if let Some(database) = ns.database {
+            connection
+                .resolve_database(Some(database))
+                .with_context(|| format!("{} reads from database '{database}'", ns.stage))?;
+        }
+        if !settings.collection_allowed(ns.collection) {
+            bail!(
+                "Collection '{}' used in {} is not accessible on this connection",
+                ns.collection,
+                ns.stage
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Enforce the collection rules on what views read, so a view on a denied
+/// collection (or looking one up) can't expose it under the view's name.
+/// Checks `collection` and the collections its pipeline looks up, when they are views.
+pub async fn check_view_access(
+    connection: &dyn MongoConnection,
+    database: &str,
+    collection: &str,
+    query: Option<&str>,
+    timeout_secs: u64,
+) -> Result<()> {
+    let settings = connection.settings();
+    if !settings.has_collection_rules() {
+        return Ok(());
+    }
+
+    let parsed = query.and_then(|q| serde_json::from_str::<serde_json::Value>(q).ok());
+    let mut names = vec![collection];
+    if let Some(parsed) = &parsed {
+        names.extend(
+            access::referenced_collections(parsed)
+                .into_iter()
+                .filter(|ns| ns.database.is_none())
+                .map(|ns| ns.collection),
+        );
+    }
+
+    let collections = connection
+        .list_collections(database, timeout_secs)
+        .await
+        .context("Failed to look up view definitions")?;
+    for name in names {
+        if let Some(source) = view_sources(&collections, name)
+            .into_iter()
+            .find(|source| !settings.collection_allowed(source))
+        {
+            bail!(
+                "View '{name}' reads from collection '{source}', which is not accessible \
+                 on this connection"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Collections the connection's rules allow, without views reading from others
+pub fn accessible_collections(
+    settings: &QuerySettings,
+    collections: Vec<CollectionInfo>,
+) -> Vec<CollectionInfo> {
+    let hidden: Vec<String> = collections
+        .iter()
+        .filter(|c| {
+            !settings.collection_allowed(&c.name)
+                || view_sources(&collections, &c.name)
+                    .into_iter()
+                    .any(|source| !settings.collection_allowed(source))
+        })
+        .map(|c| c.name.clone())
+        .collect();
+    collections
+        .into_iter()
+        .filter(|c| !hidden.contains(&c.name))
+        .collect()
+}
+
+/// Execute a query with the connection's collection rules and redaction policy applied.
+/// Every tool returning query data goes through here, as do the CLI query commands.
+pub async fn execute_query(
+    connection: &dyn MongoConnection,
+    database: &str,
+    collection: &str,
+    operation: &mongodb::QueryOperation,
+    query: &str,
+    options: &QueryOptions,
+    timeout_secs: u64,
+) -> Result<QueryResult, rmcp::ErrorData> {
+    check_collection_access(connection, collection, Some(query))
+        .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))?;
+    check_view_access(connection, database, collection, Some(query), timeout_secs)
+        .await
+        .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))?;
+
+    let redaction = &connection.settings().redaction;
+    let distinct_field = check_redaction(redaction, operation, query, options)
+        .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))?;
+    let hash_key = connection
+        .settings()
+        .redaction_hash_key()
+        .map_err(|e| rmcp::ErrorData::internal_error(format_error(&e), None))?;
+
+    let result = connection
+        .execute_query(
+            database,
+            collection,
+            operation,
+            query,
+            options,
+            timeout_secs,
+        )
+        .await
+        .map_err(|e| rmcp::ErrorData::internal_error(format_error(&e), None))?;
+
+    Ok(redaction.redact_result(result, distinct_field.as_deref(), hash_key.as_deref()))
+}
+
+/// Cheap to clone: the HTTP transport hands every session its own clone
+#[derive(Clone)]
 pub struct McpServer {
     name: String,
     version: String,
-    connections: Arc<ConnectionRegistry>,
+    connections: SharedRegistry,
+    pages: Arc<PageStore>,
+    schemas: Arc<SchemaCache>,
+    audit: Arc<AuditLog>,
+    peers: ClientPeers,
     tool_router: ToolRouter<Self>,
 }
 
@@ -94,16 +280,25 @@ impl McpServer {
     pub fn new(
         name: impl Into<String>,
         version: impl Into<String>,
-        connections: ConnectionRegistry,
+        connections: SharedRegistry,
+        pages: Arc<PageStore>,
+        schemas: Arc<SchemaCache>,
+        audit: AuditLog,
+        peers: ClientPeers,
     ) -> Self {
         Self {
             name: name.into(),
             version: version.into(),
-            connections: Arc::new(connections),
+            connections,
+            pages,
+            schemas,
+            audit: Arc::new(audit),
+            peers,
             tool_router: Self::tool_router(),
         }
     }
 
+    /// Serve a single client over stdin/stdout
     pub async fn run(self) -> Result<()> {
         use rmcp::ServiceExt;
 
@@ -117,12 +312,117 @@ impl McpServer {
         Ok(())
     }
 
-    fn connection_not_found(&self, name: &str) -> rmcp::ErrorData {
-        let available = self.connections.list_names().join(", ");
-        rmcp::ErrorData::invalid_params(
-            format!("Connection '{name}' not found. Available: {available}"),
-            None,
+    /// Look up a connection in the current registry
+    fn connection(&self, name: &str) -> Result<Arc<dyn MongoConnection>, rmcp::ErrorData> {
+        let registry = self.connections.current();
+        registry.get(name).ok_or_else(|| {
+            let available = registry.list_names().join(", ");
+            rmcp::ErrorData::invalid_params(
+                format!("Connection '{name}' not found. Available: {available}"),
+                None,
+            )
+        })
+    }
+
+    /// Resolve the requested database against the connection's allowed databases
+    fn resolve_database(
+        &self,
+        connection: &dyn MongoConnection,
+        requested: Option<&str>,
+    ) -> Result<String, rmcp::ErrorData> {
+        connection
+            .resolve_database(requested)
+            .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))
+    }
+
+    /// Time limit for a call, from the requested `timeout_secs` or the connection default
+    fn timeout_secs(
+        &self,
+        connection: &dyn MongoConnection,
+        requested: Option<u64>,
+    ) -> Result<u64, rmcp::ErrorData> {
+        connection
+            .settings()
+            .timeout_secs(requested)
+            .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))
+    }
+
+    /// Run a query, record it in the audit log and render the result.
+    /// Truncated results get a metadata block with a token for get_next_page.
+    async fn run_paged_query(
+        &self,
+        connection: &dyn MongoConnection,
+        cursor: PageCursor,
+        warning: Option<&str>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let started = Instant::now();
+        let result = execute_query(
+            connection,
+            &cursor.database,
+            &cursor.collection,
+            &cursor.operation,
+            &cursor.query,
+            &cursor.options,
+            cursor.timeout_secs,
         )
+        .await
+        .and_then(|result| {
+            let text = result
+                .to_json_string()
+                .map_err(|e| rmcp::ErrorData::internal_error(format_error(&e), None))?;
+            Ok((result, text))
+        });
+
+        self.audit
+            .record(&AuditEntry {
+                timestamp: chrono::Utc::now(),
+                connection: cursor.connection.clone(),
+                database: cursor.database.clone(),
+                collection: cursor.collection.clone(),
+                operation: cursor.operation.as_str().to_string(),
+                query: cursor.query.clone(),
+                distinct_field: cursor.options.distinct_field.clone(),
+                projection: cursor.options.projection.clone(),
+                sort: cursor.options.sort.clone(),
+                limit: cursor.options.limit,
+                saved_query: cursor.saved_query.clone(),
+                skip: cursor.options.skip,
+                duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+                documents: result.as_ref().ok().map(|(result, _)| result.len()),
+                bytes: result.as_ref().ok().map(|(_, text)| text.len()),
+                error: result.as_ref().err().map(|e| e.message.to_string()),
+            })
+            .await;
+
+        let (result, text) = result?;
+
+        // Include warning if applicable
+        let output = if let Some(warn) = warning {
+            format!("{}\n\n{}", warn, text)
+        } else {
+            text
+        };
+
+        let mut content = vec![Content::text(output)];
+
+        if result.is_truncated() {
+            let returned = result.len();
+            let next_page_token = cursor.next(returned).map(|next| self.pages.insert(next));
+            let metadata = serde_json::json!({
+                "truncated": true,
+                "returned": returned,
+                "skip": cursor.options.skip.unwrap_or(0),
+                "limits": cursor.options.limits,
+                "next_page_token": next_page_token,
+                "hint": "Result truncated by server limits. Call get_next_page with next_page_token \
+                         for more, or narrow the query with filters, projection or limit."
+            });
+            content.push(Content::text(
+                serde_json::to_string_pretty(&metadata).unwrap(),
+            ));
+        }
+
+        Ok(CallToolResult::success(content))
     }
 }
 
@@ -162,11 +462,12 @@ impl McpServer {
 
     /// Lists all available MongoDB connections that you can query.
     ///
-    /// This includes both Kubernetes namespace connections and direct MongoDB URL connections.
+    /// This includes Kubernetes namespace, direct MongoDB URL and SSH tunnel connections.
     /// Use this first to discover available connections before querying.
+    /// Call again after a tools/list_changed notification: the server config was reloaded.
     #[tool]
     fn list_connections(&self) -> Result<CallToolResult, rmcp::ErrorData> {
-        let connections = self.connections.list_with_types();
+        let connections = self.connections.current().list_with_types();
 
         let response = serde_json::json!({
             "connections": connections.iter().map(|(name, conn_type)| {
@@ -180,6 +481,31 @@ impl McpServer {
         )]))
     }
 
+    /// Checks a connection step by step and reports how long each step took.
+    ///
+    /// The first step, config, re-reads the config files and checks they still define
+    /// the connection. The rest depend on the connection type: kubeconfig,
+    /// pod_discovery, credentials, mongosh or port_forward (Kubernetes), client
+    /// (direct) or ssh_tunnel (SSH), then auth, ping and database for all. Steps
+    /// after a failure are skipped.
+    /// Use when queries on a connection fail, to see which step is broken.
+    #[tool]
+    async fn check_connection(
+        &self,
+        Parameters(params): Parameters<CheckConnectionParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let connection = self.connection(&params.connection_name)?;
+
+        let database = self.resolve_database(&*connection, params.database_name.as_deref())?;
+        let timeout_secs = self.timeout_secs(&*connection, None)?;
+
+        let report = diagnostics::check_running(&*connection, &database, timeout_secs).await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&report).unwrap(),
+        )]))
+    }
+
     /// Retrieves the data model documentation for a specific connection.
     ///
     /// Returns the contents of data_model_file_path from config (any format: markdown, TypeScript, etc.).
@@ -189,10 +515,7 @@ impl McpServer {
         &self,
         Parameters(params): Parameters<GetDataModelParams>,
     ) -> Result<CallToolResult, rmcp::ErrorData> {
-        let connection = self
-            .connections
-            .get(&params.connection_name)
-            .ok_or_else(|| self.connection_not_found(&params.connection_name))?;
+        let connection = self.connection(&params.connection_name)?;
 
         let Some(path) = connection.data_model_path() else {
             return Ok(CallToolResult::success(vec![Content::text(format!(
@@ -220,27 +543,69 @@ impl McpServer {
         Ok(CallToolResult::success(vec![Content::text(content)]))
     }
 
-    /// Lists all MongoDB collection names in a connection.
+    /// Lists the databases a connection may query.
+    ///
+    /// The default database comes from the connection config; other databases are
+    /// selectable with the database_name parameter of the query, list and stats tools.
+    #[tool]
+    async fn list_databases(
+        &self,
+        Parameters(params): Parameters<ListDatabasesParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let connection = self.connection(&params.connection_name)?;
+
+        let default = connection.database_name();
+        let allowed = &connection.settings().databases;
+        let timeout_secs = self.timeout_secs(&*connection, None)?;
+
+        // Without extra databases there is nothing to discover
+        let databases = if allowed.is_empty() {
+            vec![default.to_string()]
+        } else {
+            let reply = connection
+                .list_databases(timeout_secs)
+                .await
+                .map_err(|e| rmcp::ErrorData::internal_error(format_error(&e), None))?;
+            access::allowed_databases(&reply, default, allowed)
+        };
+
+        let response = serde_json::json!({
+            "default": default,
+            "databases": databases,
+            "count": databases.len()
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&response).unwrap(),
+        )]))
+    }
+
+    /// Lists all MongoDB collections and views in a connection.
     ///
     /// IMPORTANT: Collection names are CASE-SENSITIVE in MongoDB!
     /// Always use this tool to get exact collection names before querying.
+    ///
+    /// Each entry has a type (collection, view, timeseries). Views include view_on
+    /// and pipeline; validators (often $jsonSchema) describe required fields and types.
     #[tool]
     async fn list_collections(
         &self,
         Parameters(params): Parameters<ListCollectionsParams>,
     ) -> Result<CallToolResult, rmcp::ErrorData> {
-        let connection = self
-            .connections
-            .get(&params.connection_name)
-            .ok_or_else(|| self.connection_not_found(&params.connection_name))?;
+        let connection = self.connection(&params.connection_name)?;
+
+        let database = self.resolve_database(&*connection, params.database_name.as_deref())?;
+
+        let timeout_secs = self.timeout_secs(&*connection, None)?;
 
         let collections = connection
-            .list_collections()
+            .list_collections(&database, timeout_secs)
             .await
             .map_err(|e| rmcp::ErrorData::internal_error(format_error(&e), None))?;
+        let collections = accessible_collections(connection.settings(), collections);
 
         let response = serde_json::json!({
-            "database": connection.database_name(),
+            "database": database,
             "collections": collections,
             "count": collections.len()
         });
@@ -250,6 +615,111 @@ impl McpServer {
         )]))
     }
 
+    /// Lists the indexes of a collection.
+    ///
+    /// Returns each index's key pattern (in order) and its unique, sparse, hidden,
+    /// partial filter and TTL (expire_after_seconds) properties.
+    /// Filter and sort on indexed fields (leading keys first) to keep queries fast.
+    #[tool]
+    async fn list_indexes(
+        &self,
+        Parameters(params): Parameters<ListIndexesParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let connection = self.connection(&params.connection_name)?;
+
+        let database = self.resolve_database(&*connection, params.database_name.as_deref())?;
+
+        check_collection_access(&*connection, &params.collection_name, None)
+            .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))?;
+
+        let timeout_secs = self.timeout_secs(&*connection, None)?;
+        let indexes = connection
+            .list_indexes(&database, &params.collection_name, timeout_secs)
+            .await
+            .map_err(|e| rmcp::ErrorData::internal_error(format_error(&e), None))?;
+
+        let response = serde_json::json!({
+            "database": database,
+            "collection": params.collection_name,
+            "indexes": indexes,
+            "count": indexes.len()
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&response).unwrap(),
+        )]))
+    }
+
+    /// Returns size statistics for a collection, or for the whole database when
+    /// collection_name is omitted.
+    ///
+    /// Collection: estimated_count, avg_document_bytes, storage and index sizes, capped.
+    /// Database: collections, views, estimated_documents, data/storage/index sizes.
+    /// Counts are metadata estimates and return instantly, unlike countDocuments.
+    /// Check before running a find without limit on an unfamiliar collection.
+    #[tool]
+    async fn collection_stats(
+        &self,
+        Parameters(params): Parameters<CollectionStatsParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let connection = self.connection(&params.connection_name)?;
+
+        let database = self.resolve_database(&*connection, params.database_name.as_deref())?;
+        let timeout_secs = self.timeout_secs(&*connection, None)?;
+
+        let summary = match params.collection_name {
+            Some(collection) => {
+                let pipeline = stats::collection_stats_pipeline();
+                check_collection_access(&*connection, &collection, Some(&pipeline))
+                    .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))?;
+                check_view_access(
+                    &*connection,
+                    &database,
+                    &collection,
+                    Some(&pipeline),
+                    timeout_secs,
+                )
+                .await
+                .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))?;
+
+                // $collStats returns no document data, so redaction rules don't
+                // apply; a pattern like `.*size.*` would only mask the sizes
+                let result = connection
+                    .execute_query(
+                        &database,
+                        &collection,
+                        &mongodb::QueryOperation::Aggregate,
+                        &pipeline,
+                        &QueryOptions::default(),
+                        timeout_secs,
+                    )
+                    .await
+                    .map_err(|e| rmcp::ErrorData::internal_error(format_error(&e), None))?;
+
+                let QueryResult::Documents { items, .. } = result else {
+                    return Err(rmcp::ErrorData::internal_error(
+                        "Unexpected count result for $collStats",
+                        None,
+                    ));
+                };
+                let mut summary = stats::summarize_collection(&collection, &items);
+                summary["database"] = serde_json::json!(database);
+                summary
+            }
+            None => {
+                let reply = connection
+                    .database_stats(&database, timeout_secs)
+                    .await
+                    .map_err(|e| rmcp::ErrorData::internal_error(format_error(&e), None))?;
+                stats::summarize_database(&database, &reply)
+            }
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&summary).unwrap(),
+        )]))
+    }
+
     /// Executes a READ-ONLY MongoDB query against a specific collection.
     ///
     /// Operations:
@@ -258,16 +728,15 @@ impl McpServer {
     /// - countDocuments: query={"status": "active"}
     /// - distinct: distinct_field="country", query={"active": true} ← query is filter
     ///
-    /// 30-second timeout. Limit/sort/projection only apply to find.
+    /// Times out after the connection's default (30s unless configured); pass
+    /// timeout_secs for longer queries. Limit/sort/projection only apply to find.
+    /// Large results are truncated; follow next_page_token with get_next_page.
     #[tool]
     async fn query_mongodb(
         &self,
         Parameters(params): Parameters<QueryMongodbParams>,
     ) -> Result<CallToolResult, rmcp::ErrorData> {
-        let connection = self
-            .connections
-            .get(&params.connection_name)
-            .ok_or_else(|| self.connection_not_found(&params.connection_name))?;
+        let connection = self.connection(&params.connection_name)?;
 
         let op = mongodb::QueryOperation::from(&params.operation);
 
@@ -281,26 +750,207 @@ impl McpServer {
             None
         };
 
+        if let Some(mode) = params.read_preference {
+            connection
+                .check_read_preference(mode)
+                .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))?;
+        }
+
         let options = QueryOptions {
             limit: params.limit,
             sort: params.sort,
             projection: params.projection,
             distinct_field: params.distinct_field,
+            ejson_mode: connection.settings().extended_json,
+            skip: None,
+            limits: Some(connection.settings().result_limits()),
+            max_time_ms: None,
+            read: params
+                .read_preference
+                .map(|mode| connection.settings().read.with_mode(mode)),
         };
 
-        let result = connection
-            .execute_query(&params.collection_name, &op, &params.query, &options, 30)
+        let database = self.resolve_database(&*connection, params.database_name.as_deref())?;
+        let timeout_secs = self.timeout_secs(&*connection, params.timeout_secs)?;
+
+        let cursor = PageCursor {
+            connection: params.connection_name,
+            database,
+            collection: params.collection_name,
+            operation: op,
+            query: params.query,
+            options,
+            timeout_secs,
+            saved_query: None,
+        };
+
+        self.run_paged_query(&*connection, cursor, warning).await
+    }
+
+    /// Explains how MongoDB would execute a query, without returning its documents.
+    ///
+    /// Takes the same parameters as query_mongodb plus verbosity
+    /// (queryPlanner | executionStats | allPlansExecution, default executionStats).
+    /// Returns a summary (winning plan, indexes used, docs examined vs returned,
+    /// COLLSCAN warning) followed by the raw explain output.
+    /// Use before running queries against large collections.
+    #[tool]
+    async fn explain_query(
+        &self,
+        Parameters(params): Parameters<ExplainQueryParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let connection = self.connection(&params.connection_name)?;
+
+        let op = mongodb::QueryOperation::from(&params.operation);
+
+        let options = QueryOptions {
+            limit: params.limit,
+            sort: params.sort,
+            projection: params.projection,
+            distinct_field: params.distinct_field,
+            ..Default::default()
+        };
+
+        let database = self.resolve_database(&*connection, params.database_name.as_deref())?;
+        let timeout_secs = self.timeout_secs(&*connection, params.timeout_secs)?;
+
+        check_collection_access(&*connection, &params.collection_name, Some(&params.query))
+            .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))?;
+        check_view_access(
+            &*connection,
+            &database,
+            &params.collection_name,
+            Some(&params.query),
+            timeout_secs,
+        )
+        .await
+        .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))?;
+
+        let explain = connection
+            .explain_query(
+                &database,
+                &params.collection_name,
+                &op,
+                &params.query,
+                &options,
+                params.verbosity,
+                timeout_secs,
+            )
             .await
             .map_err(|e| rmcp::ErrorData::internal_error(format_error(&e), None))?;
 
-        // Include warning if applicable
-        let output = if let Some(warn) = warning {
-            format!("{}\n\n{}", warn, result)
-        } else {
-            result
+        let response = serde_json::json!({
+            "verbosity": params.verbosity.as_str(),
+            "summary": explain::summarize(&explain),
+            "explain": explain,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&response).unwrap(),
+        )]))
+    }
+
+    /// Infers a collection schema by sampling documents with $sample.
+    ///
+    /// Returns every field path (nested objects as a.b, array elements as a[]) with
+    /// its BSON types and presence (fraction of sampled documents containing it).
+    /// Use when get_data_model has no documentation. Results are cached for
+    /// 10 minutes per collection; pass refresh=true to sample again.
+    #[tool]
+    async fn infer_schema(
+        &self,
+        Parameters(params): Parameters<InferSchemaParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let connection = self.connection(&params.connection_name)?;
+
+        let database = self.resolve_database(&*connection, params.database_name.as_deref())?;
+        check_collection_access(&*connection, &params.collection_name, None)
+            .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))?;
+
+        if !params.refresh
+            && let Some(cached) =
+                self.schemas
+                    .get(&params.connection_name, &database, &params.collection_name)
+        {
+            return Ok(CallToolResult::success(vec![Content::text(
+                serde_json::to_string_pretty(&cached).unwrap(),
+            )]));
+        }
+
+        let sample_size = params.sample_size.unwrap_or(100).clamp(1, 1000);
+        let pipeline = serde_json::json!([{"$sample": {"size": sample_size}}]);
+
+        // Canonical mode keeps BSON type wrappers ($numberLong, $date, ...) visible
+        let options = QueryOptions {
+            ejson_mode: ExtJsonMode::Canonical,
+            limits: Some(ResultLimits {
+                max_documents: sample_size as usize,
+                max_result_bytes: connection.settings().max_result_bytes,
+            }),
+            ..Default::default()
         };
 
-        Ok(CallToolResult::success(vec![Content::text(output)]))
+        // Sampled documents are redacted before inference, so dropped fields stay hidden
+        let result = execute_query(
+            &*connection,
+            &database,
+            &params.collection_name,
+            &mongodb::QueryOperation::Aggregate,
+            &pipeline.to_string(),
+            &options,
+            self.timeout_secs(&*connection, None)?,
+        )
+        .await?;
+
+        let QueryResult::Documents { items, .. } = result else {
+            return Err(rmcp::ErrorData::internal_error(
+                "Unexpected count result for $sample",
+                None,
+            ));
+        };
+
+        let mut summary = schema::infer(&items);
+        summary["database"] = serde_json::json!(database);
+        summary["collection"] = serde_json::json!(params.collection_name);
+        summary["requested_sample_size"] = serde_json::json!(sample_size);
+
+        self.schemas.insert(
+            &params.connection_name,
+            &database,
+            &params.collection_name,
+            summary.clone(),
+        );
+
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&summary).unwrap(),
+        )]))
+    }
+
+    /// Fetches the next page of a truncated query_mongodb or run_saved_query result.
+    ///
+    /// Pass the next_page_token from the previous result's metadata. Tokens are
+    /// single-use and expire after 30 minutes; each page may return a new token.
+    /// Pages are skip-based and ordered with _id as the final sort key. Pipelines
+    /// whose output lacks a unique _id, or writes between pages, can still cause
+    /// documents to repeat or be missed.
+    #[tool]
+    async fn get_next_page(
+        &self,
+        Parameters(params): Parameters<GetNextPageParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let cursor = self.pages.take(&params.page_token).ok_or_else(|| {
+            rmcp::ErrorData::invalid_params(
+                format!(
+                    "Page token '{}' is unknown, already used or expired. Re-run the query.",
+                    params.page_token
+                ),
+                None,
+            )
+        })?;
+
+        let connection = self.connection(&cursor.connection)?;
+
+        self.run_paged_query(&*connection, cursor, None).await
     }
 
     /// Saves a query for reuse (upsert: same name overwrites existing).
@@ -316,10 +966,10 @@ impl McpServer {
         &self,
         Parameters(params): Parameters<SaveQueryParams>,
     ) -> Result<CallToolResult, rmcp::ErrorData> {
-        // Verify connection exists
-        self.connections
-            .get(&params.connection_name)
-            .ok_or_else(|| self.connection_not_found(&params.connection_name))?;
+        let connection = self.connection(&params.connection_name)?;
+
+        check_collection_access(&*connection, &params.collection_name, Some(&params.query))
+            .map_err(|e| rmcp::ErrorData::invalid_params(format_error(&e), None))?;
 
         let mut saved_queries = SavedQueries::load(&params.connection_name)
             .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
@@ -356,9 +1006,7 @@ impl McpServer {
         &self,
         Parameters(params): Parameters<ListSavedQueriesParams>,
     ) -> Result<CallToolResult, rmcp::ErrorData> {
-        self.connections
-            .get(&params.connection_name)
-            .ok_or_else(|| self.connection_not_found(&params.connection_name))?;
+        self.connection(&params.connection_name)?;
 
         let saved_queries = SavedQueries::load(&params.connection_name)
             .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
@@ -382,9 +1030,7 @@ impl McpServer {
         &self,
         Parameters(params): Parameters<GetSavedQueryParams>,
     ) -> Result<CallToolResult, rmcp::ErrorData> {
-        self.connections
-            .get(&params.connection_name)
-            .ok_or_else(|| self.connection_not_found(&params.connection_name))?;
+        self.connection(&params.connection_name)?;
 
         let saved_queries = SavedQueries::load(&params.connection_name)
             .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
@@ -412,9 +1058,7 @@ impl McpServer {
         &self,
         Parameters(params): Parameters<DeleteSavedQueryParams>,
     ) -> Result<CallToolResult, rmcp::ErrorData> {
-        self.connections
-            .get(&params.connection_name)
-            .ok_or_else(|| self.connection_not_found(&params.connection_name))?;
+        self.connection(&params.connection_name)?;
 
         let mut saved_queries = SavedQueries::load(&params.connection_name)
             .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
@@ -450,10 +1094,7 @@ impl McpServer {
         &self,
         Parameters(params): Parameters<RunSavedQueryParams>,
     ) -> Result<CallToolResult, rmcp::ErrorData> {
-        let connection = self
-            .connections
-            .get(&params.connection_name)
-            .ok_or_else(|| self.connection_not_found(&params.connection_name))?;
+        let connection = self.connection(&params.connection_name)?;
 
         let saved_queries = SavedQueries::load(&params.connection_name)
             .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
@@ -521,28 +1162,90 @@ impl McpServer {
             sort: params.sort,
             projection: params.projection,
             distinct_field: params.distinct_field.or(saved_query.distinct_field.clone()),
+            ejson_mode: connection.settings().extended_json,
+            skip: None,
+            limits: Some(connection.settings().result_limits()),
+            max_time_ms: None,
+            read: None,
         };
 
-        let result = connection
-            .execute_query(&saved_query.collection, &operation, &query, &options, 30)
+        let database = self.resolve_database(&*connection, params.database_name.as_deref())?;
+        let timeout_secs = self.timeout_secs(&*connection, params.timeout_secs)?;
+
+        let cursor = PageCursor {
+            connection: params.connection_name,
+            database,
+            collection: saved_query.collection.clone(),
+            operation,
+            query,
+            options,
+            timeout_secs,
+            saved_query: Some(params.query_name),
+        };
+
+        self.run_paged_query(&*connection, cursor, warning).await
+    }
+
+    /// Lists recently executed queries from the audit log, newest first.
+    ///
+    /// Each entry has the connection, database, collection, operation, final query
+    /// (after variable substitution), saved query name, duration, documents and
+    /// bytes returned, or the error. Use to revisit or refine earlier queries.
+    /// Only available when the config enables audit.history.
+    #[tool]
+    async fn query_history(
+        &self,
+        Parameters(params): Parameters<QueryHistoryParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        if !self.audit.is_enabled() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "The audit log is disabled. Set audit.enabled: true in config.yaml to record queries.",
+            )]));
+        }
+        // Entries are not per client: every session would see every other's queries
+        if !self.audit.history_enabled() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "Query history is disabled. Set audit.history: true in config.yaml to let \
+                 clients read the audit log; it includes the queries of every client.",
+            )]));
+        }
+
+        if let Some(name) = &params.connection_name {
+            self.connection(name)?;
+        }
+
+        let limit = params.limit.unwrap_or(20).clamp(1, 200) as usize;
+        let entries = self
+            .audit
+            .recent(params.connection_name.as_deref(), limit)
             .await
             .map_err(|e| rmcp::ErrorData::internal_error(format_error(&e), None))?;
 
-        // Include warning if applicable
-        let output = if let Some(warn) = warning {
-            format!("{}\n\n{}", warn, result)
-        } else {
-            result
-        };
+        let response = serde_json::json!({
+            "queries": entries,
+            "count": entries.len()
+        });
 
-        Ok(CallToolResult::success(vec![Content::text(output)]))
+        Ok(CallToolResult::success(vec![Content::text(
+            serde_json::to_string_pretty(&response).unwrap(),
+        )]))
     }
 }
 
 #[tool_handler]
 impl ServerHandler for McpServer {
+    /// Remember the client so it hears about connection changes on config reload
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        self.peers.add(context.peer);
+    }
+
     fn get_info(&self) -> ServerInfo {
-        ServerInfo::new(ServerCapabilities::builder().enable_tools().build())
+        ServerInfo::new(
+            ServerCapabilities::builder()
+                .enable_tools()
+                .enable_tool_list_changed()
+                .build(),
+        )
             .with_server_info(rmcp::model::Implementation::new(
                 self.name.clone(),
                 self.version.clone(),
@@ -550,12 +1253,17 @@ impl ServerHandler for McpServer {
             .with_instructions(
                 "Read-only MongoDB query server. Workflow: \
                  1) list_connections to see available connections, \
-                 2) get_data_model to understand the schema, \
+                 2) get_data_model to understand the schema (or infer_schema when none is documented), \
                  3) list_collections to get exact collection names (case-sensitive!), \
                  4) query_mongodb to run queries. \
+                 Use collection_stats to gauge collection size, and list_indexes and \
+                 explain_query to check query plans on large collections. \
+                 Large results are truncated with a next_page_token; use get_next_page to continue. \
                  For time-based queries, use get_current_time first. \
                  Save reusable queries with save_query using {{placeholder}} variables, \
-                 then run them with run_saved_query providing variable values.",
+                 then run them with run_saved_query providing variable values. \
+                 query_history lists queries you ran earlier. \
+                 If a connection keeps failing, check_connection shows which step breaks.",
             )
     }
 }
@@ -564,6 +1272,72 @@ impl ServerHandler for McpServer {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_check_redaction() {
+        let redaction: Redaction =
+            serde_yaml::from_str("- path: ssn\n- path: email\n  action: hash").unwrap();
+        let options = QueryOptions {
+            distinct_field: Some("email".to_string()),
+            ..Default::default()
+        };
+
+        let field = check_redaction(
+            &redaction,
+            &mongodb::QueryOperation::Distinct,
+            "{}",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(field.as_deref(), Some("email"));
+
+        let options = QueryOptions {
+            distinct_field: Some("ssn".to_string()),
+            ..Default::default()
+        };
+        assert!(
+            check_redaction(
+                &redaction,
+                &mongodb::QueryOperation::Distinct,
+                "{}",
+                &options
+            )
+            .is_err()
+        );
+
+        let options = QueryOptions {
+            projection: Some(r#"{"copy": "$ssn"}"#.to_string()),
+            ..Default::default()
+        };
+        assert!(
+            check_redaction(&redaction, &mongodb::QueryOperation::Find, "{}", &options).is_err()
+        );
+    }
+
+    #[test]
+    fn test_accessible_collections_hide_views_on_denied() {
+        let specs = vec![
+            serde_json::json!({"name": "users", "type": "collection", "options": {}}),
+            serde_json::json!({"name": "secrets", "type": "collection", "options": {}}),
+            serde_json::json!({"name": "leak", "type": "view", "options": {"viewOn": "secrets"}}),
+            serde_json::json!({"name": "joined", "type": "view", "options": {
+                "viewOn": "users",
+                "pipeline": [{"$unionWith": "leak"}]
+            }}),
+            serde_json::json!({"name": "active", "type": "view", "options": {"viewOn": "users"}}),
+        ];
+        let collections = crate::collections::from_specs(&specs).unwrap();
+        let settings = QuerySettings {
+            denied_collections: vec!["secrets".to_string()],
+            ..Default::default()
+        };
+
+        let names: Vec<String> = accessible_collections(&settings, collections)
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, ["active", "users"]);
+    }
+
     #[test]
     fn test_find_placeholders() {
         let query = r#"{"userId": "{{userId}}", "date": {"$gte": "{{startDate}}"}}"#;
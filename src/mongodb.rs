@@ -1,22 +1,43 @@
 //! MongoDB query operations and mongosh execution.
 
 use anyhow::{Context, Result, anyhow, bail};
+use mongodb::bson::{Bson, Document};
 use serde::{Deserialize, Serialize};
 
+use crate::collections::{self, CollectionInfo};
+use crate::config::NamespaceConfig;
+use crate::credentials::K8sCredentials;
+use crate::ejson::ExtJsonMode;
+use crate::indexes::{self, IndexInfo};
 use crate::k8s_client::K8sClient;
+use crate::read_preference::ReadSettings;
+use crate::results::{PageBuilder, QueryResult, ResultLimits};
+use crate::validation::validate_read_only;
 
 #[derive(Debug, Clone)]
 pub struct MongoCredentials {
     pub username: String,
     pub password: String,
+    /// Database the user authenticates against
+    pub auth_source: String,
 }
 
-/// Build mongosh command with authentication
+/// Build mongosh command with authentication. Read settings are passed as
+/// connection string options for the local server; that server is the only one
+/// mongosh reaches, so they can't route reads (see `prefer_secondary`).
 fn build_mongosh_command(
     credentials: &MongoCredentials,
     database: &str,
+    read: &ReadSettings,
     eval_code: String,
 ) -> Vec<String> {
+    let read_options = read.connection_string_options();
+    let database = if read_options.is_empty() {
+        database.to_string()
+    } else {
+        format!("mongodb://127.0.0.1/{database}?{}", read_options.join("&"))
+    };
+
     vec![
         "mongosh".to_string(),
         "-u".to_string(),
@@ -24,8 +45,8 @@ fn build_mongosh_command(
         "-p".to_string(),
         credentials.password.clone(),
         "--authenticationDatabase".to_string(),
-        "admin".to_string(),
-        database.to_string(),
+        credentials.auth_source.clone(),
+        database,
         "--quiet".to_string(),
         "--eval".to_string(),
         eval_code,
@@ -52,6 +73,182 @@ pub struct QueryOptions {
     pub projection: Option<String>,
     /// Field name for distinct operation (distinct only)
     pub distinct_field: Option<String>,
+    /// Extended JSON mode used to render results
+    pub ejson_mode: ExtJsonMode,
+    /// Number of results to skip (set when resuming a truncated result)
+    pub skip: Option<u64>,
+    /// Server-enforced result size limits (None = unlimited)
+    pub limits: Option<ResultLimits>,
+    /// Server-side time limit sent as `maxTimeMS`. Set by the backends from the call timeout.
+    pub max_time_ms: Option<u64>,
+    /// Read preference and read concern for this call instead of the connection's
+    pub read: Option<ReadSettings>,
+}
+
+/// Stages that keep the order of the documents they pass on, so a `$sort` before
+/// them still decides the order of the results
+const ORDER_PRESERVING_STAGES: &[&str] = &[
+    "$match",
+    "$project",
+    "$addFields",
+    "$set",
+    "$unset",
+    "$unwind",
+    "$lookup",
+    "$graphLookup",
+    "$replaceRoot",
+    "$replaceWith",
+    "$redact",
+    "$skip",
+    "$limit",
+];
+
+/// Stages that put documents in an order of their own (distance, relevance,
+/// bucket boundaries), which paging keeps rather than sorting by `_id`
+const ORDER_SETTING_STAGES: &[&str] = &[
+    "$geoNear",
+    "$search",
+    "$vectorSearch",
+    "$searchMeta",
+    "$bucket",
+    "$bucketAuto",
+];
+
+/// Where the order of documents at a point in a pipeline comes from
+#[derive(Debug, Clone, Copy)]
+enum Order {
+    /// No stage decides it
+    Undefined,
+    /// The `$sort` stage at this index, which may still need a tie-breaker
+    Sort(usize),
+    /// A stage the pipeline can't refine, or a `$sort` that already has one
+    Settled,
+}
+
+/// Extra seconds the client waits past a call's timeout, so MongoDB's own
+/// `maxTimeMS` error arrives before the client gives up
+pub const CLIENT_TIMEOUT_GRACE_SECS: u64 = 5;
+
+impl QueryOptions {
+    /// Copy of these options with `maxTimeMS` set to `timeout_secs`
+    pub fn with_max_time(&self, timeout_secs: u64) -> Self {
+        Self {
+            max_time_ms: Some(timeout_secs.saturating_mul(1000)),
+            ..self.clone()
+        }
+    }
+
+    /// Server-side limit for find: the requested limit capped at one more than
+    /// max_documents, so truncation is detected without reading further.
+    pub fn effective_find_limit(&self) -> Option<i64> {
+        let cap = self
+            .limits
+            .map(|l| i64::try_from(l.max_documents).unwrap_or(i64::MAX - 1) + 1);
+        match (self.limit.map(i64::from), cap) {
+            (Some(limit), Some(cap)) => Some(limit.min(cap)),
+            (limit, cap) => limit.or(cap),
+        }
+    }
+
+    /// Sort for find. When paging, `_id` breaks ties: pages are skip-based, and
+    /// documents in an undefined order could repeat or be missed across pages.
+    pub fn find_sort(&self) -> Result<Option<String>> {
+        if self.limits.is_none() {
+            return Ok(self.sort.clone());
+        }
+        let mut sort = match &self.sort {
+            Some(sort) => serde_json::from_str(sort)
+                .with_context(|| format!("Sort is not valid JSON: '{sort}'"))?,
+            None => serde_json::json!({}),
+        };
+        let serde_json::Value::Object(keys) = &mut sort else {
+            bail!("Sort must be a JSON object");
+        };
+        // $natural can't be combined with other sort keys
+        if !keys.contains_key("_id") && !keys.contains_key("$natural") {
+            keys.insert("_id".to_string(), serde_json::json!(1));
+        }
+        Ok(Some(sort.to_string()))
+    }
+
+    /// Aggregation pipeline to run. When paging, the order must be the same on
+    /// every page, at the end and wherever a `$limit` or `$skip` picks documents:
+    /// `_id` breaks ties in the `$sort` deciding the order there, and a `$sort` on
+    /// `_id` is added where no stage decides it. Stages with an order of their own
+    /// (`$geoNear`, `$search`, ...) are left alone. `$skip`/`$limit` stages then
+    /// select the page.
+    pub fn paged_pipeline(&self, query: &str) -> Result<String> {
+        if self.limits.is_none() && self.skip.is_none_or(|s| s == 0) {
+            return Ok(query.to_string());
+        }
+        let pipeline = match serde_json::from_str(query).context("Query is not valid JSON")? {
+            serde_json::Value::Array(pipeline) => pipeline,
+            _ => bail!("Aggregation pipeline must be a JSON array of stages"),
+        };
+
+        let mut paged = Vec::with_capacity(pipeline.len() + 3);
+        let mut order = Order::Undefined;
+        for stage in pipeline {
+            let name = stage
+                .as_object()
+                .and_then(|stage| stage.keys().next())
+                .cloned()
+                .unwrap_or_default();
+            if name == "$limit" || name == "$skip" {
+                order = settle_order(&mut paged, order);
+            }
+            let sorts_windows = name == "$setWindowFields"
+                && stage.get(&name).and_then(|s| s.get("sortBy")).is_some();
+            paged.push(stage);
+            order = match name.as_str() {
+                "$sort" => Order::Sort(paged.len() - 1),
+                // Output is one document per unique _id, so ties on count break on it
+                "$sortByCount" => {
+                    paged.push(serde_json::json!({ "$sort": { "count": -1, "_id": 1 } }));
+                    Order::Settled
+                }
+                _ if sorts_windows => Order::Settled,
+                name if ORDER_SETTING_STAGES.contains(&name) => Order::Settled,
+                name if ORDER_PRESERVING_STAGES.contains(&name) => order,
+                _ => Order::Undefined,
+            };
+        }
+        settle_order(&mut paged, order);
+
+        paged.extend(self.pagination_stages());
+        Ok(serde_json::Value::Array(paged).to_string())
+    }
+
+    /// `$skip`/`$limit` stages appended to aggregation pipelines for pagination
+    pub fn pagination_stages(&self) -> Vec<serde_json::Value> {
+        let mut stages = Vec::new();
+        if let Some(skip) = self.skip.filter(|s| *s > 0) {
+            stages.push(serde_json::json!({ "$skip": skip }));
+        }
+        if let Some(limits) = self.limits {
+            stages.push(serde_json::json!({ "$limit": limits.max_documents.saturating_add(1) }));
+        }
+        stages
+    }
+}
+
+/// Make the order at the end of `pipeline` the same on every run, before
+/// documents are skipped or cut off
+fn settle_order(pipeline: &mut Vec<serde_json::Value>, order: Order) -> Order {
+    match order {
+        Order::Sort(i) => {
+            if let Some(keys) = pipeline[i]
+                .get_mut("$sort")
+                .and_then(serde_json::Value::as_object_mut)
+                && !keys.contains_key("_id")
+            {
+                keys.insert("_id".to_string(), serde_json::json!(1));
+            }
+        }
+        Order::Undefined => pipeline.push(serde_json::json!({ "$sort": { "_id": 1 } })),
+        Order::Settled => {}
+    }
+    Order::Settled
 }
 
 impl QueryOperation {
@@ -67,53 +264,85 @@ impl QueryOperation {
         }
     }
 
+    /// Operation name as accepted by `from_str`
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Find => "find",
+            Self::Aggregate => "aggregate",
+            Self::CountDocuments => "countDocuments",
+            Self::Distinct => "distinct",
+        }
+    }
+
     pub fn to_mongosh_code(
         &self,
         collection: &str,
         query: &str,
         options: &QueryOptions,
     ) -> Result<String> {
-        // Validate query is valid JSON
-        let _: serde_json::Value = serde_json::from_str(query)
+        // Validate query is valid JSON and read-only
+        let parsed: serde_json::Value = serde_json::from_str(query)
             .with_context(|| format!(
                 "Query is not valid JSON. Received: '{query}'. Please ensure the query is a valid JSON string."
             ))?;
+        validate_read_only(&parsed, "query")?;
 
         // Escape collection name for safe use in JavaScript
         // Use bracket notation with JSON-escaped string to prevent injection
         let safe_collection =
             serde_json::to_string(collection).context("Failed to escape collection name")?;
 
-        // Wrap all outputs with JSON.stringify() to ensure valid JSON output
+        // Results are rendered with EJSON.stringify() so BSON types survive the round trip
+        let ejson_options = options.ejson_mode.mongosh_options();
+
         let code = match self {
             Self::Find => {
                 // Build find with optional projection, sort, and limit
                 let projection = options.projection.as_deref().unwrap_or("{}");
-                // Validate projection is valid JSON
-                let _: serde_json::Value = serde_json::from_str(projection)
+                // Validate projection is valid JSON and read-only
+                let parsed_projection: serde_json::Value = serde_json::from_str(projection)
                     .with_context(|| format!("Projection is not valid JSON: '{projection}'"))?;
+                validate_read_only(&parsed_projection, "projection")?;
+
+                let mut chain = format!(
+                    "db[{safe_collection}].find({}, {})",
+                    ejson_literal(query)?,
+                    ejson_literal(projection)?
+                );
 
-                let mut chain = format!("db[{safe_collection}].find({query}, {projection})");
+                if let Some(sort) = options.find_sort()? {
+                    chain = format!("{chain}.sort({})", ejson_literal(&sort)?);
+                }
 
-                if let Some(sort) = &options.sort {
-                    // Validate sort is valid JSON
-                    let _: serde_json::Value = serde_json::from_str(sort)
-                        .with_context(|| format!("Sort is not valid JSON: '{sort}'"))?;
-                    chain = format!("{chain}.sort({sort})");
+                if let Some(skip) = options.skip.filter(|s| *s > 0) {
+                    chain = format!("{chain}.skip({skip})");
                 }
 
-                if let Some(limit) = options.limit {
+                if let Some(limit) = options.effective_find_limit() {
                     chain = format!("{chain}.limit({limit})");
                 }
 
-                format!("JSON.stringify({chain}.toArray())")
+                if let Some(ms) = options.max_time_ms {
+                    chain = format!("{chain}.maxTimeMS({ms})");
+                }
+
+                format!("EJSON.stringify({chain}.toArray(), {ejson_options})")
             }
             Self::Aggregate => {
-                format!("JSON.stringify(db[{safe_collection}].aggregate({query}).toArray())")
+                let pipeline = options.paged_pipeline(query)?;
+                format!(
+                    "EJSON.stringify(db[{safe_collection}].aggregate({}{}).toArray(), {ejson_options})",
+                    ejson_literal(&pipeline)?,
+                    max_time_argument(options)
+                )
             }
             Self::CountDocuments => {
-                // countDocuments returns a number, no need for JSON.stringify
-                format!("db[{safe_collection}].countDocuments({query})")
+                // countDocuments returns a number, no need for EJSON.stringify
+                format!(
+                    "db[{safe_collection}].countDocuments({}{})",
+                    ejson_literal(query)?,
+                    max_time_argument(options)
+                )
             }
             Self::Distinct => {
                 // First check if distinct_field option is provided (new simpler format)
@@ -121,12 +350,7 @@ impl QueryOperation {
                     field.clone()
                 } else {
                     // Fall back to legacy format: {"field": "fieldName", "query": {...}}
-                    let distinct_params: serde_json::Value = serde_json::from_str(query)
-                        .with_context(|| {
-                            format!("Distinct query must be valid JSON. Received: '{query}'")
-                        })?;
-
-                    distinct_params
+                    parsed
                         .get("field")
                         .and_then(|v| v.as_str())
                         .ok_or_else(|| anyhow!("Distinct requires 'distinct_field' parameter"))?
@@ -143,13 +367,16 @@ impl QueryOperation {
                     query.to_string()
                 } else {
                     // Legacy format - extract query from {"field": ..., "query": ...}
-                    let distinct_params: serde_json::Value = serde_json::from_str(query)?;
-                    distinct_params
+                    parsed
                         .get("query")
                         .map_or_else(|| "{}".to_string(), |v| v.to_string())
                 };
 
-                format!("JSON.stringify(db[{safe_collection}].distinct({safe_field}, {filter}))")
+                format!(
+                    "EJSON.stringify(db[{safe_collection}].distinct({safe_field}, {}{}), {ejson_options})",
+                    ejson_literal(&filter)?,
+                    max_time_argument(options)
+                )
             }
         };
 
@@ -157,6 +384,20 @@ impl QueryOperation {
     }
 }
 
+/// Trailing `{maxTimeMS: ...}` options argument for aggregate, countDocuments and distinct
+fn max_time_argument(options: &QueryOptions) -> String {
+    options
+        .max_time_ms
+        .map_or_else(String::new, |ms| format!(", {{maxTimeMS: {ms}}}"))
+}
+
+/// Wrap a JSON string in `EJSON.parse(...)` so mongosh decodes Extended JSON types.
+/// The JSON is passed as an escaped string literal rather than as raw JavaScript.
+fn ejson_literal(json: &str) -> Result<String> {
+    let literal = serde_json::to_string(json).context("Failed to escape query JSON")?;
+    Ok(format!("EJSON.parse({literal})"))
+}
+
 #[derive(Debug)]
 pub struct MongoQuery {
     pub database: String,
@@ -166,82 +407,164 @@ pub struct MongoQuery {
     pub options: QueryOptions,
 }
 
-/// Get `MongoDB` credentials from pod environment variables
+/// Get `MongoDB` credentials for a namespace connection using its credential strategy
 pub async fn get_mongodb_credentials(
     k8s_client: &K8sClient,
-    namespace: &str,
+    config: &NamespaceConfig,
     pod_name: &str,
-    container_name: &str,
+    container_name: Option<&str>,
 ) -> Result<MongoCredentials> {
+    let namespace = config.namespace_name.as_str();
     tracing::debug!(
-        "Getting MongoDB credentials from pod {}/{} container {}",
+        "Getting MongoDB credentials from pod {}/{} container {:?}",
         namespace,
         pod_name,
         container_name
     );
 
-    // Get both env vars in a single pod fetch
-    let env_vars = k8s_client
-        .get_pod_env_vars(
-            namespace,
-            pod_name,
-            &[
-                "MONGO_INITDB_ROOT_USERNAME_FILE",
-                "MONGO_INITDB_ROOT_PASSWORD_FILE",
-            ],
-        )
-        .await?;
-
-    let username_file_path = env_vars.first().cloned().flatten().ok_or_else(|| anyhow!(
-        "MongoDB credentials not found: MONGO_INITDB_ROOT_USERNAME_FILE environment variable missing in pod '{namespace}/{pod_name}'."
-    ))?;
-    let password_file_path = env_vars.get(1).cloned().flatten().ok_or_else(|| anyhow!(
-        "MongoDB credentials not found: MONGO_INITDB_ROOT_PASSWORD_FILE environment variable missing in pod '{namespace}/{pod_name}'."
-    ))?;
-
-    tracing::debug!(
-        "Username file: {}, Password file: {}",
-        username_file_path,
-        password_file_path
-    );
+    let (username, password) = match &config.credentials {
+        K8sCredentials::Env {
+            username_env,
+            password_env,
+        } => {
+            // Get both env vars in a single pod fetch
+            let env_vars = k8s_client
+                .get_pod_env_vars(
+                    namespace,
+                    pod_name,
+                    container_name,
+                    &[username_env.as_str(), password_env.as_str()],
+                )
+                .await?;
 
-    // Read both credential files in parallel
-    let (username_result, password_result) = tokio::join!(
-        k8s_client.read_file_from_pod(namespace, pod_name, container_name, &username_file_path),
-        k8s_client.read_file_from_pod(namespace, pod_name, container_name, &password_file_path)
-    );
+            let missing = |name: &str| {
+                anyhow!(
+                    "MongoDB credentials not found: {name} environment variable missing in pod '{namespace}/{pod_name}'."
+                )
+            };
+            let mut env_vars = env_vars.into_iter();
+            let username = env_vars
+                .next()
+                .flatten()
+                .ok_or_else(|| missing(username_env))?;
+            let password = env_vars
+                .next()
+                .flatten()
+                .ok_or_else(|| missing(password_env))?;
 
-    let username = username_result
-        .context("Failed to read username file")?
-        .trim()
-        .to_string();
-    let password = password_result
-        .context("Failed to read password file")?
-        .trim()
-        .to_string();
+            // Read both credential files in parallel
+            let (username, password) = tokio::join!(
+                env_credential(
+                    k8s_client,
+                    namespace,
+                    pod_name,
+                    container_name,
+                    username_env,
+                    username
+                ),
+                env_credential(
+                    k8s_client,
+                    namespace,
+                    pod_name,
+                    container_name,
+                    password_env,
+                    password
+                )
+            );
+            (
+                username.context("Failed to read username")?,
+                password.context("Failed to read password")?,
+            )
+        }
+        K8sCredentials::Secret {
+            name,
+            username_key,
+            password_key,
+        } => {
+            let (username, password) = tokio::join!(
+                k8s_client.read_secret_key(namespace, name, username_key),
+                k8s_client.read_secret_key(namespace, name, password_key)
+            );
+            (username?, password?)
+        }
+        K8sCredentials::User(user) => {
+            let (username, password) = user.resolve(Some(k8s_client)).await?;
+            (
+                username.context("The user credential strategy requires a username")?,
+                password.context("The user credential strategy requires a password source")?,
+            )
+        }
+    };
 
-    Ok(MongoCredentials { username, password })
+    Ok(MongoCredentials {
+        username,
+        password,
+        auth_source: config.auth_source.clone(),
+    })
 }
 
-/// Execute a `MongoDB` query via mongosh
-pub async fn execute_mongosh_query(
+/// Value of a credential environment variable. Variables named `*_FILE` hold the
+/// path of a file in the container, as with the official image's conventions.
+async fn env_credential(
     k8s_client: &K8sClient,
     namespace: &str,
     pod_name: &str,
-    container_name: &str,
-    credentials: &MongoCredentials,
-    query: &MongoQuery,
-    timeout_secs: u64,
+    container_name: Option<&str>,
+    env_name: &str,
+    value: String,
 ) -> Result<String> {
-    // Build mongosh eval code
-    let eval_code =
-        query
-            .operation
-            .to_mongosh_code(&query.collection, &query.query, &query.options)?;
+    if !env_name.ends_with("_FILE") {
+        return Ok(value);
+    }
+    tracing::debug!("Reading {} from file {}", env_name, value);
+    let content = k8s_client
+        .read_file_from_pod(namespace, pod_name, container_name, &value)
+        .await?;
+    Ok(content.trim().to_string())
+}
+
+/// Where mongosh runs: a container in a pod, with discovered credentials
+pub struct MongoshTarget<'a> {
+    pub k8s_client: &'a K8sClient,
+    pub namespace: &'a str,
+    pub pod_name: &'a str,
+    pub container_name: Option<&'a str>,
+    pub credentials: &'a MongoCredentials,
+    /// The connection's read preference and read concern
+    pub read: &'a ReadSettings,
+}
 
-    tracing::debug!("Mongosh eval code: {}", eval_code);
+impl MongoshTarget<'_> {
+    /// Run `mongosh --eval` against a database and return raw output
+    async fn eval(&self, database: &str, eval_code: String, timeout_secs: u64) -> Result<String> {
+        tracing::debug!("Mongosh eval code: {}", eval_code);
 
-    let command = build_mongosh_command(credentials, &query.database, eval_code);
+        let command = build_mongosh_command(self.credentials, database, self.read, eval_code);
+
+        self.k8s_client
+            .exec_command_in_pod(
+                self.namespace,
+                self.pod_name,
+                self.container_name,
+                command,
+                timeout_secs,
+            )
+            .await
+            .context("Failed to execute mongosh command")
+    }
+}
+
+/// Execute a `MongoDB` query via mongosh
+pub async fn execute_mongosh_query(
+    target: &MongoshTarget<'_>,
+    query: &MongoQuery,
+    timeout_secs: u64,
+) -> Result<QueryResult> {
+    // Build mongosh eval code; maxTimeMS makes the server cancel the query itself
+    let options = query.options.with_max_time(timeout_secs);
+    let eval_code = query
+        .operation
+        .to_mongosh_code(&query.collection, &query.query, &options)?;
 
     tracing::info!(
         "Executing query: {:?} on {}.{}",
@@ -250,45 +573,195 @@ pub async fn execute_mongosh_query(
         query.collection
     );
 
+    // A per-call read preference replaces the connection's
+    let target = MongoshTarget {
+        read: query.options.read.as_ref().unwrap_or(target.read),
+        ..*target
+    };
+
     // Execute command with timeout
+    let output = target
+        .eval(
+            &query.database,
+            eval_code,
+            timeout_secs + CLIENT_TIMEOUT_GRACE_SECS,
+        )
+        .await?;
+
+    // Parse and validate output
+    let output = parse_mongosh_output(&output, &query.collection, &query.database)?;
+    parse_query_result(&output, &query.operation, &query.options)
+}
+
+/// Run `hello` with mongosh in a pod. `hello` needs no authentication, so this
+/// works before credentials are discovered.
+pub async fn hello(
+    k8s_client: &K8sClient,
+    namespace: &str,
+    pod_name: &str,
+    container_name: Option<&str>,
+    timeout_secs: u64,
+) -> Result<serde_json::Value> {
+    let command = vec![
+        "mongosh".to_string(),
+        "--quiet".to_string(),
+        "--eval".to_string(),
+        "EJSON.stringify(db.hello())".to_string(),
+    ];
     let output = k8s_client
         .exec_command_in_pod(namespace, pod_name, container_name, command, timeout_secs)
         .await
-        .context("Failed to execute mongosh command")?;
+        .context("Failed to run hello")?;
+    let output = parse_mongosh_output(&output, "", "admin")?;
 
-    // Parse and validate output
-    parse_mongosh_output(&output, &query.collection, &query.database)
+    serde_json::from_str(&output).with_context(|| format!("Failed to parse hello reply: {output}"))
 }
 
-/// List all collections in a database
-pub async fn list_collections(
+/// Version of the mongosh binary in a pod's container
+pub async fn mongosh_version(
     k8s_client: &K8sClient,
     namespace: &str,
     pod_name: &str,
-    container_name: &str,
-    credentials: &MongoCredentials,
+    container_name: Option<&str>,
+    timeout_secs: u64,
+) -> Result<String> {
+    let command = vec!["mongosh".to_string(), "--version".to_string()];
+    let output = k8s_client
+        .exec_command_in_pod(namespace, pod_name, container_name, command, timeout_secs)
+        .await
+        .context("Failed to run mongosh")?;
+
+    // A missing binary shows up as an error message instead of a version
+    let version = output.trim();
+    if !version.starts_with(|c: char| c.is_ascii_digit()) {
+        bail!("mongosh is not available in the container: {version}");
+    }
+    Ok(version.to_string())
+}
+
+/// Build mongosh code running a command and printing its reply as relaxed Extended JSON
+fn run_command_code(command: Document) -> Result<String> {
+    let command_json = Bson::Document(command).into_canonical_extjson().to_string();
+    Ok(format!(
+        "EJSON.stringify(db.runCommand({}), {})",
+        ejson_literal(&command_json)?,
+        ExtJsonMode::Relaxed.mongosh_options()
+    ))
+}
+
+/// Run a database command via `db.runCommand` and return its reply as relaxed Extended JSON.
+/// The command is passed as canonical Extended JSON so BSON types and key order survive.
+pub async fn run_command(
+    target: &MongoshTarget<'_>,
+    database: &str,
+    command: Document,
+    timeout_secs: u64,
+) -> Result<serde_json::Value> {
+    let eval_code = run_command_code(command)?;
+
+    let output = target.eval(database, eval_code, timeout_secs).await?;
+    let output = parse_mongosh_output(&output, "", database)?;
+
+    let reply: serde_json::Value = serde_json::from_str(&output)
+        .with_context(|| format!("Failed to parse command reply: {output}"))?;
+    if reply.get("ok").and_then(serde_json::Value::as_f64) == Some(0.0) {
+        bail!(
+            "MongoDB command failed: {}",
+            reply
+                .get("errmsg")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or("unknown error")
+        );
+    }
+    Ok(reply)
+}
+
+/// Convert validated mongosh output into a query result, applying result limits.
+/// Find and aggregate are already skipped/limited server-side; distinct is paged here.
+pub fn parse_query_result(
+    output: &str,
+    operation: &QueryOperation,
+    options: &QueryOptions,
+) -> Result<QueryResult> {
+    if matches!(operation, QueryOperation::CountDocuments) {
+        let count = output
+            .trim()
+            .parse::<u64>()
+            .with_context(|| format!("Failed to parse countDocuments result: {output}"))?;
+        return Ok(QueryResult::Count(count));
+    }
+
+    let items: Vec<serde_json::Value> = serde_json::from_str(output)
+        .with_context(|| format!("Expected a JSON array from mongosh, got: {output}"))?;
+
+    let skip = if matches!(operation, QueryOperation::Distinct) {
+        usize::try_from(options.skip.unwrap_or(0)).unwrap_or(usize::MAX)
+    } else {
+        0
+    };
+
+    Ok(PageBuilder::collect(
+        items.into_iter().skip(skip),
+        options.limits,
+    ))
+}
+
+/// List all collections in a database
+pub async fn list_collections(
+    target: &MongoshTarget<'_>,
     database: &str,
-) -> Result<Vec<String>> {
-    let eval_code = "JSON.stringify(db.getCollectionNames())".to_string();
-    let command = build_mongosh_command(credentials, database, eval_code);
+    timeout_secs: u64,
+) -> Result<Vec<CollectionInfo>> {
+    let eval_code = format!(
+        "EJSON.stringify(db.getCollectionInfos(), {})",
+        ExtJsonMode::Relaxed.mongosh_options()
+    );
 
     tracing::info!("Listing collections in database: {}", database);
 
-    let output = k8s_client
-        .exec_command_in_pod(namespace, pod_name, container_name, command, 30)
+    let output = target
+        .eval(database, eval_code, timeout_secs)
         .await
         .context("Failed to list collections")?;
 
     let trimmed = output.trim();
 
-    // Parse the JSON array of collection names
-    let mut collections: Vec<String> = serde_json::from_str(trimmed)
+    // Parse the JSON array of collection specifications
+    let specs: Vec<serde_json::Value> = serde_json::from_str(trimmed)
         .with_context(|| format!("Failed to parse collection list: {trimmed}"))?;
 
-    // Sort for deterministic output
-    collections.sort();
+    collections::from_specs(&specs)
+}
+
+fn list_indexes_code(collection: &str) -> Result<String> {
+    let safe_collection =
+        serde_json::to_string(collection).context("Failed to escape collection name")?;
+    Ok(format!(
+        "EJSON.stringify(db[{safe_collection}].getIndexes(), {})",
+        ExtJsonMode::Relaxed.mongosh_options()
+    ))
+}
+
+/// List the indexes of a collection via `getIndexes()`
+pub async fn list_indexes(
+    target: &MongoshTarget<'_>,
+    database: &str,
+    collection: &str,
+    timeout_secs: u64,
+) -> Result<Vec<IndexInfo>> {
+    let eval_code = list_indexes_code(collection)?;
 
-    Ok(collections)
+    tracing::info!("Listing indexes of {}.{}", database, collection);
+
+    let output = target
+        .eval(database, eval_code, timeout_secs)
+        .await
+        .context("Failed to list indexes")?;
+    let output = parse_mongosh_output(&output, collection, database)?;
+
+    let specs: Vec<serde_json::Value> = serde_json::from_str(&output)
+        .with_context(|| format!("Failed to parse index list: {output}"))?;
+    indexes::from_specs(&specs)
 }
 
 /// Parse mongosh output and validate it's valid JSON
@@ -397,7 +870,10 @@ mod tests {
 
         let op = QueryOperation::Find;
         let code = op.to_mongosh_code("users", "{}", &opts).unwrap();
-        assert_eq!(code, "JSON.stringify(db[\"users\"].find({}, {}).toArray())");
+        assert_eq!(
+            code,
+            r#"EJSON.stringify(db["users"].find(EJSON.parse("{}"), EJSON.parse("{}")).toArray(), {relaxed: true})"#
+        );
 
         let op = QueryOperation::Aggregate;
         let code = op
@@ -405,12 +881,12 @@ mod tests {
             .unwrap();
         assert_eq!(
             code,
-            "JSON.stringify(db[\"users\"].aggregate([{\"$match\": {}}]).toArray())"
+            r#"EJSON.stringify(db["users"].aggregate(EJSON.parse("[{\"$match\": {}}]")).toArray(), {relaxed: true})"#
         );
 
         let op = QueryOperation::CountDocuments;
         let code = op.to_mongosh_code("users", "{}", &opts).unwrap();
-        assert_eq!(code, "db[\"users\"].countDocuments({})");
+        assert_eq!(code, r#"db["users"].countDocuments(EJSON.parse("{}"))"#);
 
         let op = QueryOperation::Distinct;
         let code = op
@@ -418,7 +894,7 @@ mod tests {
             .unwrap();
         assert_eq!(
             code,
-            "JSON.stringify(db[\"users\"].distinct(\"email\", {}))"
+            r#"EJSON.stringify(db["users"].distinct("email", EJSON.parse("{}")), {relaxed: true})"#
         );
     }
 
@@ -434,7 +910,7 @@ mod tests {
             .unwrap();
         assert_eq!(
             code,
-            "JSON.stringify(db[\"users\"].find({}, {}).limit(10).toArray())"
+            r#"EJSON.stringify(db["users"].find(EJSON.parse("{}"), EJSON.parse("{}")).limit(10).toArray(), {relaxed: true})"#
         );
 
         // Test find with sort and limit
@@ -448,7 +924,7 @@ mod tests {
             .unwrap();
         assert_eq!(
             code,
-            "JSON.stringify(db[\"users\"].find({}, {}).sort({\"createdAt\": -1}).limit(5).toArray())"
+            r#"EJSON.stringify(db["users"].find(EJSON.parse("{}"), EJSON.parse("{}")).sort(EJSON.parse("{\"createdAt\": -1}")).limit(5).toArray(), {relaxed: true})"#
         );
 
         // Test find with projection
@@ -461,7 +937,7 @@ mod tests {
             .unwrap();
         assert_eq!(
             code,
-            "JSON.stringify(db[\"users\"].find({}, {\"name\": 1}).toArray())"
+            r#"EJSON.stringify(db["users"].find(EJSON.parse("{}"), EJSON.parse("{\"name\": 1}")).toArray(), {relaxed: true})"#
         );
 
         // Test distinct with distinct_field option
@@ -474,7 +950,7 @@ mod tests {
             .unwrap();
         assert_eq!(
             code,
-            "JSON.stringify(db[\"users\"].distinct(\"country\", {}))"
+            r#"EJSON.stringify(db["users"].distinct("country", EJSON.parse("{}")), {relaxed: true})"#
         );
     }
 
@@ -488,11 +964,11 @@ mod tests {
             .unwrap();
         // The collection name should be JSON-escaped in bracket notation
         // db["test\"; db.dropDatabase(); //"].find({}) - the quotes are escaped
-        assert!(code.starts_with("JSON.stringify(db[\""));
+        assert!(code.starts_with("EJSON.stringify(db[\""));
         assert!(code.contains("\\\"")); // Contains escaped quotes
         // The malicious code is inside the string, not executed as JS
         assert!(code.contains("db.dropDatabase()")); // It's in the string
-        assert!(!code.starts_with("JSON.stringify(db.test")); // NOT using dot notation
+        assert!(!code.starts_with("EJSON.stringify(db.test")); // NOT using dot notation
     }
 
     #[test]
@@ -505,6 +981,28 @@ mod tests {
         assert!(parse_mongosh_output("MongoServerError: connection failed", "test", "db").is_err());
     }
 
+    #[test]
+    fn test_build_mongosh_command_read_settings() {
+        let credentials = MongoCredentials {
+            username: "root".to_string(),
+            password: "pw".to_string(),
+            auth_source: "admin".to_string(),
+        };
+
+        let command =
+            build_mongosh_command(&credentials, "app", &ReadSettings::default(), "1".into());
+        assert_eq!(command[7], "app");
+
+        let read: ReadSettings =
+            serde_yaml::from_str("read_preference: secondaryPreferred\nread_concern: majority")
+                .unwrap();
+        let command = build_mongosh_command(&credentials, "app", &read, "1".into());
+        assert_eq!(
+            command[7],
+            "mongodb://127.0.0.1/app?readPreference=secondaryPreferred&readConcernLevel=majority"
+        );
+    }
+
     #[test]
     fn test_to_mongosh_code_invalid_json() {
         let opts = QueryOptions::default();
@@ -517,6 +1015,282 @@ mod tests {
         assert!(op.to_mongosh_code("users", "{unclosed", &opts).is_err());
     }
 
+    #[test]
+    fn test_to_mongosh_code_extended_json() {
+        // Extended JSON is passed to EJSON.parse as a string literal, not as raw JavaScript
+        let opts = QueryOptions::default();
+        let code = QueryOperation::Find
+            .to_mongosh_code(
+                "users",
+                r#"{"_id": {"$oid": "65a1b2c3d4e5f60718293a4b"}}"#,
+                &opts,
+            )
+            .unwrap();
+        assert!(
+            code.contains(r#"EJSON.parse("{\"_id\": {\"$oid\": \"65a1b2c3d4e5f60718293a4b\"}}")"#)
+        );
+
+        let opts = QueryOptions {
+            ejson_mode: ExtJsonMode::Canonical,
+            ..Default::default()
+        };
+        let code = QueryOperation::Aggregate
+            .to_mongosh_code("users", "[]", &opts)
+            .unwrap();
+        assert!(code.ends_with("{relaxed: false})"));
+    }
+
+    #[test]
+    fn test_to_mongosh_code_max_time() {
+        let opts = QueryOptions::default().with_max_time(30);
+
+        let code = QueryOperation::Find
+            .to_mongosh_code("users", "{}", &opts)
+            .unwrap();
+        assert!(code.contains(".maxTimeMS(30000).toArray()"));
+
+        let code = QueryOperation::Aggregate
+            .to_mongosh_code("users", "[]", &opts)
+            .unwrap();
+        assert!(code.contains(r#"aggregate(EJSON.parse("[]"), {maxTimeMS: 30000})"#));
+
+        let code = QueryOperation::CountDocuments
+            .to_mongosh_code("users", "{}", &opts)
+            .unwrap();
+        assert_eq!(
+            code,
+            r#"db["users"].countDocuments(EJSON.parse("{}"), {maxTimeMS: 30000})"#
+        );
+
+        let opts = QueryOptions {
+            distinct_field: Some("email".to_string()),
+            ..opts
+        };
+        let code = QueryOperation::Distinct
+            .to_mongosh_code("users", "{}", &opts)
+            .unwrap();
+        assert!(code.contains(r#"distinct("email", EJSON.parse("{}"), {maxTimeMS: 30000})"#));
+    }
+
+    #[test]
+    fn test_to_mongosh_code_pagination() {
+        let limits = Some(ResultLimits {
+            max_documents: 100,
+            max_result_bytes: 1024,
+        });
+
+        // Server limit caps find at max_documents + 1 to detect truncation
+        let opts = QueryOptions {
+            skip: Some(200),
+            limits,
+            ..Default::default()
+        };
+        let code = QueryOperation::Find
+            .to_mongosh_code("users", "{}", &opts)
+            .unwrap();
+        assert!(code.contains(".skip(200).limit(101)"));
+
+        // A smaller user limit wins
+        let opts = QueryOptions {
+            limit: Some(10),
+            limits,
+            ..Default::default()
+        };
+        let code = QueryOperation::Find
+            .to_mongosh_code("users", "{}", &opts)
+            .unwrap();
+        assert!(code.contains(".limit(10)"));
+
+        // Aggregate pipelines get $skip/$limit stages appended
+        let opts = QueryOptions {
+            skip: Some(5),
+            limits,
+            ..Default::default()
+        };
+        let code = QueryOperation::Aggregate
+            .to_mongosh_code("users", r#"[{"$match": {}}]"#, &opts)
+            .unwrap();
+        assert!(code.contains(
+            r#"[{\"$match\":{}},{\"$sort\":{\"_id\":1}},{\"$skip\":5},{\"$limit\":101}]"#
+        ));
+
+        // The truncation probe must not overflow for an unlimited page
+        let opts = QueryOptions {
+            limits: Some(ResultLimits {
+                max_documents: usize::MAX,
+                max_result_bytes: 1024,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            opts.pagination_stages(),
+            [serde_json::json!({ "$limit": usize::MAX })]
+        );
+    }
+
+    #[test]
+    fn test_paging_breaks_sort_ties_on_id() {
+        let paged = |sort: Option<&str>| QueryOptions {
+            sort: sort.map(str::to_string),
+            limits: Some(ResultLimits {
+                max_documents: 100,
+                max_result_bytes: 1024,
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            paged(None).find_sort().unwrap().as_deref(),
+            Some(r#"{"_id":1}"#)
+        );
+        // The tie-breaker goes last, keeping the caller's key order
+        assert_eq!(
+            paged(Some(r#"{"status": 1, "createdAt": -1}"#))
+                .find_sort()
+                .unwrap()
+                .as_deref(),
+            Some(r#"{"status":1,"createdAt":-1,"_id":1}"#)
+        );
+        assert_eq!(
+            paged(Some(r#"{"_id": -1}"#))
+                .find_sort()
+                .unwrap()
+                .as_deref(),
+            Some(r#"{"_id":-1}"#)
+        );
+        assert_eq!(
+            paged(Some(r#"{"$natural": -1}"#))
+                .find_sort()
+                .unwrap()
+                .as_deref(),
+            Some(r#"{"$natural":-1}"#)
+        );
+        // Without paging the sort is left alone
+        assert_eq!(QueryOptions::default().find_sort().unwrap(), None);
+
+        // The pipeline's own $sort gets the tie-breaker if nothing reorders after it
+        let pipeline = paged(None)
+            .paged_pipeline(r#"[{"$sort": {"total": -1}}, {"$project": {"total": 1}}]"#)
+            .unwrap();
+        assert_eq!(
+            pipeline,
+            r#"[{"$sort":{"total":-1,"_id":1}},{"$project":{"total":1}},{"$limit":101}]"#
+        );
+
+        // A $group after the $sort decides the order itself, so _id sorts its output
+        let pipeline = paged(None)
+            .paged_pipeline(r#"[{"$sort": {"total": -1}}, {"$group": {"_id": "$country"}}]"#)
+            .unwrap();
+        assert_eq!(
+            pipeline,
+            r#"[{"$sort":{"total":-1}},{"$group":{"_id":"$country"}},{"$sort":{"_id":1}},{"$limit":101}]"#
+        );
+
+        // $sortByCount's order is kept, with ties on count broken by _id
+        let pipeline = paged(None)
+            .paged_pipeline(r#"[{"$sortByCount": "$country"}, {"$project": {"n": "$count"}}]"#)
+            .unwrap();
+        assert_eq!(
+            pipeline,
+            r#"[{"$sortByCount":"$country"},{"$sort":{"count":-1,"_id":1}},{"$project":{"n":"$count"}},{"$limit":101}]"#
+        );
+
+        // Nearest documents first: $geoNear's order is left alone
+        let query = r#"[{"$geoNear":{"near":{"type":"Point","coordinates":[0,0]},"distanceField":"d"}},{"$match":{"open":true}}]"#;
+        let pipeline = paged(None).paged_pipeline(query).unwrap();
+        assert_eq!(
+            pipeline,
+            r#"[{"$geoNear":{"near":{"type":"Point","coordinates":[0,0]},"distanceField":"d"}},{"$match":{"open":true}},{"$limit":101}]"#
+        );
+
+        // A $limit picks the same documents on every page
+        let pipeline = paged(None)
+            .paged_pipeline(r#"[{"$match": {}}, {"$limit": 10}]"#)
+            .unwrap();
+        assert_eq!(
+            pipeline,
+            r#"[{"$match":{}},{"$sort":{"_id":1}},{"$limit":10},{"$limit":101}]"#
+        );
+        let pipeline = paged(None)
+            .paged_pipeline(
+                r#"[{"$sort": {"total": -1}}, {"$limit": 10}, {"$group": {"_id": "$country"}}]"#,
+            )
+            .unwrap();
+        assert_eq!(
+            pipeline,
+            r#"[{"$sort":{"total":-1,"_id":1}},{"$limit":10},{"$group":{"_id":"$country"}},{"$sort":{"_id":1}},{"$limit":101}]"#
+        );
+
+        // Unpaged pipelines run as written
+        let query = r#"[{"$match": {}}]"#;
+        assert_eq!(
+            QueryOptions::default().paged_pipeline(query).unwrap(),
+            query
+        );
+    }
+
+    #[test]
+    fn test_run_command_code_preserves_order_and_types() {
+        let command = mongodb::bson::doc! { "explain": { "find": "users", "limit": 5_i64 }, "verbosity": "queryPlanner" };
+        let code = run_command_code(command).unwrap();
+        assert_eq!(
+            code,
+            r#"EJSON.stringify(db.runCommand(EJSON.parse("{\"explain\":{\"find\":\"users\",\"limit\":{\"$numberLong\":\"5\"}},\"verbosity\":\"queryPlanner\"}")), {relaxed: true})"#
+        );
+    }
+
+    #[test]
+    fn test_list_indexes_code_escapes_collection() {
+        let code = list_indexes_code(r#"odd"name"#).unwrap();
+        assert_eq!(
+            code,
+            r#"EJSON.stringify(db["odd\"name"].getIndexes(), {relaxed: true})"#
+        );
+    }
+
+    #[test]
+    fn test_parse_query_result() {
+        let opts = QueryOptions {
+            limits: Some(ResultLimits {
+                max_documents: 2,
+                max_result_bytes: 1024,
+            }),
+            ..Default::default()
+        };
+
+        let result = parse_query_result("42", &QueryOperation::CountDocuments, &opts).unwrap();
+        assert!(matches!(result, QueryResult::Count(42)));
+
+        let result = parse_query_result("[1, 2, 3]", &QueryOperation::Find, &opts).unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(result.is_truncated());
+
+        // Distinct values are skipped client-side
+        let opts = QueryOptions {
+            skip: Some(2),
+            ..opts
+        };
+        let result =
+            parse_query_result(r#"["a", "b", "c"]"#, &QueryOperation::Distinct, &opts).unwrap();
+        assert_eq!(result.to_json_string().unwrap(), r#"["c"]"#);
+        assert!(!result.is_truncated());
+    }
+
+    #[test]
+    fn test_to_mongosh_code_rejects_write_stages() {
+        let opts = QueryOptions::default();
+        let err = QueryOperation::Aggregate
+            .to_mongosh_code("users", r#"[{"$match": {}}, {"$out": "copy"}]"#, &opts)
+            .unwrap_err();
+        assert!(err.to_string().contains("$out"));
+
+        assert!(
+            QueryOperation::Find
+                .to_mongosh_code("users", r#"{"$where": "sleep(1000)"}"#, &opts)
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_to_mongosh_code_distinct_missing_field() {
         let opts = QueryOptions::default();
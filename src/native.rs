@@ -0,0 +1,275 @@
+//! Native driver query engine.
+//! Shared by every connection type that talks to MongoDB through the `mongodb` crate.
+
+use anyhow::{Context, Result, anyhow};
+use futures::TryStreamExt;
+use mongodb::{
+    Client,
+    action::Action,
+    bson::{Bson, Document, doc},
+    options::ClientOptions,
+};
+use std::time::Duration;
+
+use crate::collections::{self, CollectionInfo};
+use crate::ejson;
+use crate::indexes::{self, IndexInfo};
+use crate::mongodb::{CLIENT_TIMEOUT_GRACE_SECS, QueryOperation, QueryOptions};
+use crate::read_preference::ReadSettings;
+use crate::results::{PageBuilder, QueryResult};
+use crate::validation::validate_read_only;
+
+/// Create a client with the server's default timeouts applied
+pub fn connect(mut client_options: ClientOptions) -> Result<Client> {
+    // Set reasonable defaults
+    client_options.connect_timeout = Some(Duration::from_secs(10));
+    client_options.server_selection_timeout = Some(Duration::from_secs(30));
+
+    Client::with_options(client_options).context("Failed to create MongoDB client")
+}
+
+/// List all collections and views in a database
+pub async fn list_collections(
+    client: &Client,
+    database: &str,
+    timeout_secs: u64,
+) -> Result<Vec<CollectionInfo>> {
+    let specs: Vec<Document> = tokio::time::timeout(Duration::from_secs(timeout_secs), async {
+        client
+            .database(database)
+            .run_cursor_command(doc! { "listCollections": 1 })
+            .await?
+            .try_collect()
+            .await
+    })
+    .await
+    .map_err(|_| {
+        anyhow!(
+            "Listing collections timed out after {} seconds",
+            timeout_secs
+        )
+    })?
+    .context("Failed to list collections")?;
+
+    let specs: Vec<serde_json::Value> = specs
+        .into_iter()
+        .map(|spec| Bson::Document(spec).into_relaxed_extjson())
+        .collect();
+    collections::from_specs(&specs)
+}
+
+/// List the indexes of a collection
+pub async fn list_indexes(
+    client: &Client,
+    database: &str,
+    collection: &str,
+    timeout_secs: u64,
+) -> Result<Vec<IndexInfo>> {
+    let coll = client.database(database).collection::<Document>(collection);
+
+    let specs = tokio::time::timeout(Duration::from_secs(timeout_secs), async {
+        let models: Vec<mongodb::IndexModel> = coll.list_indexes().await?.try_collect().await?;
+        models
+            .iter()
+            .map(|model| {
+                // IndexModel serializes back to the server's specification document
+                let spec = mongodb::bson::to_document(model)?;
+                Ok(Bson::Document(spec).into_relaxed_extjson())
+            })
+            .collect::<Result<Vec<_>>>()
+    })
+    .await
+    .map_err(|_| anyhow!("Listing indexes timed out after {} seconds", timeout_secs))?
+    .context("Failed to list indexes")?;
+
+    indexes::from_specs(&specs)
+}
+
+/// Execute a query with the native driver, stopping once result limits are reached.
+/// The server cancels the query after `timeout_secs` through `maxTimeMS`.
+pub async fn execute_query(
+    client: &Client,
+    database: &str,
+    collection: &str,
+    operation: &QueryOperation,
+    query: &str,
+    options: &QueryOptions,
+    timeout_secs: u64,
+) -> Result<QueryResult> {
+    let coll = client.database(database).collection::<Document>(collection);
+    let options = options.with_max_time(timeout_secs);
+
+    // Execute with timeout; the grace period lets the server's maxTimeMS error arrive first
+    match tokio::time::timeout(
+        Duration::from_secs(timeout_secs + CLIENT_TIMEOUT_GRACE_SECS),
+        execute_operation(&coll, operation, query, &options),
+    )
+    .await
+    {
+        Ok(Ok(result)) => Ok(result),
+        Ok(Err(e)) => Err(e), // Preserve original error
+        Err(_) => Err(anyhow!("Query timed out after {} seconds", timeout_secs)),
+    }
+}
+
+/// Run a database command and return its reply as relaxed Extended JSON.
+/// Callers build the command themselves; it is never taken from tool input.
+/// Commands go to the client's read preference (the connection's `read_preference`
+/// or the URL's); the driver would otherwise always send them to the primary.
+pub async fn run_command(
+    client: &Client,
+    database: &str,
+    command: Document,
+    timeout_secs: u64,
+) -> Result<serde_json::Value> {
+    let db = client.database(database);
+    let mut action = db.run_command(command);
+    if let Some(criteria) = db.selection_criteria() {
+        action = action.selection_criteria(criteria.clone());
+    }
+    let reply = tokio::time::timeout(Duration::from_secs(timeout_secs), action)
+        .await
+        .map_err(|_| anyhow!("Command timed out after {} seconds", timeout_secs))?
+        .context("Command failed")?;
+
+    Ok(Bson::Document(reply).into_relaxed_extjson())
+}
+
+async fn execute_operation(
+    collection: &mongodb::Collection<Document>,
+    operation: &QueryOperation,
+    query_str: &str,
+    options: &QueryOptions,
+) -> Result<QueryResult> {
+    // Reject write stages and server-side JavaScript before touching the database
+    let parsed: serde_json::Value =
+        serde_json::from_str(query_str).context("Query is not valid JSON")?;
+    validate_read_only(&parsed, "query")?;
+    if let Some(projection_str) = &options.projection {
+        let projection: serde_json::Value =
+            serde_json::from_str(projection_str).context("Invalid projection JSON")?;
+        validate_read_only(&projection, "projection")?;
+    }
+
+    let mode = options.ejson_mode;
+    let max_time = options.max_time_ms.map(Duration::from_millis);
+    // Per-call read settings; otherwise the client's apply
+    let selection_criteria = options
+        .read
+        .as_ref()
+        .and_then(ReadSettings::selection_criteria);
+    let read_concern = options.read.as_ref().and_then(ReadSettings::read_concern);
+
+    match operation {
+        QueryOperation::Find => {
+            let filter = ejson::parse_document(query_str, "query")?;
+
+            // Build find options
+            let mut find_options = mongodb::options::FindOptions::default();
+
+            find_options.skip = options.skip.filter(|s| *s > 0);
+            find_options.limit = options.effective_find_limit();
+            find_options.max_time = max_time;
+            find_options.selection_criteria = selection_criteria;
+            find_options.read_concern = read_concern;
+
+            if let Some(sort_str) = options.find_sort()? {
+                find_options.sort = Some(ejson::parse_document(&sort_str, "sort")?);
+            }
+
+            if let Some(projection_str) = &options.projection {
+                find_options.projection =
+                    Some(ejson::parse_document(projection_str, "projection")?);
+            }
+
+            let cursor = collection
+                .find(filter)
+                .with_options(find_options)
+                .await
+                .context("Find query failed")?;
+            collect_page(cursor, options)
+                .await
+                .context("Failed to collect find results")
+        }
+        QueryOperation::Aggregate => {
+            let pipeline = ejson::parse_pipeline(&options.paged_pipeline(query_str)?)?;
+            let cursor = collection
+                .aggregate(pipeline)
+                .optional(max_time, |a, t| a.max_time(t))
+                .optional(selection_criteria, |a, s| a.selection_criteria(s))
+                .optional(read_concern, |a, r| a.read_concern(r))
+                .await
+                .context("Aggregate query failed")?;
+            collect_page(cursor, options)
+                .await
+                .context("Failed to collect aggregate results")
+        }
+        QueryOperation::CountDocuments => {
+            let filter = ejson::parse_document(query_str, "query")?;
+            let count = collection
+                .count_documents(filter)
+                .optional(max_time, |a, t| a.max_time(t))
+                .optional(selection_criteria, |a, s| a.selection_criteria(s))
+                .optional(read_concern, |a, r| a.read_concern(r))
+                .await
+                .context("CountDocuments query failed")?;
+            Ok(QueryResult::Count(count))
+        }
+        QueryOperation::Distinct => {
+            let (field, filter) = distinct_parts(query_str, options)?;
+
+            let values = collection
+                .distinct(&field, filter)
+                .optional(max_time, |a, t| a.max_time(t))
+                .optional(selection_criteria, |a, s| a.selection_criteria(s))
+                .optional(read_concern, |a, r| a.read_concern(r))
+                .await
+                .context("Distinct query failed")?;
+
+            let skip = usize::try_from(options.skip.unwrap_or(0)).unwrap_or(usize::MAX);
+            Ok(PageBuilder::collect(
+                values.into_iter().skip(skip).map(|v| mode.to_json(v)),
+                options.limits,
+            ))
+        }
+    }
+}
+
+/// Distinct field and filter, from the distinct_field option or the legacy
+/// `{"field": ..., "query": {...}}` format
+pub fn distinct_parts(query: &str, options: &QueryOptions) -> Result<(String, Document)> {
+    if let Some(field) = &options.distinct_field {
+        return Ok((
+            field.clone(),
+            ejson::parse_document(query, "distinct filter")?,
+        ));
+    }
+
+    let params: serde_json::Value =
+        serde_json::from_str(query).context("Invalid distinct query JSON")?;
+    let field = params
+        .get("field")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Distinct requires 'distinct_field' parameter"))?
+        .to_string();
+    let filter = match params.get("query") {
+        Some(filter) => ejson::parse_document(&filter.to_string(), "distinct filter")?,
+        None => Document::new(),
+    };
+    Ok((field, filter))
+}
+
+/// Read documents from a cursor until the page is full.
+/// Dropping the cursor early closes it on the server.
+async fn collect_page(
+    mut cursor: mongodb::Cursor<Document>,
+    options: &QueryOptions,
+) -> Result<QueryResult> {
+    let mut page = PageBuilder::new(options.limits);
+    while let Some(doc) = cursor.try_next().await? {
+        if !page.push(options.ejson_mode.to_json(Bson::Document(doc))) {
+            break;
+        }
+    }
+    Ok(page.finish())
+}
@@ -0,0 +1,280 @@
+//! Read preference and read concern settings.
+//!
+//! Applied to driver clients (direct and port-forward connections) and passed to
+//! mongosh as connection string options. `query_mongodb` can override the mode per call.
+
+use anyhow::{Result, bail};
+use mongodb::options::{
+    ClientOptions, ReadConcern, ReadConcernLevel, ReadPreference, ReadPreferenceOptions,
+    SelectionCriteria, TagSet,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Smallest `maxStalenessSeconds` MongoDB accepts
+const MIN_MAX_STALENESS_SECS: u64 = 90;
+
+/// Which replica set members may serve reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ReadPreferenceMode {
+    /// Only the primary
+    Primary,
+    /// The primary, or a secondary when no primary is available
+    PrimaryPreferred,
+    /// Only secondaries
+    Secondary,
+    /// A secondary, or the primary when no secondary is available
+    SecondaryPreferred,
+    /// The member with the lowest latency
+    Nearest,
+}
+
+impl ReadPreferenceMode {
+    /// Name used in connection strings
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Primary => "primary",
+            Self::PrimaryPreferred => "primaryPreferred",
+            Self::Secondary => "secondary",
+            Self::SecondaryPreferred => "secondaryPreferred",
+            Self::Nearest => "nearest",
+        }
+    }
+}
+
+/// Consistency of the data a read returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadConcernSetting {
+    Local,
+    Available,
+    Majority,
+    Linearizable,
+    Snapshot,
+}
+
+impl ReadConcernSetting {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Local => "local",
+            Self::Available => "available",
+            Self::Majority => "majority",
+            Self::Linearizable => "linearizable",
+            Self::Snapshot => "snapshot",
+        }
+    }
+
+    fn to_read_concern(self) -> ReadConcern {
+        let level = match self {
+            Self::Local => ReadConcernLevel::Local,
+            Self::Available => ReadConcernLevel::Available,
+            Self::Majority => ReadConcernLevel::Majority,
+            Self::Linearizable => ReadConcernLevel::Linearizable,
+            Self::Snapshot => ReadConcernLevel::Snapshot,
+        };
+        level.into()
+    }
+}
+
+/// Read routing and consistency of a connection (part of its query settings)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadSettings {
+    /// Members that may serve reads (default: the driver's, i.e. primary)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_preference: Option<ReadPreferenceMode>,
+    /// Tag sets tried in order to pick a member, e.g. `[{dc: east}, {}]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub read_preference_tags: Vec<BTreeMap<String, String>>,
+    /// Skip secondaries lagging further behind the primary (at least 90 seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_staleness_secs: Option<u64>,
+    /// Read concern level
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_concern: Option<ReadConcernSetting>,
+}
+
+impl ReadSettings {
+    /// Reject combinations MongoDB refuses
+    pub fn validate(&self) -> Result<()> {
+        let primary = matches!(
+            self.read_preference,
+            None | Some(ReadPreferenceMode::Primary)
+        );
+        if primary && !self.read_preference_tags.is_empty() {
+            bail!("read_preference_tags require a read_preference other than primary");
+        }
+        if let Some(secs) = self.max_staleness_secs {
+            if primary {
+                bail!("max_staleness_secs requires a read_preference other than primary");
+            }
+            if secs < MIN_MAX_STALENESS_SECS {
+                bail!("max_staleness_secs must be at least {MIN_MAX_STALENESS_SECS}, got {secs}");
+            }
+        }
+        Ok(())
+    }
+
+    /// These settings with the mode replaced for one call. Tags and staleness are
+    /// kept unless the new mode is primary, which doesn't accept them.
+    pub fn with_mode(&self, mode: ReadPreferenceMode) -> Self {
+        let mut settings = self.clone();
+        settings.read_preference = Some(mode);
+        if mode == ReadPreferenceMode::Primary {
+            settings.read_preference_tags.clear();
+            settings.max_staleness_secs = None;
+        }
+        settings
+    }
+
+    /// Driver read preference, if one is configured
+    pub fn selection_criteria(&self) -> Option<SelectionCriteria> {
+        let mode = self.read_preference?;
+
+        let tag_sets: Vec<TagSet> = self
+            .read_preference_tags
+            .iter()
+            .map(|tags| tags.clone().into_iter().collect())
+            .collect();
+        let options = (!tag_sets.is_empty() || self.max_staleness_secs.is_some()).then(|| {
+            ReadPreferenceOptions::builder()
+                .tag_sets((!tag_sets.is_empty()).then_some(tag_sets))
+                .max_staleness(self.max_staleness_secs.map(Duration::from_secs))
+                .build()
+        });
+
+        let read_preference = match mode {
+            ReadPreferenceMode::Primary => ReadPreference::Primary,
+            ReadPreferenceMode::PrimaryPreferred => ReadPreference::PrimaryPreferred { options },
+            ReadPreferenceMode::Secondary => ReadPreference::Secondary { options },
+            ReadPreferenceMode::SecondaryPreferred => {
+                ReadPreference::SecondaryPreferred { options }
+            }
+            ReadPreferenceMode::Nearest => ReadPreference::Nearest { options },
+        };
+        Some(SelectionCriteria::ReadPreference(read_preference))
+    }
+
+    /// Driver read concern, if one is configured
+    pub fn read_concern(&self) -> Option<ReadConcern> {
+        self.read_concern.map(ReadConcernSetting::to_read_concern)
+    }
+
+    /// Set the configured read preference and read concern on client options.
+    /// Anything not configured keeps the value from the connection URL.
+    pub fn apply(&self, client_options: &mut ClientOptions) {
+        if let Some(criteria) = self.selection_criteria() {
+            client_options.selection_criteria = Some(criteria);
+        }
+        if let Some(read_concern) = self.read_concern() {
+            client_options.read_concern = Some(read_concern);
+        }
+    }
+
+    /// Connection string options for mongosh, e.g. `readPreference=secondaryPreferred`
+    pub fn connection_string_options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if let Some(mode) = self.read_preference {
+            options.push(format!("readPreference={}", mode.as_str()));
+        }
+        for tags in &self.read_preference_tags {
+            let tags: Vec<String> = tags
+                .iter()
+                .map(|(k, v)| format!("{}:{}", percent_encode(k), percent_encode(v)))
+                .collect();
+            options.push(format!("readPreferenceTags={}", tags.join(",")));
+        }
+        if let Some(secs) = self.max_staleness_secs {
+            options.push(format!("maxStalenessSeconds={secs}"));
+        }
+        if let Some(level) = self.read_concern {
+            options.push(format!("readConcernLevel={}", level.as_str()));
+        }
+        options
+    }
+}
+
+/// Percent-encode a connection string value, so `:`, `,`, `&` and the like in
+/// tag names and values can't change the meaning of the URL
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(yaml: &str) -> ReadSettings {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_read_settings_validate() {
+        assert!(ReadSettings::default().validate().is_ok());
+        assert!(
+            settings("read_preference: secondaryPreferred\nmax_staleness_secs: 120")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            settings("read_preference: secondary\nmax_staleness_secs: 30")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            settings("read_preference_tags: [{dc: east}]")
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_read_settings_options() {
+        let read = settings(
+            "read_preference: nearest\nread_preference_tags: [{dc: east, rack: r1}, {}]\nmax_staleness_secs: 90\nread_concern: majority",
+        );
+        assert_eq!(
+            read.connection_string_options(),
+            [
+                "readPreference=nearest",
+                "readPreferenceTags=dc:east,rack:r1",
+                "readPreferenceTags=",
+                "maxStalenessSeconds=90",
+                "readConcernLevel=majority",
+            ]
+        );
+
+        let Some(SelectionCriteria::ReadPreference(ReadPreference::Nearest { options })) =
+            read.selection_criteria()
+        else {
+            panic!("expected nearest read preference");
+        };
+        let options = options.unwrap();
+        assert_eq!(options.tag_sets.unwrap().len(), 2);
+        assert_eq!(options.max_staleness, Some(Duration::from_secs(90)));
+
+        // A per-call primary override drops options primary doesn't accept
+        let primary = read.with_mode(ReadPreferenceMode::Primary);
+        assert!(primary.validate().is_ok());
+        assert_eq!(primary.read_concern, Some(ReadConcernSetting::Majority));
+
+        // Tag names and values are encoded so they can't add options
+        let read = settings(
+            "read_preference: secondary\nread_preference_tags: [{zone: 'eu west&readPreference=primary'}]",
+        );
+        assert_eq!(
+            read.connection_string_options()[1],
+            "readPreferenceTags=zone:eu%20west%26readPreference%3Dprimary"
+        );
+    }
+}
@@ -0,0 +1,656 @@
+//! Field-level redaction of query results.
+//!
+//! Rules are matched against dotted field paths of every returned document
+//! (array indices are skipped, so `items.card` covers each array element).
+//! A `path` rule matches the end of a path: `ssn` hides the field at any depth,
+//! `customer.ssn` only below `customer`. Each segment may use `*` and `?` globs.
+//! A `pattern` rule is a regex tested against the dotted path and each suffix of it
+//! starting at a segment, so stages that nest whole documents under a new name
+//! (`$facet`, `$lookup`'s `as`) can't move a field out of an anchored pattern.
+//!
+//! Queries may not reference redacted fields in expressions (`"$ssn"`), so an
+//! aggregation cannot copy a value to an unredacted name. That includes any
+//! field below which a rule could match, since copying it renames the paths inside.
+//!
+//! Hashed values are an HMAC-SHA256 keyed with the connection's
+//! `redaction_hash_key_env`, so they can't be reversed by hashing guesses.
+
+use anyhow::{Context, Result, bail};
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use regex_automata::{Anchored, hybrid::dfa::DFA, util::start};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::access::glob_match;
+use crate::results::QueryResult;
+
+/// Replacement for masked values
+const MASK: &str = "[REDACTED]";
+
+/// What happens to a matching field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionAction {
+    /// Replace the value with a keyed SHA-256 digest, keeping values comparable
+    Hash,
+    /// Replace the value with "[REDACTED]"
+    #[default]
+    Mask,
+    /// Remove the field from the result
+    Drop,
+}
+
+/// A redaction rule as written in the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionRule {
+    /// Field path suffix with optional globs, e.g. `password`, `*.ssn`, `card.*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Regex tested against the full dotted path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub action: RedactionAction,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Path(Vec<String>),
+    /// The regex, and a lazy DFA of it to test where matches could start
+    Regex(Regex, Box<DFA>),
+}
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    matcher: Matcher,
+    action: RedactionAction,
+}
+
+/// Compiled redaction policy of a connection.
+/// Deserialized from a list of rules; invalid rules fail config loading.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(try_from = "Vec<RedactionRule>", into = "Vec<RedactionRule>")]
+pub struct Redaction {
+    rules: Vec<RedactionRule>,
+    compiled: Vec<CompiledRule>,
+}
+
+impl TryFrom<Vec<RedactionRule>> for Redaction {
+    type Error = anyhow::Error;
+
+    fn try_from(rules: Vec<RedactionRule>) -> Result<Self> {
+        let compiled = rules
+            .iter()
+            .map(|rule| {
+                let matcher = match (&rule.path, &rule.pattern) {
+                    (Some(path), None) if !path.is_empty() => {
+                        Matcher::Path(path.split('.').map(str::to_string).collect())
+                    }
+                    (None, Some(pattern)) => {
+                        let invalid = || format!("Invalid redaction pattern '{pattern}'");
+                        Matcher::Regex(
+                            Regex::new(pattern).with_context(invalid)?,
+                            Box::new(DFA::new(pattern).with_context(invalid)?),
+                        )
+                    }
+                    _ => bail!("Each redaction rule needs exactly one of 'path' or 'pattern'"),
+                };
+                Ok(CompiledRule {
+                    matcher,
+                    action: rule.action,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { rules, compiled })
+    }
+}
+
+impl From<Redaction> for Vec<RedactionRule> {
+    fn from(redaction: Redaction) -> Self {
+        redaction.rules
+    }
+}
+
+impl CompiledRule {
+    fn matches(&self, path: &[&str]) -> bool {
+        match &self.matcher {
+            Matcher::Path(segments) => {
+                segments.len() <= path.len()
+                    && segments
+                        .iter()
+                        .zip(&path[path.len() - segments.len()..])
+                        .all(|(pattern, name)| glob_match(pattern, name))
+            }
+            // Like path rules, a pattern keeps matching when documents are nested
+            Matcher::Regex(regex, _) => {
+                (0..path.len()).any(|start| regex.is_match(&path[start..].join(".")))
+            }
+        }
+    }
+
+    /// Whether copying the value at `path` under another name could move a
+    /// redacted field out of this rule's reach
+    fn exposed_by_reference(&self, path: &[&str]) -> bool {
+        // The reference reads a redacted field or something inside it
+        if (1..=path.len()).any(|end| self.matches(&path[..end])) {
+            return true;
+        }
+        // The reference ends part-way into a multi-segment rule (`$customer`
+        // for `customer.ssn`, or `^customer\.ssn$`); a rename would break the match
+        match &self.matcher {
+            Matcher::Path(segments) => (1..segments.len().min(path.len() + 1)).any(|len| {
+                segments[..len]
+                    .iter()
+                    .zip(&path[path.len() - len..])
+                    .all(|(pattern, name)| glob_match(pattern, name))
+            }),
+            Matcher::Regex(_, dfa) => match_could_start_in(dfa, &format!("{}.", path.join("."))),
+        }
+    }
+}
+
+/// Whether a match of `dfa` could start inside `prefix` and continue past its end.
+/// Matches starting after `prefix` see the same text whatever it is renamed to.
+/// A match may start at a segment either mid-path or at the start of a suffix.
+/// Anything the DFA can't decide counts as a possible match.
+fn match_could_start_in(dfa: &DFA, prefix: &str) -> bool {
+    let haystack = prefix.as_bytes();
+    let mut cache = dfa.create_cache();
+    let mut could_match = |at: usize, look_behind: Option<u8>| {
+        let config = start::Config::new()
+            .anchored(Anchored::Yes)
+            .look_behind(look_behind);
+        let Ok(mut state) = dfa.start_state(&mut cache, &config) else {
+            return true;
+        };
+        for &byte in &haystack[at..] {
+            match dfa.next_state(&mut cache, state, byte) {
+                Ok(next) if next.is_dead() => return false,
+                Ok(next) if next.is_quit() || next.is_match() => return true,
+                Ok(next) => state = next,
+                Err(_) => return true,
+            }
+        }
+        true
+    };
+    (0..haystack.len()).any(|at| {
+        let look_behind = at.checked_sub(1).map(|i| haystack[i]);
+        could_match(at, look_behind) || (look_behind == Some(b'.') && could_match(at, None))
+    })
+}
+
+/// First 8 bytes of an HMAC-SHA256 of the value; masks the value without a key
+fn hash_value(value: &Value, key: Option<&[u8]>) -> Value {
+    let Some(key) = key else {
+        return Value::String(MASK.to_string());
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(value.to_string().as_bytes());
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+    Value::String(format!("hmac:{hex}"))
+}
+
+impl Redaction {
+    pub fn is_empty(&self) -> bool {
+        self.compiled.is_empty()
+    }
+
+    /// Whether any rule hashes values, which needs a key
+    pub fn hashes(&self) -> bool {
+        self.compiled
+            .iter()
+            .any(|rule| rule.action == RedactionAction::Hash)
+    }
+
+    /// Strongest action of all rules matching `path`
+    fn action_for(&self, path: &[&str]) -> Option<RedactionAction> {
+        self.compiled
+            .iter()
+            .filter(|rule| rule.matches(path))
+            .map(|rule| rule.action)
+            .max()
+    }
+
+    /// Redact a value found at `path` (empty for a whole document), hashing with `key`.
+    /// Returns None when the value is dropped.
+    pub fn redact_at(&self, path: &[&str], value: Value, key: Option<&[u8]>) -> Option<Value> {
+        if path.is_empty() {
+            return Some(self.redact_inside(path, value, key));
+        }
+        match self.action_for(path) {
+            Some(RedactionAction::Drop) => None,
+            Some(RedactionAction::Mask) => Some(Value::String(MASK.to_string())),
+            Some(RedactionAction::Hash) => Some(hash_value(&value, key)),
+            None => Some(self.redact_inside(path, value, key)),
+        }
+    }
+
+    /// Redact the children of a value whose own path is not redacted
+    fn redact_inside(&self, path: &[&str], value: Value, key: Option<&[u8]>) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .filter_map(|(name, child)| {
+                        let mut child_path = path.to_vec();
+                        child_path.push(&name);
+                        let child = self.redact_at(&child_path, child, key)?;
+                        Some((name, child))
+                    })
+                    .collect(),
+            ),
+            // Array elements share the array's path
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| self.redact_inside(path, item, key))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+
+    /// Redact query result items. Distinct values are redacted as the value of `distinct_field`.
+    pub fn redact_result(
+        &self,
+        result: QueryResult,
+        distinct_field: Option<&str>,
+        key: Option<&[u8]>,
+    ) -> QueryResult {
+        let QueryResult::Documents { items, truncated } = result else {
+            return result;
+        };
+        if self.is_empty() {
+            return QueryResult::Documents { items, truncated };
+        }
+
+        let path: Vec<&str> = distinct_field
+            .map(|field| field.split('.').collect())
+            .unwrap_or_default();
+        QueryResult::Documents {
+            items: items
+                .into_iter()
+                .filter_map(|item| self.redact_at(&path, item, key))
+                .collect(),
+            truncated,
+        }
+    }
+
+    /// Reject queries that reference redacted fields in expressions.
+    /// Plain filter keys are allowed; `"$field"` values would copy data to another name.
+    /// With any rule configured, expressions that reach fields without a path the
+    /// rules can match are rejected too: operators taking field names as strings or
+    /// turning fields into values, whole-document variables and user variables.
+    pub fn check_query(&self, query: &Value, what: &str) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        match query {
+            Value::String(s) => match expression_reference(s) {
+                Reference::None => Ok(()),
+                Reference::Field(reference) => {
+                    let path: Vec<&str> = reference.split('.').collect();
+                    if self.compiled.iter().any(|r| r.exposed_by_reference(&path)) {
+                        bail!(
+                            "The {what} references redacted field '{reference}'. \
+                             Redacted fields cannot be used in expressions on this connection."
+                        );
+                    }
+                    Ok(())
+                }
+                Reference::Variable(variable) => bail!(
+                    "The {what} uses variable '{variable}'. Variables can expose redacted \
+                     fields and cannot be used on this connection."
+                ),
+            },
+            Value::Array(items) => items.iter().try_for_each(|v| self.check_query(v, what)),
+            Value::Object(map) => map.iter().try_for_each(|(key, v)| {
+                if FIELD_NAME_OPERATORS.contains(&key.as_str()) {
+                    bail!(
+                        "The {what} uses {key}, which can expose redacted fields. \
+                         It cannot be used on this connection."
+                    );
+                }
+                self.check_query(v, what)
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check a distinct field before running the query.
+    /// Dropped or masked fields would only return redacted values.
+    pub fn check_distinct_field(&self, field: &str) -> Result<()> {
+        let path: Vec<&str> = field.split('.').collect();
+        let blocked = (1..=path.len()).any(|end| {
+            matches!(
+                self.action_for(&path[..end]),
+                Some(RedactionAction::Drop | RedactionAction::Mask)
+            )
+        });
+        if blocked {
+            bail!(
+                "Field '{field}' is redacted on this connection and cannot be used with distinct"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Operators that take field names as plain strings or turn field names into
+/// values, so the path of what they return no longer matches redaction rules
+const FIELD_NAME_OPERATORS: &[&str] = &["$getField", "$setField", "$unsetField", "$objectToArray"];
+
+/// System variables that never hold document data
+const DATA_FREE_VARIABLES: &[&str] = &["NOW", "CLUSTER_TIME", "REMOVE", "DESCEND", "PRUNE", "KEEP"];
+
+/// What an aggregation expression string refers to
+#[derive(Debug, PartialEq, Eq)]
+enum Reference<'a> {
+    /// A literal, or a data-free system variable
+    None,
+    /// A field path: `$a.b`, or `$$ROOT.a.b` / `$$CURRENT.a.b`
+    Field(&'a str),
+    /// `$$ROOT`, `$$CURRENT` or a user variable, whose fields rules can't follow
+    Variable(&'a str),
+}
+
+fn expression_reference(s: &str) -> Reference<'_> {
+    if let Some(var) = s.strip_prefix("$$") {
+        return match var.split_once('.') {
+            Some(("ROOT" | "CURRENT", rest)) => Reference::Field(rest),
+            _ if DATA_FREE_VARIABLES.contains(&var) => Reference::None,
+            _ => Reference::Variable(s),
+        };
+    }
+    match s.strip_prefix('$') {
+        Some(reference) if !reference.is_empty() => Reference::Field(reference),
+        _ => Reference::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn redaction(yaml: &str) -> Redaction {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    const KEY: &[u8] = b"test-key";
+
+    fn redact(r: &Redaction, doc: Value) -> Value {
+        r.redact_at(&[], doc, Some(KEY)).unwrap()
+    }
+
+    #[test]
+    fn test_redact_distinct_values() {
+        let r = redaction(
+            "- path: email
+  action: hash
+- path: ssn",
+        );
+        let hashed = r.redact_at(&["email"], json!("a@b.c"), Some(KEY)).unwrap();
+        assert!(hashed.as_str().unwrap().starts_with("hmac:"));
+
+        // Subdocument values are redacted below the distinct field
+        let value = r
+            .redact_at(&["customer"], json!({"ssn": "1", "name": "Bo"}), Some(KEY))
+            .unwrap();
+        assert_eq!(value, json!({"ssn": MASK, "name": "Bo"}));
+    }
+
+    #[test]
+    fn test_redact_actions() {
+        let r = redaction(
+            r"
+- path: password
+  action: drop
+- path: ssn
+- path: email
+  action: hash
+",
+        );
+        let doc = json!({"name": "Ann", "password": "x", "ssn": "123", "email": "a@b.c"});
+        let out = redact(&r, doc);
+
+        assert!(out.get("password").is_none());
+        assert_eq!(out["ssn"], MASK);
+        assert!(out["email"].as_str().unwrap().starts_with("hmac:"));
+        assert_eq!(out["name"], "Ann");
+
+        // Hashing is stable so values can still be compared
+        let again = redact(&r, json!({"email": "a@b.c"}));
+        assert_eq!(again["email"], out["email"]);
+    }
+
+    #[test]
+    fn test_hash_depends_on_key() {
+        let r = redaction("- path: email\n  action: hash");
+        let doc = json!({"email": "a@b.c"});
+        let keyed = r.redact_at(&[], doc.clone(), Some(KEY)).unwrap();
+        let other = r.redact_at(&[], doc.clone(), Some(b"other-key")).unwrap();
+        assert_ne!(keyed["email"], other["email"]);
+
+        // Without a key nothing guessable is returned
+        let unkeyed = r.redact_at(&[], doc, None).unwrap();
+        assert_eq!(unkeyed["email"], MASK);
+    }
+
+    #[test]
+    fn test_redact_nested_and_arrays() {
+        let r = redaction(
+            r"
+- path: ssn
+  action: drop
+- path: card.*
+",
+        );
+        let doc = json!({
+            "ssn": "1",
+            "customer": {"ssn": "2", "name": "Bo"},
+            "payments": [{"card": {"number": "4111", "exp": "12/30"}, "amount": 5}]
+        });
+        let out = redact(&r, doc);
+
+        assert!(out.get("ssn").is_none());
+        assert_eq!(out["customer"], json!({"name": "Bo"}));
+        assert_eq!(
+            out["payments"][0],
+            json!({"card": {"number": MASK, "exp": MASK}, "amount": 5})
+        );
+    }
+
+    #[test]
+    fn test_redact_scoped_path_and_regex() {
+        let r = redaction(
+            r#"
+- path: "*.ssn"
+- pattern: "(?i)token$"
+  action: drop
+"#,
+        );
+        let out = redact(
+            &r,
+            json!({"ssn": "top", "customer": {"ssn": "nested"}, "auth": {"refreshToken": "t"}}),
+        );
+        assert_eq!(out["ssn"], "top");
+        assert_eq!(out["customer"]["ssn"], MASK);
+        assert_eq!(out["auth"], json!({}));
+    }
+
+    #[test]
+    fn test_check_query_references() {
+        let r = redaction(
+            r"
+- path: customer.ssn
+- path: email
+  action: hash
+",
+        );
+
+        // Filtering on a redacted field is allowed; copying it is not
+        assert!(r.check_query(&json!({"email": "a@b.c"}), "query").is_ok());
+        assert!(
+            r.check_query(&json!([{"$project": {"e": "$email"}}]), "query")
+                .is_err()
+        );
+        assert!(
+            r.check_query(&json!([{"$project": {"c": "$customer"}}]), "query")
+                .is_err()
+        );
+        assert!(
+            r.check_query(
+                &json!([{"$project": {"s": "$$ROOT.customer.ssn"}}]),
+                "query"
+            )
+            .is_err()
+        );
+        assert!(
+            r.check_query(&json!([{"$group": {"_id": "$country"}}]), "query")
+                .is_ok()
+        );
+        assert!(
+            r.check_query(&json!([{"$project": {"doc": "$$ROOT"}}]), "query")
+                .is_err()
+        );
+        assert!(
+            r.check_query(&json!([{"$addFields": {"at": "$$NOW"}}]), "query")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_check_query_regex_references() {
+        let r = redaction(r"- pattern: '^customer\.ssn$'");
+
+        // Copying the parent would return the SSN as `x.ssn`
+        assert!(
+            r.check_query(&json!([{"$project": {"x": "$customer"}}]), "query")
+                .is_err()
+        );
+        assert!(
+            r.check_query(&json!({"x": "$customer"}), "projection")
+                .is_err()
+        );
+        assert!(
+            r.check_query(&json!([{"$project": {"x": "$customer.ssn"}}]), "query")
+                .is_err()
+        );
+        // Fields the pattern can't reach stay usable
+        assert!(
+            r.check_query(&json!([{"$project": {"x": "$country"}}]), "query")
+                .is_ok()
+        );
+        assert!(
+            r.check_query(&json!([{"$project": {"x": "$customer.name"}}]), "query")
+                .is_ok()
+        );
+
+        // An unanchored suffix still matches after a rename
+        let r = redaction("- pattern: '(?i)token$'");
+        assert!(
+            r.check_query(&json!([{"$project": {"a": "$auth"}}]), "query")
+                .is_ok()
+        );
+        let r = redaction(r"- pattern: 'tomer\.ssn'");
+        assert!(
+            r.check_query(&json!([{"$project": {"x": "$customer"}}]), "query")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_regex_follows_nested_documents() {
+        let r = redaction(r"- pattern: '^customer\.ssn$'");
+        let doc = || json!({"customer": {"ssn": "123-45-6789", "name": "Ann"}});
+
+        // $facet: [{"$facet": {"a": [{"$match": {}}]}}]
+        let out = redact(&r, json!({"a": [doc()]}));
+        assert_eq!(out["a"][0]["customer"]["ssn"], MASK);
+        assert_eq!(out["a"][0]["customer"]["name"], "Ann");
+
+        // $lookup and $graphLookup self-joins nest the joined documents under `as`
+        let out = redact(&r, json!({"_id": 1, "joined": [doc()], "graph": [doc()]}));
+        assert_eq!(out["joined"][0]["customer"]["ssn"], MASK);
+        assert_eq!(out["graph"][0]["customer"]["ssn"], MASK);
+
+        // $replaceRoot / $replaceWith / $group $push wrapping a sub-document
+        let out = redact(&r, json!({"wrapped": {"inner": doc()}}));
+        assert_eq!(out["wrapped"]["inner"]["customer"]["ssn"], MASK);
+        for query in [
+            json!([{"$replaceWith": {"wrapped": "$$ROOT"}}]),
+            json!([{"$replaceRoot": {"newRoot": {"c": "$customer"}}}]),
+            json!([{"$group": {"_id": null, "all": {"$push": "$customer"}}}]),
+        ] {
+            assert!(r.check_query(&query, "query").is_err(), "{query}");
+        }
+
+        // Only suffixes starting at a segment match; `xcustomer.ssn` is another field
+        let out = redact(&r, json!({"xcustomer": {"ssn": "1"}}));
+        assert_eq!(out["xcustomer"]["ssn"], "1");
+    }
+
+    #[test]
+    fn test_check_query_rejects_get_field() {
+        let r = redaction("- path: ssn");
+        let query = json!([{"$project": {"x": {"$getField": "ssn"}}}]);
+        assert!(r.check_query(&query, "query").is_err());
+    }
+
+    #[test]
+    fn test_check_query_rejects_object_to_array() {
+        let r = redaction("- path: ssn");
+        let query = json!([{"$project": {"kv": {"$objectToArray": "$$ROOT"}}}]);
+        assert!(r.check_query(&query, "query").is_err());
+        // Also without a whole-document variable
+        let query = json!([{"$project": {"kv": {"$objectToArray": "$customer"}}}]);
+        assert!(r.check_query(&query, "query").is_err());
+    }
+
+    #[test]
+    fn test_check_query_rejects_user_variables() {
+        let r = redaction("- path: ssn");
+        let query =
+            json!([{"$project": {"x": {"$let": {"vars": {"r": "$$ROOT"}, "in": "$$r.ssn"}}}}]);
+        assert!(r.check_query(&query, "query").is_err());
+
+        let lookup = json!([{"$lookup": {
+            "from": "people",
+            "let": {"p": "$person"},
+            "pipeline": [{"$project": {"s": "$$p.ssn"}}],
+            "as": "people"
+        }}]);
+        assert!(r.check_query(&lookup, "query").is_err());
+
+        // No rules, no restrictions
+        assert!(Redaction::default().check_query(&query, "query").is_ok());
+    }
+
+    #[test]
+    fn test_check_distinct_field() {
+        let r = redaction(
+            r"
+- path: ssn
+- path: email
+  action: hash
+- path: secrets
+  action: drop
+",
+        );
+        assert!(r.check_distinct_field("ssn").is_err());
+        assert!(r.check_distinct_field("secrets.key").is_err());
+        assert!(r.check_distinct_field("email").is_ok());
+        assert!(r.check_distinct_field("country").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_rules_fail_to_parse() {
+        assert!(serde_yaml::from_str::<Redaction>("- action: drop").is_err());
+        assert!(serde_yaml::from_str::<Redaction>("- pattern: '('").is_err());
+        assert!(serde_yaml::from_str::<Redaction>("- path: a\n  pattern: b").is_err());
+    }
+}
@@ -0,0 +1,498 @@
+//! Connection registry construction and config hot reload.
+//!
+//! The config file is re-read when its modification time changes or on SIGHUP.
+//! Connections whose settings are unchanged are carried over, so direct clients,
+//! K8s pod caches, port-forwards and SSH tunnels survive a reload. Cached schemas
+//! and page tokens of changed or removed connections are dropped, since they were
+//! produced under the old redaction, collection and limit settings.
+
+use anyhow::Result;
+use rmcp::{Peer, RoleServer};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::config::Config;
+use crate::connection::{ConnectionRegistry, MongoConnection, SharedRegistry};
+use crate::direct_connection::DirectConnection;
+use crate::k8s_client::K8sClient;
+use crate::k8s_connection::K8sConnection;
+use crate::results::PageStore;
+use crate::schema::SchemaCache;
+use crate::ssh_connection::SshConnection;
+
+/// How often the config file's modification time is checked
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What a rebuild changed, by connection name
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RegistryChanges {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl RegistryChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// A built connection and the config it was built from
+struct BuiltConnection {
+    fingerprint: String,
+    connection: Arc<dyn MongoConnection>,
+}
+
+/// Builds registries from config, reusing connections whose config is unchanged
+#[derive(Default)]
+pub struct RegistryBuilder {
+    /// Kubernetes client and the kubeconfig_path it was created from
+    k8s_client: Option<(Option<String>, Arc<K8sClient>)>,
+    built: HashMap<String, BuiltConnection>,
+}
+
+impl RegistryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kubernetes client for the configured kubeconfig, created on first use
+    async fn k8s_client(&mut self, config: &Config) -> Result<Arc<K8sClient>> {
+        if let Some((path, client)) = &self.k8s_client
+            && *path == config.kubeconfig_path
+        {
+            return Ok(client.clone());
+        }
+
+        let client = Arc::new(K8sClient::new(config.kubeconfig_path.clone()).await?);
+        tracing::info!("Kubernetes client initialized");
+        self.k8s_client = Some((config.kubeconfig_path.clone(), client.clone()));
+        Ok(client)
+    }
+
+    /// Build a registry for `config`. Nothing is replaced if this fails.
+    pub async fn build(
+        &mut self,
+        config: &Config,
+    ) -> Result<(ConnectionRegistry, RegistryChanges)> {
+        config.validate_connections()?;
+
+        let mut built = HashMap::new();
+
+        if !config.namespaces.is_empty() {
+            let k8s_client = self.k8s_client(config).await?;
+            for ns in &config.namespaces {
+                // A different kubeconfig means a different cluster, so it is part of the fingerprint
+                let fingerprint = fingerprint(&(&config.kubeconfig_path, ns));
+                let connection =
+                    self.reuse(&ns.namespace_name, &fingerprint)
+                        .unwrap_or_else(|| {
+                            tracing::info!("Registering K8s connection: {}", ns.namespace_name);
+                            Arc::new(K8sConnection::new(ns.clone(), k8s_client.clone()))
+                        });
+                built.insert(
+                    ns.namespace_name.clone(),
+                    BuiltConnection {
+                        fingerprint,
+                        connection,
+                    },
+                );
+            }
+        }
+
+        // Direct connections connect lazily, so building them is cheap
+        for conn in &config.connections {
+            // Secrets are read through the cluster of the configured kubeconfig
+            let (fingerprint, k8s_client) = if conn.credentials.uses_k8s_secrets() {
+                (
+                    fingerprint(&(&config.kubeconfig_path, conn)),
+                    Some(self.k8s_client(config).await?),
+                )
+            } else {
+                (fingerprint(conn), None)
+            };
+            let connection = self.reuse(&conn.name, &fingerprint).unwrap_or_else(|| {
+                tracing::info!("Registering direct connection: {}", conn.name);
+                Arc::new(DirectConnection::new(conn.clone(), k8s_client))
+            });
+            built.insert(
+                conn.name.clone(),
+                BuiltConnection {
+                    fingerprint,
+                    connection,
+                },
+            );
+        }
+
+        // SSH connections open their tunnel on first use
+        for conn in &config.ssh_connections {
+            let (fingerprint, k8s_client) = if conn.credentials.uses_k8s_secrets() {
+                (
+                    fingerprint(&(&config.kubeconfig_path, conn)),
+                    Some(self.k8s_client(config).await?),
+                )
+            } else {
+                (fingerprint(conn), None)
+            };
+            let connection = self.reuse(&conn.name, &fingerprint).unwrap_or_else(|| {
+                tracing::info!("Registering SSH connection: {}", conn.name);
+                Arc::new(SshConnection::new(conn.clone(), k8s_client))
+            });
+            built.insert(
+                conn.name.clone(),
+                BuiltConnection {
+                    fingerprint,
+                    connection,
+                },
+            );
+        }
+
+        let changes = diff(&self.built, &built);
+        let mut registry = ConnectionRegistry::new();
+        for entry in built.values() {
+            registry.register(entry.connection.clone());
+        }
+        self.built = built;
+
+        Ok((registry, changes))
+    }
+
+    fn reuse(&self, name: &str, fingerprint: &str) -> Option<Arc<dyn MongoConnection>> {
+        self.built
+            .get(name)
+            .filter(|previous| previous.fingerprint == fingerprint)
+            .map(|previous| previous.connection.clone())
+    }
+}
+
+fn fingerprint(config: &impl serde::Serialize) -> String {
+    serde_json::to_string(config).unwrap_or_default()
+}
+
+fn diff(
+    before: &HashMap<String, BuiltConnection>,
+    after: &HashMap<String, BuiltConnection>,
+) -> RegistryChanges {
+    let mut changes = RegistryChanges::default();
+    for (name, entry) in after {
+        match before.get(name) {
+            None => changes.added.push(name.clone()),
+            Some(previous) if previous.fingerprint != entry.fingerprint => {
+                changes.updated.push(name.clone());
+            }
+            Some(_) => {}
+        }
+    }
+    changes.removed = before
+        .keys()
+        .filter(|name| !after.contains_key(*name))
+        .cloned()
+        .collect();
+
+    changes.added.sort();
+    changes.updated.sort();
+    changes.removed.sort();
+    changes
+}
+
+/// Connected MCP clients, notified when the connection list changes
+#[derive(Clone, Default)]
+pub struct ClientPeers(Arc<Mutex<Vec<Peer<RoleServer>>>>);
+
+impl ClientPeers {
+    pub fn add(&self, peer: Peer<RoleServer>) {
+        let mut peers = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        peers.retain(|p| !p.is_transport_closed());
+        peers.push(peer);
+    }
+
+    /// Send `notifications/tools/list_changed` to every connected client
+    pub async fn notify_tool_list_changed(&self) {
+        let peers: Vec<_> = {
+            let mut peers = self.0.lock().unwrap_or_else(|e| e.into_inner());
+            peers.retain(|p| !p.is_transport_closed());
+            peers.clone()
+        };
+        for peer in peers {
+            if let Err(e) = peer.notify_tool_list_changed().await {
+                tracing::debug!("Failed to notify client of reload: {}", e);
+            }
+        }
+    }
+}
+
+/// Re-reads the config and swaps in a new registry
+pub struct Reloader {
+    builder: tokio::sync::Mutex<RegistryBuilder>,
+    connections: SharedRegistry,
+    pages: Arc<PageStore>,
+    schemas: Arc<SchemaCache>,
+    peers: ClientPeers,
+    /// Config files and includes of the last successful load, which `watch` polls
+    sources: Mutex<Vec<PathBuf>>,
+}
+
+impl Reloader {
+    pub fn new(
+        builder: RegistryBuilder,
+        connections: SharedRegistry,
+        pages: Arc<PageStore>,
+        schemas: Arc<SchemaCache>,
+        peers: ClientPeers,
+    ) -> Self {
+        Self {
+            builder: tokio::sync::Mutex::new(builder),
+            connections,
+            pages,
+            schemas,
+            peers,
+            sources: Mutex::default(),
+        }
+    }
+
+    /// Reload the config files. Errors leave the current connections in place.
+    pub async fn reload(&self) -> Result<RegistryChanges> {
+        let config = Config::load_existing()?;
+
+        let mut builder = self.builder.lock().await;
+        let (registry, changes) = builder.build(&config).await?;
+        self.connections.replace(registry);
+        drop(builder);
+        self.evict(&changes);
+        self.set_sources(config.sources);
+
+        if !changes.is_empty() {
+            self.peers.notify_tool_list_changed().await;
+        }
+        Ok(changes)
+    }
+
+    /// Forget schemas and page tokens of connections whose settings changed or that
+    /// were removed, so a new redaction rule or denied collection applies at once
+    fn evict(&self, changes: &RegistryChanges) {
+        for name in changes.updated.iter().chain(&changes.removed) {
+            self.pages.remove_connection(name);
+            self.schemas.remove_connection(name);
+        }
+    }
+
+    async fn reload_and_log(&self) {
+        match self.reload().await {
+            Ok(changes) if changes.is_empty() => {
+                tracing::info!("Configuration reloaded, connections unchanged");
+            }
+            Ok(changes) => tracing::info!(
+                "Configuration reloaded: added {:?}, updated {:?}, removed {:?}",
+                changes.added,
+                changes.updated,
+                changes.removed
+            ),
+            Err(e) => tracing::error!(
+                "Failed to reload configuration, keeping current connections: {:#}",
+                e
+            ),
+        }
+    }
+
+    fn sources(&self) -> Vec<PathBuf> {
+        self.sources
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn set_sources(&self, sources: Vec<PathBuf>) {
+        *self.sources.lock().unwrap_or_else(|e| e.into_inner()) = sources;
+    }
+
+    /// Reload whenever one of `sources` (the loaded config files and their
+    /// includes) changes or the process receives SIGHUP
+    pub async fn watch(self: Arc<Self>, sources: Vec<PathBuf>) {
+        let modified = |files: &[PathBuf]| -> Vec<Option<SystemTime>> {
+            files
+                .iter()
+                .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+                .collect()
+        };
+        self.set_sources(sources);
+        let mut last_modified = modified(&self.sources());
+
+        #[cfg(unix)]
+        let mut hangup =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok();
+
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            #[cfg(unix)]
+            let signalled = tokio::select! {
+                _ = interval.tick() => false,
+                Some(()) = async {
+                    match hangup.as_mut() {
+                        Some(signal) => signal.recv().await,
+                        None => std::future::pending().await,
+                    }
+                } => true,
+            };
+            #[cfg(not(unix))]
+            let signalled = {
+                interval.tick().await;
+                false
+            };
+
+            let sources = self.sources();
+            let current = modified(&sources);
+            if signalled {
+                tracing::info!("Received SIGHUP, reloading configuration");
+            } else if current == last_modified {
+                continue;
+            } else {
+                tracing::info!("Configuration file changed, reloading");
+            }
+            last_modified = current;
+            self.reload_and_log().await;
+
+            // Includes may have been added or removed
+            let reloaded = self.sources();
+            if reloaded != sources {
+                last_modified = modified(&reloaded);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mongodb::{QueryOperation, QueryOptions};
+    use crate::results::PageCursor;
+
+    fn config(yaml: &str) -> Config {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_reuses_unchanged_connections() {
+        let mut builder = RegistryBuilder::new();
+        let (first, changes) = builder
+            .build(&config(
+                r"
+connections:
+  - name: local
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+  - name: reports
+    mongodb_url: mongodb://localhost:27017
+    database_name: reports
+",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(changes.added, ["local", "reports"]);
+
+        let (second, changes) = builder
+            .build(&config(
+                r"
+connections:
+  - name: local
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+  - name: reports
+    mongodb_url: mongodb://localhost:27017
+    database_name: reports
+    max_documents: 10
+  - name: staging
+    mongodb_url: mongodb://localhost:27018
+    database_name: app
+",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            changes,
+            RegistryChanges {
+                added: vec!["staging".to_string()],
+                updated: vec!["reports".to_string()],
+                removed: vec![],
+            }
+        );
+
+        // The unchanged connection is the same instance, keeping its client
+        assert!(Arc::ptr_eq(
+            &first.get("local").unwrap(),
+            &second.get("local").unwrap()
+        ));
+        assert!(!Arc::ptr_eq(
+            &first.get("reports").unwrap(),
+            &second.get("reports").unwrap()
+        ));
+
+        let (third, changes) = builder.build(&config("connections: []")).await.unwrap();
+        assert_eq!(changes.removed, ["local", "reports", "staging"]);
+        assert!(third.list_names().is_empty());
+    }
+
+    #[test]
+    fn test_evict_changed_connections() {
+        let pages = Arc::new(PageStore::new());
+        let schemas = Arc::new(SchemaCache::new());
+        let reloader = Reloader::new(
+            RegistryBuilder::new(),
+            SharedRegistry::default(),
+            pages.clone(),
+            schemas.clone(),
+            ClientPeers::default(),
+        );
+
+        let cursor = |connection: &str| PageCursor {
+            connection: connection.to_string(),
+            database: "app".to_string(),
+            collection: "users".to_string(),
+            operation: QueryOperation::Find,
+            query: "{}".to_string(),
+            options: QueryOptions::default(),
+            timeout_secs: 30,
+            saved_query: None,
+        };
+        let tokens: Vec<String> = ["local", "reports", "staging"]
+            .into_iter()
+            .map(|name| {
+                schemas.insert(name, "app", "users", serde_json::json!({"fields": []}));
+                pages.insert(cursor(name))
+            })
+            .collect();
+
+        reloader.evict(&RegistryChanges {
+            added: vec![],
+            updated: vec!["reports".to_string()],
+            removed: vec!["staging".to_string()],
+        });
+
+        assert!(schemas.get("local", "app", "users").is_some());
+        assert!(schemas.get("reports", "app", "users").is_none());
+        assert!(schemas.get("staging", "app", "users").is_none());
+        assert!(pages.take(&tokens[0]).is_some());
+        assert!(pages.take(&tokens[1]).is_none());
+        assert!(pages.take(&tokens[2]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_rejects_duplicate_names() {
+        let mut builder = RegistryBuilder::new();
+        let result = builder
+            .build(&config(
+                r"
+connections:
+  - name: local
+    mongodb_url: mongodb://localhost:27017
+    database_name: app
+  - name: local
+    mongodb_url: mongodb://localhost:27018
+    database_name: app
+",
+            ))
+            .await;
+        assert!(result.is_err());
+    }
+}
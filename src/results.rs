@@ -0,0 +1,339 @@
+//! Query results, server-enforced size limits and pagination.
+//!
+//! Both backends stop reading once a page is full, so a `find` without a limit
+//! never materialises a whole collection. Truncated results get a page token
+//! that resumes the same query with a `skip` continuation.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::mongodb::{QueryOperation, QueryOptions};
+
+/// How long an unused page token stays valid
+const PAGE_TOKEN_TTL: Duration = Duration::from_secs(1800);
+
+/// Maximum number of outstanding page tokens kept in memory
+const MAX_PAGE_TOKENS: usize = 256;
+
+/// Per-call result size limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ResultLimits {
+    pub max_documents: usize,
+    pub max_result_bytes: usize,
+}
+
+/// Result of a query after limits are applied
+#[derive(Debug, Clone)]
+pub enum QueryResult {
+    /// countDocuments result
+    Count(u64),
+    /// Documents (find/aggregate) or values (distinct) as Extended JSON
+    Documents { items: Vec<Value>, truncated: bool },
+}
+
+impl QueryResult {
+    /// Render the result the way tools return it: a number or a JSON array
+    pub fn to_json_string(&self) -> Result<String> {
+        match self {
+            Self::Count(count) => Ok(count.to_string()),
+            Self::Documents { items, .. } => {
+                serde_json::to_string(items).context("Failed to serialize query results")
+            }
+        }
+    }
+
+    /// Number of documents or values returned
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Count(_) => 1,
+            Self::Documents { items, .. } => items.len(),
+        }
+    }
+
+    pub const fn is_truncated(&self) -> bool {
+        matches!(
+            self,
+            Self::Documents {
+                truncated: true,
+                ..
+            }
+        )
+    }
+}
+
+/// Accumulates result items until a limit is reached.
+/// Without limits every item is accepted.
+pub struct PageBuilder {
+    limits: Option<ResultLimits>,
+    items: Vec<Value>,
+    bytes: usize,
+    truncated: bool,
+}
+
+impl PageBuilder {
+    pub const fn new(limits: Option<ResultLimits>) -> Self {
+        Self {
+            limits,
+            items: Vec::new(),
+            bytes: 0,
+            truncated: false,
+        }
+    }
+
+    /// Add an item. Returns false once the page is full; the item is then not included.
+    /// The first item is always accepted so pagination makes progress.
+    pub fn push(&mut self, item: Value) -> bool {
+        let Some(limits) = self.limits else {
+            self.items.push(item);
+            return true;
+        };
+
+        if self.items.len() >= limits.max_documents {
+            self.truncated = true;
+            return false;
+        }
+
+        let size = item.to_string().len();
+        if !self.items.is_empty() && self.bytes + size > limits.max_result_bytes {
+            self.truncated = true;
+            return false;
+        }
+
+        self.bytes += size;
+        self.items.push(item);
+        true
+    }
+
+    pub fn finish(self) -> QueryResult {
+        QueryResult::Documents {
+            items: self.items,
+            truncated: self.truncated,
+        }
+    }
+
+    /// Build a page from an already materialised list (distinct values, mongosh output)
+    pub fn collect(
+        items: impl IntoIterator<Item = Value>,
+        limits: Option<ResultLimits>,
+    ) -> QueryResult {
+        let mut page = Self::new(limits);
+        for item in items {
+            if !page.push(item) {
+                break;
+            }
+        }
+        page.finish()
+    }
+}
+
+/// A query that can be resumed from where the previous page stopped
+#[derive(Debug, Clone)]
+pub struct PageCursor {
+    pub connection: String,
+    pub database: String,
+    pub collection: String,
+    pub operation: QueryOperation,
+    pub query: String,
+    pub options: QueryOptions,
+    /// Time limit of the original call, reused for every page
+    pub timeout_secs: u64,
+    /// Saved query the cursor came from, for the audit log
+    pub saved_query: Option<String>,
+}
+
+impl PageCursor {
+    /// Cursor for the page following `returned` items of this one, if any remain.
+    /// An empty page can't make progress, so it never has a next page.
+    pub fn next(&self, returned: usize) -> Option<Self> {
+        if returned == 0 {
+            return None;
+        }
+        let returned_u32 = u32::try_from(returned).unwrap_or(u32::MAX);
+        let limit = match self.options.limit {
+            Some(limit) if limit <= returned_u32 => return None,
+            Some(limit) => Some(limit - returned_u32),
+            None => None,
+        };
+
+        let mut next = self.clone();
+        next.options.skip = Some(self.options.skip.unwrap_or(0) + returned as u64);
+        next.options.limit = limit;
+        Some(next)
+    }
+}
+
+struct StoredCursor {
+    cursor: PageCursor,
+    created_at: Instant,
+}
+
+/// Server-side store of page tokens
+#[derive(Default)]
+pub struct PageStore {
+    cursors: Mutex<HashMap<String, StoredCursor>>,
+    counter: AtomicU64,
+    hasher: RandomState,
+}
+
+impl PageStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a cursor and return its token
+    pub fn insert(&self, cursor: PageCursor) -> String {
+        let token = self.new_token();
+        let mut cursors = self.cursors.lock().unwrap_or_else(|e| e.into_inner());
+
+        cursors.retain(|_, c| c.created_at.elapsed() < PAGE_TOKEN_TTL);
+        if cursors.len() >= MAX_PAGE_TOKENS
+            && let Some(oldest) = cursors
+                .iter()
+                .min_by_key(|(_, c)| c.created_at)
+                .map(|(token, _)| token.clone())
+        {
+            cursors.remove(&oldest);
+        }
+
+        cursors.insert(
+            token.clone(),
+            StoredCursor {
+                cursor,
+                created_at: Instant::now(),
+            },
+        );
+        token
+    }
+
+    /// Drop every token of a connection, e.g. after a reload changed its settings
+    pub fn remove_connection(&self, connection: &str) {
+        let mut cursors = self.cursors.lock().unwrap_or_else(|e| e.into_inner());
+        cursors.retain(|_, c| c.cursor.connection != connection);
+    }
+
+    /// Take a cursor by token. Tokens are single-use.
+    pub fn take(&self, token: &str) -> Option<PageCursor> {
+        let mut cursors = self.cursors.lock().unwrap_or_else(|e| e.into_inner());
+        cursors
+            .remove(token)
+            .filter(|c| c.created_at.elapsed() < PAGE_TOKEN_TTL)
+            .map(|c| c.cursor)
+    }
+
+    fn new_token(&self) -> String {
+        let mut hasher = self.hasher.build_hasher();
+        hasher.write_u64(self.counter.fetch_add(1, Ordering::Relaxed));
+        hasher.write_u128(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default(),
+        );
+        format!("pg_{:016x}", hasher.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn limits(max_documents: usize, max_result_bytes: usize) -> Option<ResultLimits> {
+        Some(ResultLimits {
+            max_documents,
+            max_result_bytes,
+        })
+    }
+
+    #[test]
+    fn test_page_builder_document_limit() {
+        let result = PageBuilder::collect((0..5).map(|i| json!({"i": i})), limits(3, 1024));
+        assert_eq!(result.len(), 3);
+        assert!(result.is_truncated());
+
+        // Exactly at the limit is not truncated
+        let result = PageBuilder::collect((0..3).map(|i| json!({"i": i})), limits(3, 1024));
+        assert_eq!(result.len(), 3);
+        assert!(!result.is_truncated());
+    }
+
+    #[test]
+    fn test_page_builder_byte_limit() {
+        // Each item serializes to 7 bytes: {"i":0}
+        let result = PageBuilder::collect((0..5).map(|i| json!({"i": i})), limits(100, 15));
+        assert_eq!(result.len(), 2);
+        assert!(result.is_truncated());
+
+        // An oversized first item is still returned
+        let result = PageBuilder::collect(vec![json!({"big": "x".repeat(50)})], limits(100, 10));
+        assert_eq!(result.len(), 1);
+        assert!(!result.is_truncated());
+    }
+
+    #[test]
+    fn test_page_builder_unlimited() {
+        let result = PageBuilder::collect((0..5000).map(|i| json!(i)), None);
+        assert_eq!(result.len(), 5000);
+        assert!(!result.is_truncated());
+    }
+
+    #[test]
+    fn test_page_cursor_next() {
+        let cursor = PageCursor {
+            connection: "local".to_string(),
+            database: "app".to_string(),
+            collection: "users".to_string(),
+            operation: QueryOperation::Find,
+            query: "{}".to_string(),
+            options: QueryOptions {
+                limit: Some(250),
+                ..Default::default()
+            },
+            timeout_secs: 30,
+            saved_query: None,
+        };
+
+        let next = cursor.next(100).unwrap();
+        assert_eq!(next.options.skip, Some(100));
+        assert_eq!(next.options.limit, Some(150));
+
+        let last = next.next(100).unwrap();
+        assert_eq!(last.options.skip, Some(200));
+        assert_eq!(last.options.limit, Some(50));
+
+        assert!(last.next(50).is_none());
+
+        // Same skip again would loop forever
+        assert!(cursor.next(0).is_none());
+    }
+
+    #[test]
+    fn test_page_store_tokens_are_single_use() {
+        let store = PageStore::new();
+        let cursor = PageCursor {
+            connection: "local".to_string(),
+            database: "app".to_string(),
+            collection: "users".to_string(),
+            operation: QueryOperation::Aggregate,
+            query: "[]".to_string(),
+            options: QueryOptions::default(),
+            timeout_secs: 30,
+            saved_query: None,
+        };
+
+        let a = store.insert(cursor.clone());
+        let b = store.insert(cursor);
+        assert_ne!(a, b);
+
+        assert!(store.take(&a).is_some());
+        assert!(store.take(&a).is_none());
+        assert!(store.take("pg_unknown").is_none());
+    }
+}
@@ -108,6 +108,22 @@ impl SavedQueries {
         self.queries.iter().find(|q| q.name == name)
     }
 
+    /// Add queries from an export, replacing queries with the same name.
+    /// Returns how many were added and how many replaced.
+    pub fn merge(&mut self, imported: Self) -> (usize, usize) {
+        let (mut added, mut replaced) = (0, 0);
+        for query in imported.queries {
+            if let Some(existing) = self.queries.iter_mut().find(|q| q.name == query.name) {
+                *existing = query;
+                replaced += 1;
+            } else {
+                self.queries.push(query);
+                added += 1;
+            }
+        }
+        (added, replaced)
+    }
+
     /// Delete a query by name
     pub fn delete_query(&mut self, name: &str) -> bool {
         let original_len = self.queries.len();
@@ -184,6 +200,35 @@ mod tests {
         assert!(!queries.delete_query("test"));
     }
 
+    #[test]
+    fn test_merge_queries() {
+        let mut queries = SavedQueries::default();
+        queries.upsert_query(
+            "active".to_string(),
+            "old".to_string(),
+            "users".to_string(),
+            "find".to_string(),
+            "{}".to_string(),
+            None,
+        );
+
+        let mut imported = SavedQueries::default();
+        for name in ["active", "recent"] {
+            imported.upsert_query(
+                name.to_string(),
+                "imported".to_string(),
+                "users".to_string(),
+                "find".to_string(),
+                "{}".to_string(),
+                None,
+            );
+        }
+
+        assert_eq!(queries.merge(imported), (1, 1));
+        assert_eq!(queries.queries.len(), 2);
+        assert_eq!(queries.get_query("active").unwrap().description, "imported");
+    }
+
     #[test]
     fn test_upsert_with_distinct_field() {
         let mut queries = SavedQueries::default();
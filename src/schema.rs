@@ -0,0 +1,279 @@
+//! Collection schema inference from sampled documents.
+//!
+//! Documents are sampled as canonical Extended JSON, so BSON types can be read
+//! back from the type wrappers (`$oid`, `$date`, `$numberLong`, ...).
+
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long an inferred schema is reused before sampling again
+const SCHEMA_CACHE_TTL: Duration = Duration::from_secs(600);
+
+/// Maximum number of field paths reported, to keep the summary compact
+const MAX_FIELD_PATHS: usize = 300;
+
+/// Statistics for a single field path
+#[derive(Default)]
+struct FieldStats {
+    /// Documents containing this path at least once
+    documents: usize,
+    /// Occurrences per BSON type
+    types: BTreeMap<&'static str, usize>,
+}
+
+/// Accumulates field paths and types over sampled documents
+#[derive(Default)]
+pub struct SchemaBuilder {
+    sampled: usize,
+    fields: BTreeMap<String, FieldStats>,
+}
+
+/// BSON type name for a canonical Extended JSON value
+fn bson_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(n) if n.is_f64() => "double",
+        Value::Number(_) => "int",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(map) => wrapper_type(map).unwrap_or("object"),
+    }
+}
+
+/// Recognise Extended JSON type wrappers such as `{"$oid": "..."}`
+fn wrapper_type(map: &Map<String, Value>) -> Option<&'static str> {
+    let key = map.keys().next()?;
+    let ty = match key.as_str() {
+        "$oid" => "objectId",
+        "$date" => "date",
+        "$numberInt" => "int",
+        "$numberLong" => "long",
+        "$numberDouble" => "double",
+        "$numberDecimal" => "decimal",
+        "$binary" => "binData",
+        "$uuid" => "uuid",
+        "$regularExpression" => "regex",
+        "$timestamp" => "timestamp",
+        "$code" => "javascript",
+        "$symbol" => "symbol",
+        "$dbPointer" => "dbPointer",
+        "$minKey" => "minKey",
+        "$maxKey" => "maxKey",
+        "$undefined" => "undefined",
+        _ => return None,
+    };
+    // `$code` may carry `$scope`; every other wrapper has exactly one key
+    (map.len() == 1 || key == "$code").then_some(ty)
+}
+
+impl SchemaBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one sampled document
+    pub fn add_document(&mut self, doc: &Value) {
+        let Value::Object(map) = doc else {
+            return;
+        };
+        self.sampled += 1;
+
+        let mut seen = HashSet::new();
+        for (key, value) in map {
+            self.add_value(key.clone(), value, &mut seen);
+        }
+    }
+
+    fn add_value(&mut self, path: String, value: &Value, seen: &mut HashSet<String>) {
+        let ty = bson_type(value);
+
+        let stats = self.fields.entry(path.clone()).or_default();
+        *stats.types.entry(ty).or_default() += 1;
+        if seen.insert(path.clone()) {
+            stats.documents += 1;
+        }
+
+        match value {
+            Value::Object(map) if ty == "object" => {
+                for (key, child) in map {
+                    self.add_value(format!("{path}.{key}"), child, seen);
+                }
+            }
+            Value::Array(items) => {
+                // Array elements are reported under `path[]`
+                for item in items {
+                    self.add_value(format!("{path}[]"), item, seen);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Summarise as `{sampled, fields: [{path, presence, types}]}`.
+    /// `presence` is the fraction of sampled documents containing the path;
+    /// `types` counts occurrences per BSON type.
+    pub fn finish(self) -> Value {
+        let total_paths = self.fields.len();
+        let sampled = self.sampled.max(1) as f64;
+
+        let fields: Vec<Value> = self
+            .fields
+            .into_iter()
+            .take(MAX_FIELD_PATHS)
+            .map(|(path, stats)| {
+                let presence = (stats.documents as f64 / sampled * 1000.0).round() / 1000.0;
+                json!({
+                    "path": path,
+                    "presence": presence,
+                    "types": stats.types,
+                })
+            })
+            .collect();
+
+        let mut summary = json!({
+            "sampled": self.sampled,
+            "fields": fields,
+        });
+        if total_paths > MAX_FIELD_PATHS {
+            summary["omitted_paths"] = json!(total_paths - MAX_FIELD_PATHS);
+        }
+        summary
+    }
+}
+
+/// Infer a schema summary from sampled documents
+pub fn infer(docs: &[Value]) -> Value {
+    let mut builder = SchemaBuilder::new();
+    for doc in docs {
+        builder.add_document(doc);
+    }
+    builder.finish()
+}
+
+type SchemaKey = (String, String, String);
+
+/// Inferred schemas cached per connection, database and collection
+#[derive(Default)]
+pub struct SchemaCache {
+    entries: Mutex<HashMap<SchemaKey, (Instant, Value)>>,
+}
+
+impl SchemaCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key(connection: &str, database: &str, collection: &str) -> SchemaKey {
+        (
+            connection.to_string(),
+            database.to_string(),
+            collection.to_string(),
+        )
+    }
+
+    pub fn get(&self, connection: &str, database: &str, collection: &str) -> Option<Value> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(&Self::key(connection, database, collection))
+            .filter(|(cached_at, _)| cached_at.elapsed() < SCHEMA_CACHE_TTL)
+            .map(|(_, schema)| schema.clone())
+    }
+
+    /// Drop every cached schema of a connection, e.g. after a reload changed its settings
+    pub fn remove_connection(&self, connection: &str) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|(name, _, _), _| name != connection);
+    }
+
+    pub fn insert(&self, connection: &str, database: &str, collection: &str, schema: Value) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (cached_at, _)| cached_at.elapsed() < SCHEMA_CACHE_TTL);
+        entries.insert(
+            Self::key(connection, database, collection),
+            (Instant::now(), schema),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field<'a>(schema: &'a Value, path: &str) -> &'a Value {
+        schema["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|f| f["path"] == path)
+            .unwrap_or_else(|| panic!("missing path {path}"))
+    }
+
+    #[test]
+    fn test_infer_types_and_presence() {
+        let docs = vec![
+            json!({
+                "_id": {"$oid": "65a1b2c3d4e5f60718293a4b"},
+                "age": {"$numberInt": "31"},
+                "createdAt": {"$date": {"$numberLong": "1704067200000"}},
+                "email": "a@example.com"
+            }),
+            json!({
+                "_id": {"$oid": "65a1b2c3d4e5f60718293a4c"},
+                "age": {"$numberLong": "40"},
+                "createdAt": {"$date": {"$numberLong": "1704067200000"}}
+            }),
+        ];
+        let schema = infer(&docs);
+
+        assert_eq!(schema["sampled"], 2);
+        assert_eq!(field(&schema, "_id")["types"], json!({"objectId": 2}));
+        assert_eq!(field(&schema, "age")["types"], json!({"int": 1, "long": 1}));
+        assert_eq!(field(&schema, "createdAt")["types"], json!({"date": 2}));
+        assert_eq!(field(&schema, "email")["presence"], 0.5);
+    }
+
+    #[test]
+    fn test_infer_nested_objects_and_arrays() {
+        let docs = vec![json!({
+            "address": {"city": "Berlin", "geo": {"lat": {"$numberDouble": "52.5"}}},
+            "tags": ["a", "b"],
+            "items": [{"sku": "x", "qty": {"$numberInt": "1"}}, {"sku": "y"}]
+        })];
+        let schema = infer(&docs);
+
+        assert_eq!(field(&schema, "address")["types"], json!({"object": 1}));
+        assert_eq!(
+            field(&schema, "address.geo.lat")["types"],
+            json!({"double": 1})
+        );
+        assert_eq!(field(&schema, "tags[]")["types"], json!({"string": 2}));
+        assert_eq!(field(&schema, "items[].sku")["types"], json!({"string": 2}));
+        // Repeated occurrences in one document count once towards presence
+        assert_eq!(field(&schema, "items[].sku")["presence"], 1.0);
+        assert_eq!(field(&schema, "items[].qty")["types"], json!({"int": 1}));
+    }
+
+    #[test]
+    fn test_wrapper_detection_requires_single_key() {
+        // A document that merely has a "$date"-like first key plus others is an object
+        let value = json!({"$date": "2024-01-01", "other": 1});
+        assert_eq!(bson_type(&value), "object");
+        assert_eq!(
+            bson_type(&json!({"$code": "x", "$scope": {}})),
+            "javascript"
+        );
+    }
+
+    #[test]
+    fn test_schema_cache() {
+        let cache = SchemaCache::new();
+        assert!(cache.get("local", "app", "users").is_none());
+        cache.insert("local", "app", "users", json!({"sampled": 1}));
+        assert_eq!(cache.get("local", "app", "users").unwrap()["sampled"], 1);
+        assert!(cache.get("local", "app", "orders").is_none());
+        assert!(cache.get("local", "tenant_a", "users").is_none());
+    }
+}
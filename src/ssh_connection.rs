@@ -0,0 +1,259 @@
+//! MongoDB connection through an SSH bastion host.
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use mongodb::{
+    Client,
+    bson::doc,
+    options::{ClientOptions, ServerAddress},
+};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::access;
+use crate::collections::CollectionInfo;
+use crate::config::{QuerySettings, SshConnectionConfig};
+use crate::connection::MongoConnection;
+use crate::diagnostics::{self, CheckStep, Checker};
+use crate::explain::{self, ExplainVerbosity};
+use crate::indexes::IndexInfo;
+use crate::k8s_client::K8sClient;
+use crate::mongodb::{CLIENT_TIMEOUT_GRACE_SECS, QueryOperation, QueryOptions};
+use crate::native;
+use crate::results::QueryResult;
+use crate::ssh_tunnel::SshTunnel;
+
+/// Native driver client bound to the SSH tunnel it connects through
+struct TunnelClient {
+    client: Client,
+    tunnel: SshTunnel,
+}
+
+/// MongoDB connection over an SSH local forward.
+/// The tunnel is opened on first use and reopened if the ssh process exits.
+pub struct SshConnection {
+    config: SshConnectionConfig,
+    /// Needed only when credentials come from Kubernetes Secrets
+    k8s_client: Option<Arc<K8sClient>>,
+    state: Mutex<Option<TunnelClient>>,
+}
+
+impl SshConnection {
+    pub fn new(config: SshConnectionConfig, k8s_client: Option<Arc<K8sClient>>) -> Self {
+        Self {
+            config,
+            k8s_client,
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Client connected through a running tunnel, opening one if needed
+    async fn get_client(&self) -> Result<Client> {
+        let mut state = self.state.lock().await;
+        if let Some(current) = state.as_mut() {
+            if current.tunnel.is_alive() {
+                return Ok(current.client.clone());
+            }
+            tracing::warn!(
+                "SSH tunnel for connection '{}' exited, reconnecting",
+                self.config.name
+            );
+            *state = None;
+        }
+
+        tracing::info!("Initializing SSH connection '{}'", self.config.name);
+
+        let mut client_options = ClientOptions::parse(&self.config.mongodb_url)
+            .await
+            .context("Failed to parse MongoDB connection URL")?;
+        let (host, port) = tunnel_target(&client_options)?;
+
+        self.config
+            .credentials
+            .apply(
+                &self.config.mongodb_url,
+                &mut client_options,
+                self.k8s_client.as_deref(),
+            )
+            .await
+            .context("Failed to resolve connection credentials")?;
+        self.config.settings.read.apply(&mut client_options);
+
+        let tunnel = self.config.ssh.open(&host, port).await?;
+        let addr = tunnel.local_addr();
+        client_options.hosts = vec![ServerAddress::Tcp {
+            host: addr.ip().to_string(),
+            port: Some(addr.port()),
+        }];
+        // Other replica set members are not reachable through the tunnel
+        client_options.direct_connection = Some(true);
+        client_options.repl_set_name = None;
+
+        let client = native::connect(client_options)?;
+        *state = Some(TunnelClient {
+            client: client.clone(),
+            tunnel,
+        });
+        Ok(client)
+    }
+}
+
+/// The single host of a parsed URL, which the tunnel forwards to
+fn tunnel_target(client_options: &ClientOptions) -> Result<(String, u16)> {
+    match client_options.hosts.as_slice() {
+        [ServerAddress::Tcp { host, port }] => Ok((host.clone(), port.unwrap_or(27017))),
+        [_] => bail!("mongodb_url of an SSH connection must use a TCP host"),
+        _ => bail!(
+            "mongodb_url of an SSH connection must name exactly one host, found {}",
+            client_options.hosts.len()
+        ),
+    }
+}
+
+#[async_trait]
+impl MongoConnection for SshConnection {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn connection_type(&self) -> &str {
+        "ssh"
+    }
+
+    fn data_model_path(&self) -> Option<&str> {
+        self.config.data_model_file_path.as_deref()
+    }
+
+    fn database_name(&self) -> &str {
+        &self.config.database_name
+    }
+
+    fn settings(&self) -> &QuerySettings {
+        &self.config.settings
+    }
+
+    async fn list_databases(&self, timeout_secs: u64) -> Result<serde_json::Value> {
+        let client = self.get_client().await?;
+        native::run_command(
+            &client,
+            "admin",
+            access::list_databases_command(),
+            timeout_secs,
+        )
+        .await
+    }
+
+    async fn list_collections(
+        &self,
+        database: &str,
+        timeout_secs: u64,
+    ) -> Result<Vec<CollectionInfo>> {
+        let client = self.get_client().await?;
+        native::list_collections(&client, database, timeout_secs).await
+    }
+
+    async fn list_indexes(
+        &self,
+        database: &str,
+        collection: &str,
+        timeout_secs: u64,
+    ) -> Result<Vec<IndexInfo>> {
+        let client = self.get_client().await?;
+        native::list_indexes(&client, database, collection, timeout_secs).await
+    }
+
+    async fn database_stats(&self, database: &str, timeout_secs: u64) -> Result<serde_json::Value> {
+        let client = self.get_client().await?;
+        native::run_command(&client, database, doc! { "dbStats": 1 }, timeout_secs).await
+    }
+
+    async fn execute_query(
+        &self,
+        database: &str,
+        collection: &str,
+        operation: &QueryOperation,
+        query: &str,
+        options: &QueryOptions,
+        timeout_secs: u64,
+    ) -> Result<QueryResult> {
+        let client = self.get_client().await?;
+
+        tracing::info!(
+            "Executing {:?} on {}.{} via SSH connection '{}'",
+            operation,
+            database,
+            collection,
+            self.config.name
+        );
+
+        native::execute_query(
+            &client,
+            database,
+            collection,
+            operation,
+            query,
+            options,
+            timeout_secs,
+        )
+        .await
+    }
+
+    async fn explain_query(
+        &self,
+        database: &str,
+        collection: &str,
+        operation: &QueryOperation,
+        query: &str,
+        options: &QueryOptions,
+        verbosity: ExplainVerbosity,
+        timeout_secs: u64,
+    ) -> Result<serde_json::Value> {
+        let options = options.with_max_time(timeout_secs);
+        let command = explain::explain_command(collection, operation, query, &options, verbosity)?;
+        let client = self.get_client().await?;
+        native::run_command(
+            &client,
+            database,
+            command,
+            timeout_secs + CLIENT_TIMEOUT_GRACE_SECS,
+        )
+        .await
+    }
+
+    async fn check(&self, database: &str, timeout_secs: u64) -> Vec<CheckStep> {
+        let mut checker = Checker::new(&["ssh_tunnel", "auth", "ping", "database"]);
+        let client = checker
+            .run("ssh_tunnel", async {
+                Ok((
+                    self.get_client().await?,
+                    format!("via {}", self.config.ssh.host),
+                ))
+            })
+            .await;
+        if let Some(client) = client {
+            diagnostics::check_native(&mut checker, &client, database, timeout_secs).await;
+        }
+        checker.into_steps()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tunnel_target() {
+        let options = ClientOptions::parse("mongodb://db1.internal/?authSource=admin")
+            .await
+            .unwrap();
+        assert_eq!(
+            tunnel_target(&options).unwrap(),
+            ("db1.internal".to_string(), 27017)
+        );
+
+        let options = ClientOptions::parse("mongodb://db1.internal:27018,db2.internal:27018")
+            .await
+            .unwrap();
+        assert!(tunnel_target(&options).is_err());
+    }
+}
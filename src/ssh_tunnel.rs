@@ -0,0 +1,241 @@
+//! SSH local forwards through a bastion host.
+//!
+//! Runs the system `ssh` client (`ssh -N -L ...`), so keys, the SSH agent,
+//! known_hosts and `~/.ssh/config` work exactly as they do on the command line.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::process::{Child, Command};
+
+const fn default_ssh_port() -> u16 {
+    22
+}
+
+const fn default_connect_timeout_secs() -> u64 {
+    15
+}
+
+/// Bastion host settings (`ssh` section of an SSH connection)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SshTunnelConfig {
+    /// Bastion hostname or `~/.ssh/config` alias
+    pub host: String,
+    #[serde(default = "default_ssh_port")]
+    pub port: u16,
+    /// Login user (default: from `~/.ssh/config`, else the local user)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// Private key to authenticate with; without it the agent and default keys are used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<String>,
+    /// known_hosts file used to verify the bastion (default: `~/.ssh/known_hosts`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub known_hosts_file: Option<String>,
+    /// Trust and record the host key on first connection instead of failing
+    #[serde(default)]
+    pub accept_new_host_keys: bool,
+    /// Time allowed for connecting and authenticating
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+}
+
+impl SshTunnelConfig {
+    /// Arguments for `ssh` forwarding `local_port` on 127.0.0.1 to `target_host:target_port`
+    fn ssh_args(&self, local_port: u16, target_host: &str, target_port: u16) -> Vec<String> {
+        let mut args: Vec<String> = [
+            "-N",
+            "-T",
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ExitOnForwardFailure=yes",
+            "-o",
+            "ServerAliveInterval=30",
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+        let host_key_checking = if self.accept_new_host_keys {
+            "accept-new"
+        } else {
+            "yes"
+        };
+        args.push("-o".to_string());
+        args.push(format!("StrictHostKeyChecking={host_key_checking}"));
+        args.push("-o".to_string());
+        args.push(format!("ConnectTimeout={}", self.connect_timeout_secs));
+
+        if let Some(path) = &self.known_hosts_file {
+            args.push("-o".to_string());
+            args.push(format!("UserKnownHostsFile={path}"));
+        }
+        if let Some(path) = &self.identity_file {
+            args.push("-i".to_string());
+            args.push(path.clone());
+            args.push("-o".to_string());
+            args.push("IdentitiesOnly=yes".to_string());
+        }
+        if let Some(user) = &self.user {
+            args.push("-l".to_string());
+            args.push(user.clone());
+        }
+
+        // IPv6 literals need brackets in a forward spec
+        let target_host = if target_host.contains(':') {
+            format!("[{target_host}]")
+        } else {
+            target_host.to_string()
+        };
+        args.push("-p".to_string());
+        args.push(self.port.to_string());
+        args.push("-L".to_string());
+        args.push(format!(
+            "127.0.0.1:{local_port}:{target_host}:{target_port}"
+        ));
+        // `--` keeps a host starting with '-' from being read as an option
+        args.push("--".to_string());
+        args.push(self.host.clone());
+        args
+    }
+
+    /// Start `ssh` and wait until the forward accepts connections
+    pub async fn open(&self, target_host: &str, target_port: u16) -> Result<SshTunnel> {
+        let local_port = free_local_port().await?;
+        let local_addr = SocketAddr::from(([127, 0, 0, 1], local_port));
+
+        tracing::info!(
+            "Opening SSH tunnel {} -> {}:{} via {}",
+            local_addr,
+            target_host,
+            target_port,
+            self.host
+        );
+
+        // stdin and stdout carry the MCP stdio transport, so ssh must not touch them
+        let mut child = Command::new("ssh")
+            .args(self.ssh_args(local_port, target_host, target_port))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("Failed to start ssh")?;
+
+        let deadline = Instant::now() + Duration::from_secs(self.connect_timeout_secs + 5);
+        loop {
+            if let Some(status) = child.try_wait().context("Failed to check ssh process")? {
+                let mut stderr = String::new();
+                if let Some(mut pipe) = child.stderr.take() {
+                    let _ = pipe.read_to_string(&mut stderr).await;
+                }
+                bail!(
+                    "SSH tunnel via {} failed ({}): {}",
+                    self.host,
+                    status,
+                    stderr.trim()
+                );
+            }
+            if TcpStream::connect(local_addr).await.is_ok() {
+                break;
+            }
+            if Instant::now() >= deadline {
+                bail!(
+                    "SSH tunnel via {} not ready after {} seconds",
+                    self.host,
+                    self.connect_timeout_secs + 5
+                );
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        // Keep logging what ssh reports, e.g. a dropped connection
+        if let Some(stderr) = child.stderr.take() {
+            let host = self.host.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    tracing::warn!("ssh {}: {}", host, line);
+                }
+            });
+        }
+
+        Ok(SshTunnel { local_addr, child })
+    }
+}
+
+/// A running `ssh` local forward. The process is killed when this is dropped.
+pub struct SshTunnel {
+    local_addr: SocketAddr,
+    child: Child,
+}
+
+impl SshTunnel {
+    /// Local address clients should connect to
+    pub const fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Whether the ssh process is still running
+    pub fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+/// An unused local port. ssh binds it itself, so another process could in
+/// principle take it in between; `ExitOnForwardFailure` turns that into an error.
+async fn free_local_port() -> Result<u16> {
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .context("Failed to find a free local port")?;
+    Ok(listener.local_addr()?.port())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tunnel_config(yaml: &str) -> SshTunnelConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_ssh_args() {
+        let config = tunnel_config("host: bastion.example.com");
+        assert_eq!(config.port, 22);
+        let args = config.ssh_args(40000, "db1.internal", 27017);
+        assert!(args.contains(&"StrictHostKeyChecking=yes".to_string()));
+        assert!(!args.contains(&"-i".to_string()));
+        assert_eq!(
+            args[args.len() - 4..],
+            [
+                "-L",
+                "127.0.0.1:40000:db1.internal:27017",
+                "--",
+                "bastion.example.com"
+            ]
+        );
+
+        let config = tunnel_config(
+            "host: bastion\nport: 2222\nuser: deploy\nidentity_file: /keys/id_ed25519\nknown_hosts_file: /keys/known_hosts\naccept_new_host_keys: true",
+        );
+        let args = config.ssh_args(40000, "fd00::12", 27017).join(" ");
+        assert!(args.contains("-i /keys/id_ed25519 -o IdentitiesOnly=yes"));
+        assert!(args.contains("-o UserKnownHostsFile=/keys/known_hosts"));
+        assert!(args.contains("StrictHostKeyChecking=accept-new"));
+        assert!(args.contains("-l deploy"));
+        assert!(args.contains("-p 2222 -L 127.0.0.1:40000:[fd00::12]:27017 -- bastion"));
+    }
+
+    #[tokio::test]
+    async fn test_open_reports_ssh_failure() {
+        // Nothing listens on port 1, so ssh exits (or is missing) and open fails
+        let config = tunnel_config("host: 127.0.0.1\nport: 1\nconnect_timeout_secs: 2");
+        assert!(config.open("localhost", 27017).await.is_err());
+    }
+}
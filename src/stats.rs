@@ -0,0 +1,143 @@
+//! Collection and database size statistics.
+//!
+//! Collection stats come from a `$collStats` aggregation, which runs through the
+//! regular query path on both backends. Sharded collections return one document
+//! per shard; the summary adds them up.
+
+use serde_json::{Map, Value, json};
+
+/// `$collStats` pipeline reporting storage statistics
+pub fn collection_stats_pipeline() -> String {
+    json!([{"$collStats": {"storageStats": {}}}]).to_string()
+}
+
+fn number(value: Option<&Value>) -> f64 {
+    value.and_then(Value::as_f64).unwrap_or(0.0)
+}
+
+/// Summarise `$collStats` output (one document per shard)
+pub fn summarize_collection(collection: &str, shards: &[Value]) -> Value {
+    let mut count = 0.0;
+    let mut size = 0.0;
+    let mut storage_size = 0.0;
+    let mut total_index_size = 0.0;
+    let mut index_sizes = Map::new();
+    let mut capped = false;
+
+    for shard in shards {
+        let Some(storage) = shard.get("storageStats") else {
+            continue;
+        };
+        count += number(storage.get("count"));
+        size += number(storage.get("size"));
+        storage_size += number(storage.get("storageSize"));
+        total_index_size += number(storage.get("totalIndexSize"));
+        capped |= storage
+            .get("capped")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+
+        if let Some(sizes) = storage.get("indexSizes").and_then(Value::as_object) {
+            for (name, bytes) in sizes {
+                let total = number(index_sizes.get(name)) + number(Some(bytes));
+                index_sizes.insert(name.clone(), json!(total as u64));
+            }
+        }
+    }
+
+    let avg_obj_size = if count > 0.0 { size / count } else { 0.0 };
+
+    json!({
+        "collection": collection,
+        "estimated_count": count as u64,
+        "avg_document_bytes": avg_obj_size.round() as u64,
+        "data_bytes": size as u64,
+        "storage_bytes": storage_size as u64,
+        "total_index_bytes": total_index_size as u64,
+        "index_sizes": index_sizes,
+        "capped": capped,
+        "shards": shards.len(),
+    })
+}
+
+/// Summarise a `dbStats` reply
+pub fn summarize_database(database: &str, stats: &Value) -> Value {
+    let field = |key: &str| number(stats.get(key)) as u64;
+    json!({
+        "database": database,
+        "collections": field("collections"),
+        "views": field("views"),
+        "estimated_documents": field("objects"),
+        "avg_document_bytes": number(stats.get("avgObjSize")).round() as u64,
+        "data_bytes": field("dataSize"),
+        "storage_bytes": field("storageSize"),
+        "indexes": field("indexes"),
+        "total_index_bytes": field("indexSize"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_collection() {
+        let stats = vec![json!({
+            "ns": "app.users",
+            "storageStats": {
+                "count": 1200,
+                "size": 600000,
+                "avgObjSize": 500,
+                "storageSize": 262144,
+                "totalIndexSize": 73728,
+                "indexSizes": {"_id_": 36864, "email_1": 36864},
+                "capped": false
+            }
+        })];
+        let summary = summarize_collection("users", &stats);
+        assert_eq!(summary["estimated_count"], 1200);
+        assert_eq!(summary["avg_document_bytes"], 500);
+        assert_eq!(summary["storage_bytes"], 262144);
+        assert_eq!(summary["index_sizes"]["email_1"], 36864);
+        assert_eq!(summary["capped"], false);
+    }
+
+    #[test]
+    fn test_summarize_collection_adds_shards() {
+        let shard = |count: u64, size: u64| {
+            json!({"storageStats": {
+                "count": count,
+                "size": size,
+                "storageSize": size,
+                "totalIndexSize": 100,
+                "indexSizes": {"_id_": 100},
+                "capped": false
+            }})
+        };
+        let summary = summarize_collection("orders", &[shard(100, 1000), shard(300, 5000)]);
+        assert_eq!(summary["estimated_count"], 400);
+        assert_eq!(summary["avg_document_bytes"], 15);
+        assert_eq!(summary["index_sizes"]["_id_"], 200);
+        assert_eq!(summary["shards"], 2);
+    }
+
+    #[test]
+    fn test_summarize_database() {
+        let stats = json!({
+            "db": "app",
+            "collections": 12,
+            "views": 1,
+            "objects": 50000,
+            "avgObjSize": 412.7,
+            "dataSize": 20635000.0,
+            "storageSize": 8192000,
+            "indexes": 20,
+            "indexSize": 1048576,
+            "ok": 1
+        });
+        let summary = summarize_database("app", &stats);
+        assert_eq!(summary["collections"], 12);
+        assert_eq!(summary["avg_document_bytes"], 413);
+        assert_eq!(summary["data_bytes"], 20635000);
+    }
+}
@@ -0,0 +1,202 @@
+//! TLS settings for direct connections.
+//!
+//! Structured alternative to `tls=true&tlsCAFile=...` URL options, including
+//! client certificates for mutual TLS and X.509 authentication.
+
+use anyhow::{Context, Result, bail};
+use mongodb::options::{ClientOptions, Tls, TlsOptions};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// TLS settings (`tls` section of a direct connection). TLS is enabled when present.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// CA certificates (PEM) used to verify the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<String>,
+    /// Client certificate and private key in one PEM file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert_key_file: Option<String>,
+    /// Client certificate (PEM), used together with `key_file`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert_file: Option<String>,
+    /// Client private key (PEM), used together with `cert_file`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<String>,
+    /// Accept server certificates issued for another hostname (openssl-tls builds only)
+    #[serde(default)]
+    pub allow_invalid_hostnames: bool,
+}
+
+impl TlsConfig {
+    /// Whether a client certificate is configured
+    pub const fn has_client_certificate(&self) -> bool {
+        self.cert_key_file.is_some() || self.cert_file.is_some()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.cert_key_file.is_some() && (self.cert_file.is_some() || self.key_file.is_some()) {
+            bail!("Set either tls.cert_key_file or tls.cert_file and tls.key_file, not both");
+        }
+        if self.cert_file.is_some() != self.key_file.is_some() {
+            bail!("tls.cert_file and tls.key_file must be set together");
+        }
+        if self.allow_invalid_hostnames && !cfg!(feature = "openssl-tls") {
+            bail!("tls.allow_invalid_hostnames requires a build with the openssl-tls feature");
+        }
+        Ok(())
+    }
+
+    /// Enable TLS on client options, on top of any TLS options from the URL.
+    /// The returned file, if any, must live until the client has been created.
+    pub fn apply(&self, client_options: &mut ClientOptions) -> Result<Option<CombinedPem>> {
+        self.validate()?;
+
+        let mut options = match client_options.tls.take() {
+            Some(Tls::Enabled(options)) => options,
+            _ => TlsOptions::default(),
+        };
+
+        if let Some(path) = &self.ca_file {
+            options.ca_file_path = Some(readable(path, "tls.ca_file")?);
+        }
+
+        let combined = match (&self.cert_key_file, &self.cert_file, &self.key_file) {
+            (Some(path), _, _) => {
+                options.cert_key_file_path = Some(readable(path, "tls.cert_key_file")?);
+                None
+            }
+            (None, Some(cert), Some(key)) => {
+                let combined = CombinedPem::write(cert, key)?;
+                options.cert_key_file_path = Some(combined.path.clone());
+                Some(combined)
+            }
+            _ => None,
+        };
+
+        #[cfg(feature = "openssl-tls")]
+        if self.allow_invalid_hostnames {
+            options.allow_invalid_hostnames = Some(true);
+        }
+
+        client_options.tls = Some(Tls::Enabled(options));
+        Ok(combined)
+    }
+}
+
+fn readable(path: &str, field: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(path);
+    std::fs::metadata(&path)
+        .with_context(|| format!("Cannot read {field} '{}'", path.display()))?;
+    Ok(path)
+}
+
+/// A private temporary file holding a certificate followed by its key, as the
+/// driver expects. The driver reads it when the client is created; the file is
+/// removed on drop.
+pub struct CombinedPem {
+    path: PathBuf,
+}
+
+impl CombinedPem {
+    fn write(cert_file: &str, key_file: &str) -> Result<Self> {
+        let cert = std::fs::read_to_string(cert_file)
+            .with_context(|| format!("Cannot read tls.cert_file '{cert_file}'"))?;
+        let key = std::fs::read_to_string(key_file)
+            .with_context(|| format!("Cannot read tls.key_file '{key_file}'"))?;
+
+        let path = std::env::temp_dir().join(format!(
+            "ro-mongodb-mcp-rs-{}-{}.pem",
+            std::process::id(),
+            unique_suffix()
+        ));
+        let combined = Self { path };
+        write_private(&combined.path, &format!("{}\n{}", cert.trim_end(), key))?;
+        Ok(combined)
+    }
+}
+
+impl Drop for CombinedPem {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn unique_suffix() -> u64 {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Create a file readable only by the current user
+fn write_private(path: &Path, content: &str) -> Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    file.write_all(content.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tls_config_validate() {
+        assert!(TlsConfig::default().validate().is_ok());
+
+        let cert_without_key = TlsConfig {
+            cert_file: Some("client.crt".to_string()),
+            ..Default::default()
+        };
+        assert!(cert_without_key.validate().is_err());
+
+        let both_forms = TlsConfig {
+            cert_key_file: Some("client.pem".to_string()),
+            cert_file: Some("client.crt".to_string()),
+            key_file: Some("client.key".to_string()),
+            ..Default::default()
+        };
+        assert!(both_forms.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tls_apply_combines_cert_and_key() {
+        let dir =
+            std::env::temp_dir().join(format!("ro-mongodb-mcp-rs-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cert = dir.join("client.crt");
+        let key = dir.join("client.key");
+        std::fs::write(&cert, "CERT\n").unwrap();
+        std::fs::write(&key, "KEY\n").unwrap();
+
+        let tls = TlsConfig {
+            cert_file: Some(cert.to_string_lossy().into_owned()),
+            key_file: Some(key.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let mut options = ClientOptions::parse("mongodb://localhost:27017")
+            .await
+            .unwrap();
+        let combined = tls.apply(&mut options).unwrap().unwrap();
+
+        let Some(Tls::Enabled(tls_options)) = &options.tls else {
+            panic!("TLS not enabled");
+        };
+        let path = tls_options.cert_key_file_path.clone().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "CERT\nKEY\n");
+
+        drop(combined);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -4,7 +4,9 @@
 use schemars::JsonSchema;
 use serde::Deserialize;
 
+use crate::explain::ExplainVerbosity;
 use crate::mongodb::QueryOperation;
+use crate::read_preference::ReadPreferenceMode;
 
 /// Parameters for get_data_model tool
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -13,11 +15,60 @@ pub struct GetDataModelParams {
     pub connection_name: String,
 }
 
+/// Parameters for check_connection tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CheckConnectionParams {
+    /// The connection name from list_connections. Case-sensitive.
+    pub connection_name: String,
+    /// Database to look for instead of the connection's default. Must be allowed by the
+    /// connection (see list_databases).
+    #[serde(default)]
+    pub database_name: Option<String>,
+}
+
+/// Parameters for list_databases tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ListDatabasesParams {
+    /// The connection name from list_connections. Case-sensitive.
+    pub connection_name: String,
+}
+
 /// Parameters for list_collections tool
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct ListCollectionsParams {
     /// The connection name from list_connections. Case-sensitive.
     pub connection_name: String,
+    /// Database to use instead of the connection's default. Must be allowed by the
+    /// connection (see list_databases).
+    #[serde(default)]
+    pub database_name: Option<String>,
+}
+
+/// Parameters for list_indexes tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ListIndexesParams {
+    /// The connection name from list_connections. Case-sensitive.
+    pub connection_name: String,
+    /// Database to use instead of the connection's default. Must be allowed by the
+    /// connection (see list_databases).
+    #[serde(default)]
+    pub database_name: Option<String>,
+    /// The collection name from list_collections. Case-sensitive.
+    pub collection_name: String,
+}
+
+/// Parameters for collection_stats tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CollectionStatsParams {
+    /// The connection name from list_connections. Case-sensitive.
+    pub connection_name: String,
+    /// Database to use instead of the connection's default. Must be allowed by the
+    /// connection (see list_databases).
+    #[serde(default)]
+    pub database_name: Option<String>,
+    /// The collection name from list_collections. Omit for database-wide statistics.
+    #[serde(default)]
+    pub collection_name: Option<String>,
 }
 
 /// The query operation type
@@ -62,6 +113,10 @@ impl From<&QueryOperationType> for QueryOperation {
 pub struct QueryMongodbParams {
     /// The connection name from list_connections. Case-sensitive.
     pub connection_name: String,
+    /// Database to use instead of the connection's default. Must be allowed by the
+    /// connection (see list_databases).
+    #[serde(default)]
+    pub database_name: Option<String>,
     /// The collection name from list_collections. Case-sensitive.
     pub collection_name: String,
     /// The query operation type: find, aggregate, countDocuments, distinct
@@ -80,6 +135,76 @@ pub struct QueryMongodbParams {
     /// (distinct) REQUIRED. Field to get unique values from. Query param becomes the filter.
     #[serde(default)]
     pub distinct_field: Option<String>,
+    /// Time limit in seconds (default: the connection's default_timeout_secs, usually 30).
+    /// Capped by the connection's max_timeout_secs. Also enforced by MongoDB via maxTimeMS.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Replica set members that may serve this query, overriding the connection's
+    /// read_preference. Prefer secondaryPreferred for heavy analytical queries.
+    #[serde(default)]
+    pub read_preference: Option<ReadPreferenceMode>,
+}
+
+/// Parameters for explain_query tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExplainQueryParams {
+    /// The connection name from list_connections. Case-sensitive.
+    pub connection_name: String,
+    /// Database to use instead of the connection's default. Must be allowed by the
+    /// connection (see list_databases).
+    #[serde(default)]
+    pub database_name: Option<String>,
+    /// The collection name from list_collections. Case-sensitive.
+    pub collection_name: String,
+    /// The query operation type: find, aggregate, countDocuments, distinct
+    pub operation: QueryOperationType,
+    /// JSON string: filter {} for find/countDocuments/distinct, pipeline [] for aggregate.
+    pub query: String,
+    /// (find only) Maximum number of documents to return.
+    #[serde(default)]
+    pub limit: Option<u32>,
+    /// (find only) Sort order as JSON object. Example: {"createdAt": -1}.
+    #[serde(default)]
+    pub sort: Option<String>,
+    /// (find only) Fields to include/exclude as JSON object.
+    #[serde(default)]
+    pub projection: Option<String>,
+    /// (distinct) REQUIRED. Field to get unique values from. Query param becomes the filter.
+    #[serde(default)]
+    pub distinct_field: Option<String>,
+    /// Explain verbosity: queryPlanner, executionStats (default), allPlansExecution
+    #[serde(default)]
+    pub verbosity: ExplainVerbosity,
+    /// Time limit in seconds (default: the connection's default_timeout_secs, usually 30).
+    /// Capped by the connection's max_timeout_secs. Also enforced by MongoDB via maxTimeMS.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Parameters for infer_schema tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct InferSchemaParams {
+    /// The connection name from list_connections. Case-sensitive.
+    pub connection_name: String,
+    /// Database to use instead of the connection's default. Must be allowed by the
+    /// connection (see list_databases).
+    #[serde(default)]
+    pub database_name: Option<String>,
+    /// The collection name from list_collections. Case-sensitive.
+    pub collection_name: String,
+    /// Number of documents to sample (default 100, max 1000).
+    #[serde(default)]
+    pub sample_size: Option<u32>,
+    /// Sample again instead of returning a cached schema.
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// Parameters for get_next_page tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetNextPageParams {
+    /// The next_page_token from a truncated query_mongodb or run_saved_query result.
+    pub page_token: String,
 }
 
 /// Parameters for save_query tool
@@ -134,6 +259,10 @@ pub struct DeleteSavedQueryParams {
 pub struct RunSavedQueryParams {
     /// The connection name from list_connections. Case-sensitive.
     pub connection_name: String,
+    /// Database to use instead of the connection's default. Must be allowed by the
+    /// connection (see list_databases).
+    #[serde(default)]
+    pub database_name: Option<String>,
     /// The name of the saved query to execute.
     pub query_name: String,
     /// Variables: {"userId": "123"} replaces {{userId}} → 123 or "{{userId}}" → "123".
@@ -152,4 +281,19 @@ pub struct RunSavedQueryParams {
     /// (distinct only) Override: Field to get unique values from.
     #[serde(default)]
     pub distinct_field: Option<String>,
+    /// Time limit in seconds (default: the connection's default_timeout_secs, usually 30).
+    /// Capped by the connection's max_timeout_secs. Also enforced by MongoDB via maxTimeMS.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Parameters for query_history tool
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct QueryHistoryParams {
+    /// Only return queries run on this connection. Omit for all connections.
+    #[serde(default)]
+    pub connection_name: Option<String>,
+    /// Number of most recent entries to return (default 20, max 200).
+    #[serde(default)]
+    pub limit: Option<u32>,
 }
@@ -0,0 +1,112 @@
+//! Read-only enforcement for query filters and aggregation pipelines.
+//!
+//! Queries are inspected as parsed JSON before they reach either backend, so the
+//! same rules apply to mongosh execution and to the native driver.
+
+use anyhow::{Result, bail};
+use serde_json::Value;
+
+/// Pipeline stages that write to the database
+const WRITE_STAGES: &[&str] = &["$out", "$merge"];
+
+/// Operators that execute server-side JavaScript
+const JS_OPERATORS: &[&str] = &["$where", "$function", "$accumulator"];
+
+/// Reject write stages and server-side JavaScript anywhere in a query value.
+///
+/// The whole document tree is walked, so stages nested in `$facet`, `$lookup`
+/// or `$unionWith` sub-pipelines are caught as well. `what` names the value
+/// in error messages (e.g. "query", "projection").
+pub fn validate_read_only(value: &Value, what: &str) -> Result<()> {
+    check_value(value, what)
+}
+
+fn check_value(value: &Value, path: &str) -> Result<()> {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                if WRITE_STAGES.contains(&key.as_str()) {
+                    bail!(
+                        "Write stage '{key}' is not allowed (found at {path}). \
+                        This server is read-only: remove $out/$merge stages from the pipeline."
+                    );
+                }
+                if JS_OPERATORS.contains(&key.as_str()) {
+                    bail!(
+                        "Operator '{key}' is not allowed (found at {path}). \
+                        Server-side JavaScript ($where, $function, $accumulator) is disabled."
+                    );
+                }
+                check_value(child, &format!("{path}.{key}"))?;
+            }
+            Ok(())
+        }
+        Value::Array(items) => {
+            for (i, child) in items.iter().enumerate() {
+                check_value(child, &format!("{path}[{i}]"))?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_allows_read_only_pipeline() {
+        let pipeline = json!([
+            {"$match": {"status": "active"}},
+            {"$group": {"_id": "$country", "count": {"$sum": 1}}},
+            {"$sort": {"count": -1}}
+        ]);
+        assert!(validate_read_only(&pipeline, "query").is_ok());
+    }
+
+    #[test]
+    fn test_rejects_top_level_write_stages() {
+        let out = json!([{"$match": {}}, {"$out": "copy"}]);
+        let err = validate_read_only(&out, "query").unwrap_err().to_string();
+        assert!(err.contains("$out"));
+        assert!(err.contains("query[1]"));
+
+        let merge = json!([{"$merge": {"into": "copy"}}]);
+        assert!(validate_read_only(&merge, "query").is_err());
+    }
+
+    #[test]
+    fn test_rejects_nested_write_stages() {
+        let facet = json!([{"$facet": {"a": [{"$match": {}}], "b": [{"$out": "x"}]}}]);
+        assert!(validate_read_only(&facet, "query").is_err());
+
+        let lookup = json!([{"$lookup": {
+            "from": "orders",
+            "as": "orders",
+            "pipeline": [{"$merge": {"into": "x"}}]
+        }}]);
+        assert!(validate_read_only(&lookup, "query").is_err());
+    }
+
+    #[test]
+    fn test_rejects_javascript_operators() {
+        let filter = json!({"$where": "this.a > 1"});
+        assert!(validate_read_only(&filter, "query").is_err());
+
+        let projection =
+            json!({"x": {"$function": {"body": "return 1", "args": [], "lang": "js"}}});
+        assert!(validate_read_only(&projection, "projection").is_err());
+
+        let group = json!([{"$group": {"_id": null, "v": {"$accumulator": {}}}}]);
+        assert!(validate_read_only(&group, "query").is_err());
+    }
+
+    #[test]
+    fn test_allows_field_values_named_like_operators() {
+        // Only object keys are operators; string values are plain data
+        let filter = json!({"note": "$out", "tags": ["$where"]});
+        assert!(validate_read_only(&filter, "query").is_ok());
+    }
+}